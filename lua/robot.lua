@@ -0,0 +1,11 @@
+local robot_api = require("robot_api")
+
+for _ = 1, 100 do
+    robot_api.move_forward()
+    robot_api.move_forward()
+    robot_api.turn_left()
+    robot_api.move_forward()
+    robot_api.turn_left()
+    robot_api.turn_left()
+    robot_api.move_forward()
+end
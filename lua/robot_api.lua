@@ -0,0 +1,122 @@
+---@meta robot_api
+
+---A simple robot that can be moced across a 2D-plane and draw lines
+---
+---Once upon a time there was a lonely robot with the serial number `#C0D1E`. …
+---@class robot_api
+local robot_api = {}
+
+---The position of a tile
+---
+---The tile at the origin is at `x = 0` and `y = 0`. `x` increases towards the east, `y` increases towards the north.
+---@class Position
+---@field x integer Column of the tile, increasing towards the east. Ranges from `-2147483648` to `2147483646`.
+---@field y integer Row of the tile, increasing towards the north. Ranges from `-2147483648` to `2147483646`.
+
+---The direction the robot is facing
+---
+---The robot can face any of the eight directions of the compass rose.
+---@enum Orientation
+robot_api.Orientation = {
+    ---Facing east
+    EAST = "east",
+    ---Facing north east
+    NORTH_EAST = "north east",
+    ---Facing north
+    NORTH = "north",
+    ---Facing north west
+    NORTH_WEST = "north west",
+    ---Facing west
+    WEST = "west",
+    ---Facing south west
+    SOUTH_WEST = "south west",
+    ---Facing south
+    SOUTH = "south",
+    ---Facing south east
+    SOUTH_EAST = "south east",
+}
+
+---What the robot knows about a tile
+---
+---The properties of a tile which matter for moving the robot onto it.
+---@class Tile
+---@field position Position Position of the tile; it may lie outside of the world.
+---@field blocked boolean Whether the tile cannot be entered; tiles outside of the world are always blocked.
+---@field wall boolean Whether a wall between the robot and the tile prevents the robot from moving there.
+---@field goal boolean Whether the tile is a goal of the level.
+
+---Makes the robot move to the next tile in its current orientation
+---
+---Fails with
+---- `blocked`: There's a wall or a blocked tile in the way
+---- `out of bounds`: The robot would leave the world
+---@param steps? integer Number of tiles to move; the robot doesn't move at all if any of them is blocked. Ranges from `1` to `999`. Defaults to `1`.
+function robot_api.move_forward(steps) end
+
+---Turns the robot 45° in a counter-clockwise direction
+---@param times? integer Number of 45° turns to make. Ranges from `1` to `999`. Defaults to `1`.
+function robot_api.turn_left(times) end
+
+---Turns the robot 45° in a clockwise direction
+---@param times? integer Number of 45° turns to make. Ranges from `1` to `999`. Defaults to `1`.
+function robot_api.turn_right(times) end
+
+---Tells where the robot is standing
+---
+---Tells the position of the tile the robot is standing on
+---@return Position return_ Position of the tile the robot is standing on.
+function robot_api.position() end
+
+---Tells which direction the robot is facing
+---
+---Tells which direction the robot is facing and would move to
+---@return Orientation return_ The direction the robot is facing.
+function robot_api.orientation() end
+
+---Looks at the tile in front of the robot
+---
+---Looks at the next tile in the robot's current orientation, which it would move to
+---@return Tile return_ The tile in front of the robot.
+function robot_api.tile_ahead() end
+
+---Tells which lines have been drawn on a tile
+---
+---Tells the directions in which the lines drawn by the robot leave the center of a tile. Lines which only cross a corner of the tile are left out.
+---
+---Fails with
+---- `out of bounds`: The tile lies outside of the world
+---@param position Position Position of the tile to look at.
+---@return Orientation[] return_ Directions in which lines leave the center of the tile, counter-clockwise starting in the east.
+function robot_api.line_pattern(position) end
+
+---Registers a function to be called on the event: The robot couldn't move forward
+---
+---The robot couldn't move forward as a wall, a blocked tile or the edge of the world was in the way
+---The handler receives `position`: The tile the robot is standing on, as it didn't move at all.
+---@param handler fun(position: Position)
+---@return fun(position: Position)
+function robot_api.on_bumped_into_wall(handler) end
+
+---Registers a function to be called on the event: The robot entered a goal tile
+---
+---The robot entered a goal tile of the level; this happens each time a goal is entered
+---The handler receives `position`: Position of the goal tile the robot has entered.
+---@param handler fun(position: Position)
+---@return fun(position: Position)
+function robot_api.on_reached_goal(handler) end
+
+---Registers a function to be called on the event: A key has been pressed
+---
+---A key has been pressed while the window of the engine had the focus
+---The handler receives `key`: The character of the key (e.g. `a`) or the name of a special key (e.g. `ArrowUp`).
+---@param handler fun(key: string)
+---@return fun(key: string)
+function robot_api.on_key_pressed(handler) end
+
+---Waits for events and calls their handlers
+---
+---Events are also handled after each call of another function of this module. Without `seconds`, this function waits until the script gets stopped.
+---@param seconds? number Number of seconds to wait for, at least `0`.
+function robot_api.wait_for_events(seconds) end
+
+return robot_api
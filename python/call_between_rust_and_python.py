@@ -1,5 +1,5 @@
-from rust_py_module import RustStruct, rust_function, rotate_cube, move_forward, turn_left, turn_right  # type: ignore FIXME the IDE should see the native API somehow
-import time
+from rust_py_module import RustStruct, rust_function, rotate_cube  # type: ignore FIXME the IDE should see the native API somehow
+from robot_api import move_forward, turn_left, turn_right
 
 
 class PythonPerson:
@@ -22,18 +22,11 @@ print("Hello World!")
 
 for x in range(100):
     move_forward()
-    time.sleep(1.0)
     move_forward()
-    time.sleep(1.0)
     turn_left()
-    time.sleep(1.0)
     move_forward()
-    time.sleep(1.0)
     turn_left()
-    time.sleep(1.0)
     turn_left()
-    time.sleep(1.0)
     move_forward()
-    time.sleep(1.0)
     # rotate_cube(x)
     # time.sleep(0.020)
@@ -1,9 +1,326 @@
-def move_forward():
-	pass
+"""A simple robot that can be moced across a 2D-plane and draw lines
 
-def turn_left():
-	pass
+Once upon a time there was a lonely robot with the serial number `#C0D1E`. …
 
-def turn_right():
-	pass
+Within the engine, this module uses the native module `_robot_api`. Elsewhere, it calls the HTTP control server of a running engine, which is expected at `http://localhost:8000` unless the environment variable `GAM3DU_URL` tells otherwise. Events will only be delivered within the engine.
+"""
 
+from __future__ import annotations
+from enum import Enum
+
+TYPE_CHECKING = False
+if TYPE_CHECKING:
+    from typing import Callable
+
+
+class Position:
+    """The position of a tile
+
+    The tile at the origin is at `x = 0` and `y = 0`. `x` increases towards the east, `y` increases towards the north.
+
+    Attributes:
+        x: Column of the tile, increasing towards the east. Ranges from `-2147483648` to `2147483646`.
+        y: Row of the tile, increasing towards the north. Ranges from `-2147483648` to `2147483646`.
+    """
+
+    x: int
+    y: int
+
+    def __init__(self, x: int, y: int) -> None:
+        self.x = x
+        self.y = y
+
+    def __repr__(self) -> str:
+        return f"Position(x={self.x!r}, y={self.y!r})"
+
+    def __eq__(self, other: object) -> bool:
+        return isinstance(other, Position) and self.x == other.x and self.y == other.y
+
+
+class Orientation(Enum):
+    """The direction the robot is facing
+
+    The robot can face any of the eight directions of the compass rose.
+    """
+
+    EAST = "east"
+    """Facing east"""
+
+    NORTH_EAST = "north east"
+    """Facing north east"""
+
+    NORTH = "north"
+    """Facing north"""
+
+    NORTH_WEST = "north west"
+    """Facing north west"""
+
+    WEST = "west"
+    """Facing west"""
+
+    SOUTH_WEST = "south west"
+    """Facing south west"""
+
+    SOUTH = "south"
+    """Facing south"""
+
+    SOUTH_EAST = "south east"
+    """Facing south east"""
+
+
+class Tile:
+    """What the robot knows about a tile
+
+    The properties of a tile which matter for moving the robot onto it.
+
+    Attributes:
+        position: Position of the tile; it may lie outside of the world.
+        blocked: Whether the tile cannot be entered; tiles outside of the world are always blocked.
+        wall: Whether a wall between the robot and the tile prevents the robot from moving there.
+        goal: Whether the tile is a goal of the level.
+    """
+
+    position: Position
+    blocked: bool
+    wall: bool
+    goal: bool
+
+    def __init__(self, position: Position, blocked: bool, wall: bool, goal: bool) -> None:
+        self.position = position
+        self.blocked = blocked
+        self.wall = wall
+        self.goal = goal
+
+    def __repr__(self) -> str:
+        return f"Tile(position={self.position!r}, blocked={self.blocked!r}, wall={self.wall!r}, goal={self.goal!r})"
+
+    def __eq__(self, other: object) -> bool:
+        return isinstance(other, Tile) and self.position == other.position and self.blocked == other.blocked and self.wall == other.wall and self.goal == other.goal
+
+
+class RobotError(Exception):
+    """Base class of all errors reported by the robot api"""
+
+
+class RobotBlockedError(RobotError):
+    """There's a wall or a blocked tile in the way
+
+    The robot cannot move forward as there's a wall or a blocked tile in its way. Turn the robot or check the level for another path.
+    """
+
+
+class RobotOutOfBoundsError(RobotError):
+    """The robot would leave the world
+
+    The robot cannot move forward as it's standing at the edge of the world. Turn the robot before moving on.
+    """
+
+
+_ENUMS = (Orientation,)
+_ERRORS = {"blocked": RobotBlockedError, "out of bounds": RobotOutOfBoundsError}
+_FIELDS = {
+    Position: {"x": ("x", int), "y": ("y", int)},
+    Tile: {"position": ("position", Position), "blocked": ("blocked", bool), "wall": ("wall", bool), "goal": ("goal", bool)},
+}
+
+
+def move_forward(steps: int = 1) -> None:
+    """Makes the robot move to the next tile in its current orientation
+
+    Args:
+        steps: Number of tiles to move; the robot doesn't move at all if any of them is blocked. Ranges from `1` to `999`. Defaults to `1`.
+
+    Raises:
+        RobotBlockedError: There's a wall or a blocked tile in the way
+        RobotOutOfBoundsError: The robot would leave the world
+    """
+    return _call("move forward", {"steps": _to_json(steps, int)}, None)
+
+
+def turn_left(times: int = 1) -> None:
+    """Turns the robot 45° in a counter-clockwise direction
+
+    Args:
+        times: Number of 45° turns to make. Ranges from `1` to `999`. Defaults to `1`.
+    """
+    return _call("turn left", {"times": _to_json(times, int)}, None)
+
+
+def turn_right(times: int = 1) -> None:
+    """Turns the robot 45° in a clockwise direction
+
+    Args:
+        times: Number of 45° turns to make. Ranges from `1` to `999`. Defaults to `1`.
+    """
+    return _call("turn right", {"times": _to_json(times, int)}, None)
+
+
+def position() -> Position:
+    """Tells where the robot is standing
+
+    Tells the position of the tile the robot is standing on
+
+    Returns:
+        Position: Position of the tile the robot is standing on.
+    """
+    return _call("position", {}, Position)
+
+
+def orientation() -> Orientation:
+    """Tells which direction the robot is facing
+
+    Tells which direction the robot is facing and would move to
+
+    Returns:
+        Orientation: The direction the robot is facing.
+    """
+    return _call("orientation", {}, Orientation)
+
+
+def tile_ahead() -> Tile:
+    """Looks at the tile in front of the robot
+
+    Looks at the next tile in the robot's current orientation, which it would move to
+
+    Returns:
+        Tile: The tile in front of the robot.
+    """
+    return _call("tile ahead", {}, Tile)
+
+
+def line_pattern(position: Position) -> list[Orientation]:
+    """Tells which lines have been drawn on a tile
+
+    Tells the directions in which the lines drawn by the robot leave the center of a tile. Lines which only cross a corner of the tile are left out.
+
+    Args:
+        position: Position of the tile to look at.
+
+    Returns:
+        list[Orientation]: Directions in which lines leave the center of the tile, counter-clockwise starting in the east.
+
+    Raises:
+        RobotOutOfBoundsError: The tile lies outside of the world
+    """
+    return _call("line pattern", {"position": _to_json(position, Position)}, [Orientation])
+
+
+def on_bumped_into_wall(handler: Callable[[Position], None]) -> Callable[[Position], None]:
+    """Registers the decorated function as handler of the event: The robot couldn't move forward
+
+    The robot couldn't move forward as a wall, a blocked tile or the edge of the world was in the way
+
+    The handler receives:
+        position: The tile the robot is standing on, as it didn't move at all.
+    """
+    return handler
+
+
+def on_reached_goal(handler: Callable[[Position], None]) -> Callable[[Position], None]:
+    """Registers the decorated function as handler of the event: The robot entered a goal tile
+
+    The robot entered a goal tile of the level; this happens each time a goal is entered
+
+    The handler receives:
+        position: Position of the goal tile the robot has entered.
+    """
+    return handler
+
+
+def on_key_pressed(handler: Callable[[str], None]) -> Callable[[str], None]:
+    """Registers the decorated function as handler of the event: A key has been pressed
+
+    A key has been pressed while the window of the engine had the focus
+
+    The handler receives:
+        key: The character of the key (e.g. `a`) or the name of a special key (e.g. `ArrowUp`).
+    """
+    return handler
+
+
+def wait_for_events(seconds: float | None = None) -> None:
+    """Waits for events and calls their handlers
+
+    Events are also handled after each call of another function of this module. Without `seconds`, this function waits until the script gets stopped.
+
+    Args:
+        seconds: Number of seconds to wait for, at least `0`.
+    """
+    import time
+    if seconds is not None:
+        time.sleep(seconds)
+        return
+    # no events arrive outside of the engine; wait until being interrupted
+    while True:
+        time.sleep(60)
+
+
+def _call(function, arguments, returns):
+    # only reached outside of the engine, as the native module replaces all functions
+    import json
+    import os
+    import urllib.error
+    import urllib.parse
+    import urllib.request
+
+    base_url = os.environ.get("GAM3DU_URL", "http://localhost:8000").rstrip("/")
+    path = urllib.parse.quote("robot") + "/" + urllib.parse.quote(function)
+    request = urllib.request.Request(
+        f"{base_url}/{path}",
+        data=json.dumps(arguments).encode(),
+        headers={"Content-Type": "application/json"},
+        method="POST",
+    )
+    try:
+        with urllib.request.urlopen(request) as response:
+            reply = json.loads(response.read())
+    except urllib.error.HTTPError as error:
+        try:
+            failure = json.loads(error.read())["error"]
+        except (ValueError, KeyError, TypeError):
+            raise RobotError(f"the engine failed with status {error.code}") from None
+        raise _ERRORS.get(failure["kind"], RobotError)(failure["message"]) from None
+    except urllib.error.URLError as error:
+        raise RobotError(f"the engine is not reachable at {base_url}: {error.reason}") from None
+
+    if returns is None:
+        return None
+    return _from_json(reply["result"], returns)
+
+
+def _to_json(value, hint):
+    if isinstance(hint, list):
+        return [_to_json(element, hint[0]) for element in value]
+    if hint in _ENUMS:
+        # accepts members as well as their values
+        return hint(value).value
+    fields = _FIELDS.get(hint)
+    if fields is not None:
+        def field(attribute):
+            return value[attribute] if isinstance(value, dict) else getattr(value, attribute)
+
+        return {
+            name: _to_json(field(attribute), field_hint)
+            for name, (attribute, field_hint) in fields.items()
+        }
+    return value
+
+
+def _from_json(value, hint):
+    if isinstance(hint, list):
+        return [_from_json(element, hint[0]) for element in value]
+    if hint in _ENUMS:
+        return hint(value)
+    fields = _FIELDS.get(hint)
+    if fields is not None:
+        return hint(**{
+            attribute: _from_json(value[name], field_hint)
+            for name, (attribute, field_hint) in fields.items()
+        })
+    return value
+
+
+try:
+    from _robot_api import *  # noqa: F403
+except ImportError:
+    pass
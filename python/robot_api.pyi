@@ -0,0 +1,204 @@
+"""A simple robot that can be moced across a 2D-plane and draw lines
+
+Once upon a time there was a lonely robot with the serial number `#C0D1E`. …
+"""
+
+from enum import Enum
+from typing import Callable
+
+class Position:
+    """The position of a tile
+
+    The tile at the origin is at `x = 0` and `y = 0`. `x` increases towards the east, `y` increases towards the north.
+
+    Attributes:
+        x: Column of the tile, increasing towards the east. Ranges from `-2147483648` to `2147483646`.
+        y: Row of the tile, increasing towards the north. Ranges from `-2147483648` to `2147483646`.
+    """
+
+    x: int
+    y: int
+
+    def __init__(self, x: int, y: int) -> None: ...
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+
+class Orientation(Enum):
+    """The direction the robot is facing
+
+    The robot can face any of the eight directions of the compass rose.
+    """
+
+    EAST = "east"
+    """Facing east"""
+
+    NORTH_EAST = "north east"
+    """Facing north east"""
+
+    NORTH = "north"
+    """Facing north"""
+
+    NORTH_WEST = "north west"
+    """Facing north west"""
+
+    WEST = "west"
+    """Facing west"""
+
+    SOUTH_WEST = "south west"
+    """Facing south west"""
+
+    SOUTH = "south"
+    """Facing south"""
+
+    SOUTH_EAST = "south east"
+    """Facing south east"""
+
+class Tile:
+    """What the robot knows about a tile
+
+    The properties of a tile which matter for moving the robot onto it.
+
+    Attributes:
+        position: Position of the tile; it may lie outside of the world.
+        blocked: Whether the tile cannot be entered; tiles outside of the world are always blocked.
+        wall: Whether a wall between the robot and the tile prevents the robot from moving there.
+        goal: Whether the tile is a goal of the level.
+    """
+
+    position: Position
+    blocked: bool
+    wall: bool
+    goal: bool
+
+    def __init__(self, position: Position, blocked: bool, wall: bool, goal: bool) -> None: ...
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+
+class RobotError(Exception):
+    """Base class of all errors reported by the robot api"""
+
+class RobotBlockedError(RobotError):
+    """There's a wall or a blocked tile in the way
+
+    The robot cannot move forward as there's a wall or a blocked tile in its way. Turn the robot or check the level for another path.
+    """
+
+class RobotOutOfBoundsError(RobotError):
+    """The robot would leave the world
+
+    The robot cannot move forward as it's standing at the edge of the world. Turn the robot before moving on.
+    """
+
+def move_forward(steps: int = ...) -> None:
+    """Makes the robot move to the next tile in its current orientation
+
+    Args:
+        steps: Number of tiles to move; the robot doesn't move at all if any of them is blocked. Ranges from `1` to `999`. Defaults to `1`.
+
+    Raises:
+        RobotBlockedError: There's a wall or a blocked tile in the way
+        RobotOutOfBoundsError: The robot would leave the world
+    """
+    ...
+
+def turn_left(times: int = ...) -> None:
+    """Turns the robot 45° in a counter-clockwise direction
+
+    Args:
+        times: Number of 45° turns to make. Ranges from `1` to `999`. Defaults to `1`.
+    """
+    ...
+
+def turn_right(times: int = ...) -> None:
+    """Turns the robot 45° in a clockwise direction
+
+    Args:
+        times: Number of 45° turns to make. Ranges from `1` to `999`. Defaults to `1`.
+    """
+    ...
+
+def position() -> Position:
+    """Tells where the robot is standing
+
+    Tells the position of the tile the robot is standing on
+
+    Returns:
+        Position: Position of the tile the robot is standing on.
+    """
+    ...
+
+def orientation() -> Orientation:
+    """Tells which direction the robot is facing
+
+    Tells which direction the robot is facing and would move to
+
+    Returns:
+        Orientation: The direction the robot is facing.
+    """
+    ...
+
+def tile_ahead() -> Tile:
+    """Looks at the tile in front of the robot
+
+    Looks at the next tile in the robot's current orientation, which it would move to
+
+    Returns:
+        Tile: The tile in front of the robot.
+    """
+    ...
+
+def line_pattern(position: Position) -> list[Orientation]:
+    """Tells which lines have been drawn on a tile
+
+    Tells the directions in which the lines drawn by the robot leave the center of a tile. Lines which only cross a corner of the tile are left out.
+
+    Args:
+        position: Position of the tile to look at.
+
+    Returns:
+        list[Orientation]: Directions in which lines leave the center of the tile, counter-clockwise starting in the east.
+
+    Raises:
+        RobotOutOfBoundsError: The tile lies outside of the world
+    """
+    ...
+
+def on_bumped_into_wall(handler: Callable[[Position], None]) -> Callable[[Position], None]:
+    """Registers the decorated function as handler of the event: The robot couldn't move forward
+
+    The robot couldn't move forward as a wall, a blocked tile or the edge of the world was in the way
+
+    The handler receives:
+        position: The tile the robot is standing on, as it didn't move at all.
+    """
+    ...
+
+def on_reached_goal(handler: Callable[[Position], None]) -> Callable[[Position], None]:
+    """Registers the decorated function as handler of the event: The robot entered a goal tile
+
+    The robot entered a goal tile of the level; this happens each time a goal is entered
+
+    The handler receives:
+        position: Position of the goal tile the robot has entered.
+    """
+    ...
+
+def on_key_pressed(handler: Callable[[str], None]) -> Callable[[str], None]:
+    """Registers the decorated function as handler of the event: A key has been pressed
+
+    A key has been pressed while the window of the engine had the focus
+
+    The handler receives:
+        key: The character of the key (e.g. `a`) or the name of a special key (e.g. `ArrowUp`).
+    """
+    ...
+
+def wait_for_events(seconds: float | None = ...) -> None:
+    """Waits for events and calls their handlers
+
+    Events are also handled after each call of another function of this module. Without `seconds`, this function waits until the script gets stopped.
+
+    Args:
+        seconds: Number of seconds to wait for, at least `0`.
+    """
+    ...
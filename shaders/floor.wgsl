@@ -3,12 +3,25 @@ const SQRT1_2 = sqrt(0.5);
 const BG_COLOR = vec4<f32>(0.6, 0.7, 0.8, 1.0);
 const LINE_COLOR = vec4<f32>(0.1, 0.1, 0.1, 1.0);
 const BORDER_COLOR = vec4<f32>(0.4, 0.5, 0.6, 1.0);
+const BLOCKED_COLOR = vec4<f32>(0.25, 0.25, 0.3, 1.0);
+const GOAL_COLOR = vec4<f32>(0.5, 0.8, 0.5, 1.0);
+const WALL_COLOR = vec4<f32>(0.6, 0.2, 0.1, 1.0);
 const LINE_RADIUS = 0.1;
+const WALL_WIDTH = 0.15;
+
+// must match the bits of `TileFlags`
+const FLAG_BLOCKED = 1u;
+const FLAG_GOAL = 2u;
+const FLAG_WALL_E = 16u;
+const FLAG_WALL_N = 32u;
+const FLAG_WALL_W = 64u;
+const FLAG_WALL_S = 128u;
 
 struct FloorVertex {
     @builtin(position) position: vec4<f32>,
     @location(1) tex_coord: vec2<f32>,
     @location(2) line_pattern: u32,
+    @location(3) flags: u32,
 };
 
 @group(0)
@@ -23,6 +36,7 @@ var<uniform> time_vec: vec2<u32>;
 fn vs_floor(
     @location(0) position: vec4<f32>,
     @location(2) line_pattern: u32,
+    @location(3) flags: u32,
     @builtin(vertex_index) vertex_index: u32,
     @builtin(instance_index) instance_index: u32,
 ) -> FloorVertex {
@@ -43,6 +57,7 @@ fn vs_floor(
     vertex.tex_coord = vec2(f32(is_right), f32(is_top));
     vertex.position = transform * (position + vec4(vertex.tex_coord, 0.0, 0.0));
     vertex.line_pattern = line_pattern;
+    vertex.flags = flags;
 
     return vertex;
 }
@@ -66,11 +81,24 @@ fn fs_floor_tile(vertex: FloorVertex) -> @location(0) vec4<f32> {
         (cc.x - cc.y > (2.0 - SQRT2 * LINE_RADIUS) && (vertex.line_pattern & (1u << 15u)) != 0);
     
     let border: bool = cc.x < -0.95 || cc.x > 0.95 || cc.y < -0.95 || cc.y > 0.95;
+
+    let wall_edge = 1.0 - WALL_WIDTH;
+    let wall: bool =
+        (cc.x > wall_edge && (vertex.flags & FLAG_WALL_E) != 0) ||
+        (cc.y > wall_edge && (vertex.flags & FLAG_WALL_N) != 0) ||
+        (cc.x < -wall_edge && (vertex.flags & FLAG_WALL_W) != 0) ||
+        (cc.y < -wall_edge && (vertex.flags & FLAG_WALL_S) != 0);
     
-    if line || line_end || line_corner {
+    if wall {
+        return WALL_COLOR;
+    } else if line || line_end || line_corner {
         return LINE_COLOR;
     } else if border {
         return BORDER_COLOR;
+    } else if (vertex.flags & FLAG_BLOCKED) != 0 {
+        return BLOCKED_COLOR;
+    } else if (vertex.flags & FLAG_GOAL) != 0 {
+        return GOAL_COLOR;
     } else {
         return BG_COLOR;
     }
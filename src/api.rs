@@ -1,11 +1,95 @@
 //! Contains all the building blocks to specify an API and perform reflection thereon.
 
-use std::{fmt::Display, ops::Range};
+use std::{
+    collections::{BTreeMap, HashSet},
+    error::Error,
+    fmt::{self, Display},
+    fs::read_to_string,
+    io,
+    ops::Range,
+    path::Path,
+};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct RichText(pub String);
+mod compatibility;
+
+pub use compatibility::{compare, Change, Compatibility};
+
+/// The locale texts are written in unless they state otherwise; used if a translation is missing
+pub const FALLBACK_LOCALE: &str = "en";
+
+/// A text meant to be read by humans (e.g. students), optionally translated into several
+/// languages.
+///
+/// Within json this is either a plain string written in the [`FALLBACK_LOCALE`] or an object
+/// mapping locales to translations, e.g. `{ "en": "Turns the robot", "de": "Dreht den Roboter" }`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RichText {
+    /// the same text for every locale
+    Plain(String),
+    /// one translation per locale; must contain the [`FALLBACK_LOCALE`]
+    Localized(BTreeMap<String, String>),
+}
+
+impl RichText {
+    /// Returns the translation for the given locale (e.g. `de` or `de-AT`).
+    ///
+    /// Falls back to the language without its region, then to the [`FALLBACK_LOCALE`] and
+    /// finally to any translation available.
+    #[must_use]
+    pub fn get(&self, locale: &str) -> &str {
+        match *self {
+            RichText::Plain(ref text) => text,
+            RichText::Localized(ref translations) => {
+                let language = locale.split(['-', '_']).next().unwrap_or(locale);
+                [locale, language, FALLBACK_LOCALE]
+                    .into_iter()
+                    .find_map(|locale| translations.get(locale))
+                    .or_else(|| translations.values().next())
+                    .map_or("", String::as_str)
+            }
+        }
+    }
+
+    /// Replaces all translations by the one for the given locale.
+    fn localize(&mut self, locale: &str) {
+        if let RichText::Localized(_) = *self {
+            *self = RichText::Plain(self.get(locale).to_owned());
+        }
+    }
+
+    fn validate(&self, violations: &mut Vec<Violation>, path: &str) {
+        let RichText::Localized(ref translations) = *self else {
+            return;
+        };
+        if !translations.contains_key(FALLBACK_LOCALE) {
+            violations.push(Violation::new(
+                path,
+                format!("the translation for the fallback locale `{FALLBACK_LOCALE}` is missing"),
+            ));
+        }
+        for locale in translations.keys() {
+            let valid = locale.split('-').all(|part| {
+                !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_alphanumeric())
+            });
+            if !valid {
+                violations.push(Violation::new(
+                    format!("{path}['{locale}']"),
+                    format!("`{locale}` is not a valid locale; use e.g. `de` or `de-AT`"),
+                ));
+            }
+        }
+    }
+}
+
+/// Shows the text in the [`FALLBACK_LOCALE`].
+impl Display for RichText {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.get(FALLBACK_LOCALE))
+    }
+}
 
 /// A technical name of an element (api, function, parameter, …).
 /// For compatibility reasons only the ASCII-characters `a-z`, `0-9` and ` ` are allowed.
@@ -17,11 +101,25 @@ pub struct RichText(pub String);
 /// to make sure they fit into the target ecosystem. This is why a space was chosen:
 /// It emphasizes best that such a name mangling _must_ occur and is a desired behavior
 /// as a space is rarely accepted within identifiers.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Identifier(pub String);
 
+impl Identifier {
+    /// Returns `true` if this identifier follows the rules stated above.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        !self.0.is_empty()
+            && self.0.split(' ').all(|word| {
+                !word.is_empty()
+                    && word
+                        .bytes()
+                        .all(|byte| byte.is_ascii_lowercase() || byte.is_ascii_digit())
+            })
+    }
+}
+
 impl Display for Identifier {
-    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         Display::fmt(&self.0, formatter)
     }
 }
@@ -34,8 +132,137 @@ pub struct Api {
     pub caption: RichText,
     /// a multi-line explanation what this api is for
     pub description: RichText,
+    /// List of all named types the functions of this API may refer to
+    #[serde(default)]
+    pub types: Vec<TypeDefinition>,
     /// List of all functions this API provides
     pub functions: Vec<FunctionDescriptor>,
+    /// List of all events the engine may raise to notify scripts using this API
+    #[serde(default)]
+    pub events: Vec<EventDescriptor>,
+}
+
+impl Api {
+    /// Reads an api from a json file and validates it.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ApiError> {
+        let source = read_to_string(path).map_err(ApiError::Io)?;
+        let api: Self = serde_json::from_str(&source).map_err(ApiError::Parse)?;
+        api.validate().map_err(ApiError::Invalid)?;
+        Ok(api)
+    }
+
+    /// Checks all the rules an api needs to obey, which cannot be expressed by the type system.
+    ///
+    /// Returns every violation found rather than stopping at the first one,
+    /// so that all of them can be fixed in one go.
+    pub fn validate(&self) -> Result<(), Vec<Violation>> {
+        let mut violations = Vec::new();
+
+        check_identifier(&mut violations, "$.name", &self.name);
+        check_texts(&mut violations, "$", &self.caption, &self.description);
+
+        let mut type_names = HashSet::new();
+        for (index, definition) in self.types.iter().enumerate() {
+            let path = format!("$.types[{index}]");
+            definition.validate(&self.types, &mut violations, &path);
+            if !type_names.insert(&definition.name) {
+                violations.push(Violation::new(
+                    format!("{path}.name"),
+                    format!("duplicate type name `{}`", definition.name),
+                ));
+            }
+        }
+
+        let mut function_names = HashSet::new();
+        for (index, function) in self.functions.iter().enumerate() {
+            let path = format!("$.functions[{index}]");
+            function.validate(&self.types, &mut violations, &path);
+            if !function_names.insert(&function.name) {
+                violations.push(Violation::new(
+                    format!("{path}.name"),
+                    format!("duplicate function name `{}`", function.name),
+                ));
+            }
+        }
+
+        let mut event_names = HashSet::new();
+        for (index, event) in self.events.iter().enumerate() {
+            let path = format!("$.events[{index}]");
+            event.validate(&self.types, &mut violations, &path);
+            if !event_names.insert(&event.name) {
+                violations.push(Violation::new(
+                    format!("{path}.name"),
+                    format!("duplicate event name `{}`", event.name),
+                ));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Looks up a function by its technical name.
+    #[must_use]
+    pub fn function(&self, name: &str) -> Option<&FunctionDescriptor> {
+        self.functions
+            .iter()
+            .find(|function| function.name.0 == name)
+    }
+
+    /// Returns a copy of this api which only contains the translations for the given locale.
+    ///
+    /// Generators and runtimes use this to present the api in the language of the student.
+    #[must_use]
+    pub fn localized(&self, locale: &str) -> Self {
+        let mut api = self.clone();
+        api.caption.localize(locale);
+        api.description.localize(locale);
+        for definition in &mut api.types {
+            definition.localize(locale);
+        }
+        for function in &mut api.functions {
+            function.localize(locale);
+        }
+        for event in &mut api.events {
+            event.localize(locale);
+        }
+        api
+    }
+
+    /// Returns the errors of all functions, omitting those which have already been declared by
+    /// a previous function.
+    #[must_use]
+    pub fn errors(&self) -> Vec<&ErrorDescriptor> {
+        let mut names = HashSet::new();
+        self.functions
+            .iter()
+            .flat_map(|function| &function.errors)
+            .filter(|error| names.insert(&error.name))
+            .collect()
+    }
+
+    /// Returns the read-only functions without parameters, whose values make up the state of the
+    /// engine.
+    pub fn state_functions(&self) -> impl Iterator<Item = &FunctionDescriptor> {
+        self.functions
+            .iter()
+            .filter(|function| function.read_only && function.parameters.is_empty())
+    }
+
+    /// Looks up an event by its technical name.
+    #[must_use]
+    pub fn event(&self, name: &str) -> Option<&EventDescriptor> {
+        self.events.iter().find(|event| event.name.0 == name)
+    }
+
+    /// Looks up a named type by its technical name.
+    #[must_use]
+    pub fn type_definition(&self, name: &Identifier) -> Option<&TypeDefinition> {
+        find_type(&self.types, name)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -48,11 +275,142 @@ pub struct FunctionDescriptor {
     pub description: RichText,
     /// List of all parameters this function requires
     pub parameters: Vec<ParameterDescriptor>,
-    /// List of all parameters this function requires
+    /// The value this function replies with, if any
     pub returns: Option<ParameterDescriptor>,
+    /// List of all reasons why the engine may refuse to execute this function
+    #[serde(default)]
+    pub errors: Vec<ErrorDescriptor>,
+    /// The function only tells something about the state of the engine without changing it.
+    ///
+    /// The HTTP control server reports the values of all read-only functions without parameters
+    /// as the state of the api.
+    #[serde(default)]
+    pub read_only: bool,
 }
 
-/// Description of a function parameter or return value
+impl FunctionDescriptor {
+    fn localize(&mut self, locale: &str) {
+        self.caption.localize(locale);
+        self.description.localize(locale);
+        for parameter in self.parameters.iter_mut().chain(&mut self.returns) {
+            parameter.localize(locale);
+        }
+        for error in &mut self.errors {
+            error.caption.localize(locale);
+            error.description.localize(locale);
+        }
+    }
+
+    fn validate(&self, types: &[TypeDefinition], violations: &mut Vec<Violation>, path: &str) {
+        check_identifier(violations, &format!("{path}.name"), &self.name);
+        check_texts(violations, path, &self.caption, &self.description);
+
+        let mut parameter_names = HashSet::new();
+        let mut optional = false;
+        for (index, parameter) in self.parameters.iter().enumerate() {
+            let path = format!("{path}.parameters[{index}]");
+            parameter.validate(types, violations, &path);
+            // callers may omit trailing parameters only
+            if parameter.default.is_some() {
+                optional = true;
+            } else if optional {
+                violations.push(Violation::new(
+                    format!("{path}.default"),
+                    "a parameter without a default value must not follow one with a default value",
+                ));
+            }
+            if !parameter_names.insert(&parameter.name) {
+                violations.push(Violation::new(
+                    format!("{path}.name"),
+                    format!("duplicate parameter name `{}`", parameter.name),
+                ));
+            }
+        }
+
+        if let Some(ref returns) = self.returns {
+            let path = format!("{path}.returns");
+            returns.validate(types, violations, &path);
+            returns.forbid_default(violations, &path);
+        } else if self.read_only {
+            violations.push(Violation::new(
+                format!("{path}.read_only"),
+                "a read-only function must return a value",
+            ));
+        }
+
+        let mut error_names = HashSet::new();
+        for (index, error) in self.errors.iter().enumerate() {
+            let path = format!("{path}.errors[{index}]");
+            check_texts(violations, &path, &error.caption, &error.description);
+            let path = format!("{path}.name");
+            check_identifier(violations, &path, &error.name);
+            if !error_names.insert(&error.name) {
+                violations.push(Violation::new(
+                    path,
+                    format!("duplicate error name `{}`", error.name),
+                ));
+            }
+        }
+    }
+}
+
+/// Description of a reason why the engine refuses to execute a function
+///
+/// The name is what the engine reports as kind of the error. Several functions may fail for the
+/// same reason by declaring an error of the same name.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ErrorDescriptor {
+    /// technical name of this error
+    pub name: Identifier,
+    /// a single-line explanation what went wrong
+    pub caption: RichText,
+    /// a multi-line explanation what went wrong and how to avoid it
+    pub description: RichText,
+}
+
+/// Description of something happening within the engine, which scripts may react to
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EventDescriptor {
+    /// technical name of this event
+    pub name: Identifier,
+    /// a single-line explanation when this event occurs
+    pub caption: RichText,
+    /// a multi-line explanation when this event occurs
+    pub description: RichText,
+    /// List of all values the engine passes along with this event
+    #[serde(default)]
+    pub payload: Vec<ParameterDescriptor>,
+}
+
+impl EventDescriptor {
+    fn localize(&mut self, locale: &str) {
+        self.caption.localize(locale);
+        self.description.localize(locale);
+        for value in &mut self.payload {
+            value.localize(locale);
+        }
+    }
+
+    fn validate(&self, types: &[TypeDefinition], violations: &mut Vec<Violation>, path: &str) {
+        check_identifier(violations, &format!("{path}.name"), &self.name);
+        check_texts(violations, path, &self.caption, &self.description);
+
+        let mut value_names = HashSet::new();
+        for (index, value) in self.payload.iter().enumerate() {
+            let path = format!("{path}.payload[{index}]");
+            value.validate(types, violations, &path);
+            value.forbid_default(violations, &path);
+            if !value_names.insert(&value.name) {
+                violations.push(Violation::new(
+                    format!("{path}.name"),
+                    format!("duplicate payload name `{}`", value.name),
+                ));
+            }
+        }
+    }
+}
+
+/// Description of a function parameter, return value, record field or event payload
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ParameterDescriptor {
     /// technical name of this function parameter
@@ -64,18 +422,153 @@ pub struct ParameterDescriptor {
     pub description: RichText,
     /// Data type of this parameter
     pub typ: TypeDescriptor,
+    /// Value to be used if the caller omits this parameter; only supported by function parameters
+    #[serde(default)]
+    pub default: Option<Value>,
 }
 
-/// Describes the set of valid values for a parameter or variable.
+impl ParameterDescriptor {
+    fn localize(&mut self, locale: &str) {
+        self.caption.localize(locale);
+        self.description.localize(locale);
+    }
+
+    fn validate(&self, types: &[TypeDefinition], violations: &mut Vec<Violation>, path: &str) {
+        check_identifier(violations, &format!("{path}.name"), &self.name);
+        check_texts(violations, path, &self.caption, &self.description);
+        self.typ.validate(types, violations, &format!("{path}.typ"));
+        if let Some(ref default) = self.default {
+            if !self.typ.accepts(default, types) {
+                violations.push(Violation::new(
+                    format!("{path}.default"),
+                    format!("{default} is not of type {typ}", typ = self.typ),
+                ));
+            }
+        }
+    }
+
+    /// Reports a default value where it has no meaning.
+    fn forbid_default(&self, violations: &mut Vec<Violation>, path: &str) {
+        if self.default.is_some() {
+            violations.push(Violation::new(
+                format!("{path}.default"),
+                "only function parameters may have a default value",
+            ));
+        }
+    }
+}
+
+/// A type declared at the api level, which parameters and return values may refer to by name
 #[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TypeDefinition {
+    /// technical name of this type
+    pub name: Identifier,
+    /// a single-line explanation what this type is for
+    pub caption: RichText,
+    /// a multi-line explanation what this type is for
+    pub description: RichText,
+    /// the structure of this type
+    pub kind: TypeKind,
+}
+
+impl TypeDefinition {
+    fn localize(&mut self, locale: &str) {
+        self.caption.localize(locale);
+        self.description.localize(locale);
+        match self.kind {
+            TypeKind::Record(ref mut fields) => {
+                for field in fields {
+                    field.localize(locale);
+                }
+            }
+            TypeKind::Enum(ref mut variants) => {
+                for variant in variants {
+                    variant.caption.localize(locale);
+                    variant.description.localize(locale);
+                }
+            }
+        }
+    }
+
+    fn validate(&self, types: &[TypeDefinition], violations: &mut Vec<Violation>, path: &str) {
+        check_identifier(violations, &format!("{path}.name"), &self.name);
+        check_texts(violations, path, &self.caption, &self.description);
+
+        match self.kind {
+            TypeKind::Record(ref fields) => {
+                let path = format!("{path}.kind.Record");
+                if fields.is_empty() {
+                    violations.push(Violation::new(&path, "a record needs at least one field"));
+                }
+                let mut field_names = HashSet::new();
+                for (index, field) in fields.iter().enumerate() {
+                    let path = format!("{path}[{index}]");
+                    field.validate(types, violations, &path);
+                    field.forbid_default(violations, &path);
+                    if !field_names.insert(&field.name) {
+                        violations.push(Violation::new(
+                            format!("{path}.name"),
+                            format!("duplicate field name `{}`", field.name),
+                        ));
+                    }
+                }
+            }
+            TypeKind::Enum(ref variants) => {
+                let path = format!("{path}.kind.Enum");
+                if variants.is_empty() {
+                    violations.push(Violation::new(&path, "an enum needs at least one variant"));
+                }
+                let mut variant_names = HashSet::new();
+                for (index, variant) in variants.iter().enumerate() {
+                    let path = format!("{path}[{index}]");
+                    check_texts(violations, &path, &variant.caption, &variant.description);
+                    let path = format!("{path}.name");
+                    check_identifier(violations, &path, &variant.name);
+                    if !variant_names.insert(&variant.name) {
+                        violations.push(Violation::new(
+                            path,
+                            format!("duplicate variant name `{}`", variant.name),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The structure of a [`TypeDefinition`]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum TypeKind {
+    /// A fixed set of named fields, each having its own type
+    Record(Vec<ParameterDescriptor>),
+    /// Exactly one out of a fixed set of named variants
+    Enum(Vec<VariantDescriptor>),
+}
+
+/// Description of a single variant of an enum type
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VariantDescriptor {
+    /// technical name of this variant
+    pub name: Identifier,
+    /// a single-line explanation what this variant means
+    pub caption: RichText,
+    /// a multi-line explanation what this variant means
+    pub description: RichText,
+}
+
+/// Describes the set of valid values for a parameter or variable.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TypeDescriptor {
     /// Any integer value within the defined range. Unsigned values will typically have a lower bound of `0`
-    /// Both bounds are required to fit into `i48` or `u48`.
+    /// Both bounds are required to fit into `i48`; as the end is exclusive, it may lie one above
+    /// [`TypeDescriptor::MAX_SIGNED_INTEGER`].
     Integer(Range<i64>),
     Float,
     Boolean,
     String,
     List(Box<TypeDescriptor>),
+    /// A [`TypeDefinition`] declared by the api
+    Named(Identifier),
 }
 
 impl TypeDescriptor {
@@ -83,4 +576,586 @@ impl TypeDescriptor {
     pub const MAX_UNSIGNED_INTEGER: u64 = (1 << Self::INTEGER_BITS) - 1;
     pub const MAX_SIGNED_INTEGER: i64 = (1 << (Self::INTEGER_BITS - 1)) - 1;
     pub const MIN_INTEGER: i64 = -(1 << (Self::INTEGER_BITS - 1));
+
+    fn validate(&self, types: &[TypeDefinition], violations: &mut Vec<Violation>, path: &str) {
+        match *self {
+            TypeDescriptor::Integer(ref range) => {
+                let path = format!("{path}.Integer");
+                // the end is exclusive, so it may lie one above the largest integer
+                let bounds = [
+                    ("start", range.start, Self::MAX_SIGNED_INTEGER),
+                    ("end", range.end, Self::MAX_SIGNED_INTEGER + 1),
+                ];
+                for (bound, value, max) in bounds {
+                    if !(Self::MIN_INTEGER..=max).contains(&value) {
+                        violations.push(Violation::new(
+                            format!("{path}.{bound}"),
+                            format!(
+                                "{value} exceeds the {bits} bit range of integers",
+                                bits = Self::INTEGER_BITS
+                            ),
+                        ));
+                    }
+                }
+                if range.is_empty() {
+                    violations.push(Violation::new(
+                        path,
+                        format!(
+                            "the range {}..{} doesn't contain any value",
+                            range.start, range.end
+                        ),
+                    ));
+                }
+            }
+            TypeDescriptor::Float | TypeDescriptor::Boolean | TypeDescriptor::String => {}
+            TypeDescriptor::List(ref element_type) => {
+                element_type.validate(types, violations, &format!("{path}.List"));
+            }
+            TypeDescriptor::Named(ref name) => {
+                if find_type(types, name).is_none() {
+                    violations.push(Violation::new(
+                        format!("{path}.Named"),
+                        format!("unknown type `{name}`"),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if the given value is a member of the set of values described by this type.
+    ///
+    /// Named types are looked up in `types`; they are not accepted if missing.
+    #[must_use]
+    pub fn accepts(&self, value: &Value, types: &[TypeDefinition]) -> bool {
+        match *self {
+            TypeDescriptor::Integer(ref range) => {
+                matches!(*value, Value::Integer(integer) if range.contains(&integer))
+            }
+            TypeDescriptor::Float => matches!(*value, Value::Float(_)),
+            TypeDescriptor::Boolean => matches!(*value, Value::Boolean(_)),
+            TypeDescriptor::String => matches!(*value, Value::String(_)),
+            TypeDescriptor::List(ref element_type) => match *value {
+                Value::List(ref elements) => elements
+                    .iter()
+                    .all(|element| element_type.accepts(element, types)),
+                _ => false,
+            },
+            TypeDescriptor::Named(ref name) => {
+                let Some(definition) = find_type(types, name) else {
+                    return false;
+                };
+                match definition.kind {
+                    TypeKind::Record(ref fields) => match *value {
+                        Value::Record(ref values) => {
+                            values.len() == fields.len()
+                                && fields.iter().all(|field| {
+                                    values.get(&field.name).is_some_and(|field_value| {
+                                        field.typ.accepts(field_value, types)
+                                    })
+                                })
+                        }
+                        _ => false,
+                    },
+                    TypeKind::Enum(ref variants) => match *value {
+                        Value::Enum(ref variant) => {
+                            variants.iter().any(|candidate| candidate.name == *variant)
+                        }
+                        _ => false,
+                    },
+                }
+            }
+        }
+    }
+}
+
+impl Display for TypeDescriptor {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TypeDescriptor::Integer(ref range) => {
+                write!(formatter, "integer in {}..{}", range.start, range.end)
+            }
+            TypeDescriptor::Float => formatter.write_str("float"),
+            TypeDescriptor::Boolean => formatter.write_str("boolean"),
+            TypeDescriptor::String => formatter.write_str("string"),
+            TypeDescriptor::List(ref element_type) => write!(formatter, "list of {element_type}"),
+            TypeDescriptor::Named(ref name) => Display::fmt(name, formatter),
+        }
+    }
+}
+
+fn find_type<'types>(
+    types: &'types [TypeDefinition],
+    name: &Identifier,
+) -> Option<&'types TypeDefinition> {
+    types.iter().find(|definition| definition.name == *name)
+}
+
+fn check_texts(
+    violations: &mut Vec<Violation>,
+    path: &str,
+    caption: &RichText,
+    description: &RichText,
+) {
+    caption.validate(violations, &format!("{path}.caption"));
+    description.validate(violations, &format!("{path}.description"));
+}
+
+fn check_identifier(violations: &mut Vec<Violation>, path: &str, identifier: &Identifier) {
+    if !identifier.is_valid() {
+        violations.push(Violation::new(
+            path,
+            format!(
+                "`{identifier}` is not a valid identifier; only `a-z`, `0-9` and single spaces \
+                between words are allowed"
+            ),
+        ));
+    }
+}
+
+/// A rule broken by an [`Api`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    /// [JSONPath](https://goessner.net/articles/JsonPath/) of the offending element, e.g.
+    /// `$.functions[0].parameters[1].name`
+    pub path: String,
+    /// which rule has been broken
+    pub message: String,
+}
+
+impl Violation {
+    fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Reasons why an api couldn't be loaded.
+#[derive(Debug)]
+pub enum ApiError {
+    /// the file couldn't be read
+    Io(io::Error),
+    /// the file is not a valid api description
+    Parse(serde_json::Error),
+    /// the api breaks some rules
+    Invalid(Vec<Violation>),
+}
+
+impl Display for ApiError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ApiError::Io(ref error) => write!(formatter, "failed to read api: {error}"),
+            ApiError::Parse(ref error) => write!(formatter, "failed to parse api: {error}"),
+            ApiError::Invalid(ref violations) => {
+                write!(formatter, "invalid api:")?;
+                for violation in violations {
+                    write!(formatter, "\n  {violation}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for ApiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ApiError::Io(ref error) => Some(error),
+            ApiError::Parse(ref error) => Some(error),
+            ApiError::Invalid(_) => None,
+        }
+    }
+}
+
+/// A concrete value of a function argument or return value.
+///
+/// Use [`TypeDescriptor::accepts`] to check whether a value matches a parameter.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    String(String),
+    List(Vec<Value>),
+    /// a value of a record type; one entry per field
+    Record(BTreeMap<Identifier, Value>),
+    /// a variant of an enum type
+    Enum(Identifier),
+}
+
+impl Value {
+    /// Represents this value as json, the way the HTTP control server exchanges it.
+    ///
+    /// Records become objects keyed by the names of their fields and enum variants become their
+    /// names.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        match *self {
+            Value::Integer(integer) => integer.into(),
+            Value::Float(float) => float.into(),
+            Value::Boolean(boolean) => boolean.into(),
+            Value::String(ref string) => string.as_str().into(),
+            Value::List(ref elements) => elements.iter().map(Value::to_json).collect(),
+            Value::Record(ref fields) => fields
+                .iter()
+                .map(|(name, value)| (name.0.clone(), value.to_json()))
+                .collect(),
+            Value::Enum(ref variant) => variant.0.as_str().into(),
+        }
+    }
+
+    /// Reads a value of the given type from its json representation (see [`Value::to_json`]).
+    ///
+    /// Named types are looked up in `types`. Integers are not checked against their range, use
+    /// [`TypeDescriptor::accepts`] to do so.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message describing the mismatch if the json doesn't represent a value of the
+    /// type.
+    pub fn from_json(
+        json: &serde_json::Value,
+        typ: &TypeDescriptor,
+        types: &[TypeDefinition],
+    ) -> Result<Self, String> {
+        let mismatch = || format!("expected {typ}, but got {json}");
+        let value = match *typ {
+            TypeDescriptor::Integer(_) => Value::Integer(json.as_i64().ok_or_else(mismatch)?),
+            TypeDescriptor::Float => Value::Float(json.as_f64().ok_or_else(mismatch)?),
+            TypeDescriptor::Boolean => Value::Boolean(json.as_bool().ok_or_else(mismatch)?),
+            TypeDescriptor::String => Value::String(json.as_str().ok_or_else(mismatch)?.to_owned()),
+            TypeDescriptor::List(ref element_type) => Value::List(
+                json.as_array()
+                    .ok_or_else(mismatch)?
+                    .iter()
+                    .map(|element| Value::from_json(element, element_type, types))
+                    .collect::<Result<_, _>>()?,
+            ),
+            TypeDescriptor::Named(ref name) => {
+                let definition =
+                    find_type(types, name).ok_or_else(|| format!("unknown type `{name}`"))?;
+                match definition.kind {
+                    TypeKind::Record(ref fields) => {
+                        let object = json.as_object().ok_or_else(mismatch)?;
+                        if let Some(unknown) = object
+                            .keys()
+                            .find(|key| !fields.iter().any(|field| field.name.0 == **key))
+                        {
+                            return Err(format!("`{name}` has no field `{unknown}`"));
+                        }
+                        Value::Record(
+                            fields
+                                .iter()
+                                .map(|field| {
+                                    let field_json =
+                                        object.get(&field.name.0).ok_or_else(|| {
+                                            format!("field `{}` of `{name}` is missing", field.name)
+                                        })?;
+                                    let field_value =
+                                        Value::from_json(field_json, &field.typ, types)?;
+                                    Ok((field.name.clone(), field_value))
+                                })
+                                .collect::<Result<_, String>>()?,
+                        )
+                    }
+                    TypeKind::Enum(ref variants) => {
+                        let variant = json.as_str().ok_or_else(mismatch)?;
+                        if !variants.iter().any(|candidate| candidate.name.0 == variant) {
+                            return Err(mismatch());
+                        }
+                        Value::Enum(Identifier(variant.to_owned()))
+                    }
+                }
+            }
+        };
+        Ok(value)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Value::Integer(integer) => Display::fmt(&integer, formatter),
+            Value::Float(float) => Display::fmt(&float, formatter),
+            Value::Boolean(boolean) => Display::fmt(&boolean, formatter),
+            Value::String(ref string) => write!(formatter, "{string:?}"),
+            Value::List(ref elements) => {
+                formatter.write_str("[")?;
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        formatter.write_str(", ")?;
+                    }
+                    Display::fmt(element, formatter)?;
+                }
+                formatter.write_str("]")
+            }
+            Value::Record(ref fields) => {
+                formatter.write_str("{")?;
+                for (index, (name, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        formatter.write_str(", ")?;
+                    }
+                    write!(formatter, "{name}: {value}")?;
+                }
+                formatter.write_str("}")
+            }
+            Value::Enum(ref variant) => Display::fmt(variant, formatter),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// An api with a single function, whose parameters are given as json.
+    fn api(parameters: &serde_json::Value) -> Api {
+        serde_json::from_value(json!({
+            "name": "test",
+            "caption": "test",
+            "description": "test",
+            "functions": [{
+                "name": "move",
+                "caption": "move",
+                "description": "move",
+                "parameters": parameters,
+            }],
+        }))
+        .unwrap()
+    }
+
+    fn parameter(name: &str, typ: &serde_json::Value) -> serde_json::Value {
+        json!({ "name": name, "caption": name, "description": name, "typ": typ })
+    }
+
+    /// Returns the paths and messages of all violations, which are expected to exist.
+    fn violations(api: &Api) -> Vec<(String, String)> {
+        api.validate()
+            .expect_err("the api should be invalid")
+            .into_iter()
+            .map(|violation| (violation.path, violation.message))
+            .collect()
+    }
+
+    fn paths(api: &Api) -> Vec<String> {
+        violations(api).into_iter().map(|(path, _)| path).collect()
+    }
+
+    #[test]
+    fn reads_plain_and_localized_texts() {
+        let plain: RichText = serde_json::from_value(json!("Turns the robot")).unwrap();
+        assert_eq!(plain, RichText::Plain("Turns the robot".to_owned()));
+        assert_eq!(plain.get("de"), "Turns the robot");
+
+        let localized: RichText =
+            serde_json::from_value(json!({ "en": "Turns the robot", "de": "Dreht den Roboter" }))
+                .unwrap();
+        let RichText::Localized(ref translations) = localized else {
+            panic!("expected translations, got {localized:?}");
+        };
+        assert_eq!(translations.len(), 2);
+        assert_eq!(localized.get("de"), "Dreht den Roboter");
+        assert_eq!(localized.get("en"), "Turns the robot");
+    }
+
+    #[test]
+    fn falls_back_to_other_translations() {
+        let text: RichText =
+            serde_json::from_value(json!({ "en": "Turns the robot", "de": "Dreht den Roboter" }))
+                .unwrap();
+        assert_eq!(text.get("de-AT"), "Dreht den Roboter");
+        assert_eq!(text.get("de_CH"), "Dreht den Roboter");
+        assert_eq!(text.get("fr"), "Turns the robot");
+        assert_eq!(text.to_string(), "Turns the robot");
+
+        let untranslated: RichText =
+            serde_json::from_value(json!({ "de": "Dreht den Roboter" })).unwrap();
+        assert_eq!(untranslated.get("fr"), "Dreht den Roboter");
+    }
+
+    #[test]
+    fn reports_invalid_translations() {
+        let mut api = api(&json!([]));
+        api.caption =
+            serde_json::from_value(json!({ "de": "Roboter", "de AT": "Roboter" })).unwrap();
+        assert_eq!(
+            violations(&api),
+            [
+                (
+                    "$.caption".to_owned(),
+                    "the translation for the fallback locale `en` is missing".to_owned()
+                ),
+                (
+                    "$.caption['de AT']".to_owned(),
+                    "`de AT` is not a valid locale; use e.g. `de` or `de-AT`".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_robot_api() {
+        Api::load("apis/robot.api.json").unwrap();
+    }
+
+    #[test]
+    fn accepts_valid_parameters() {
+        let steps = json!({ "Integer": { "start": 1, "end": 10 } });
+        let mut steps_parameter = parameter("steps", &steps);
+        steps_parameter["default"] = json!({ "Integer": 1 });
+        api(&json!([steps_parameter])).validate().unwrap();
+    }
+
+    #[test]
+    fn reports_duplicate_names() {
+        let mut api = api(&json!([
+            parameter("steps", &json!("Boolean")),
+            parameter("steps", &json!("Boolean")),
+        ]));
+        api.functions.push(api.functions[0].clone());
+        assert_eq!(
+            violations(&api),
+            [
+                (
+                    "$.functions[0].parameters[1].name".to_owned(),
+                    "duplicate parameter name `steps`".to_owned()
+                ),
+                (
+                    "$.functions[1].parameters[1].name".to_owned(),
+                    "duplicate parameter name `steps`".to_owned()
+                ),
+                (
+                    "$.functions[1].name".to_owned(),
+                    "duplicate function name `move`".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_duplicate_type_names() {
+        let mut api = api(&json!([]));
+        let definition: TypeDefinition = serde_json::from_value(json!({
+            "name": "direction",
+            "caption": "direction",
+            "description": "direction",
+            "kind": { "Enum": [{ "name": "left", "caption": "left", "description": "left" }] },
+        }))
+        .unwrap();
+        api.types = vec![definition.clone(), definition];
+        assert_eq!(paths(&api), ["$.types[1].name"]);
+    }
+
+    #[test]
+    fn reports_unknown_named_types() {
+        let api = api(&json!([
+            parameter("target", &json!({ "Named": "position" })),
+            parameter("path", &json!({ "List": { "Named": "position" } })),
+        ]));
+        assert_eq!(
+            violations(&api),
+            [
+                (
+                    "$.functions[0].parameters[0].typ.Named".to_owned(),
+                    "unknown type `position`".to_owned()
+                ),
+                (
+                    "$.functions[0].parameters[1].typ.List.Named".to_owned(),
+                    "unknown type `position`".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_default_outside_of_range() {
+        let mut steps = parameter("steps", &json!({ "Integer": { "start": 1, "end": 10 } }));
+        steps["default"] = json!({ "Integer": 10 });
+        assert_eq!(
+            violations(&api(&json!([steps]))),
+            [(
+                "$.functions[0].parameters[0].default".to_owned(),
+                "10 is not of type integer in 1..10".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_ranges() {
+        let api = api(&json!([
+            parameter("empty", &json!({ "Integer": { "start": 5, "end": 5 } })),
+            parameter(
+                "huge",
+                &json!({ "Integer": { "start": 0, "end": 1_i64 << 48 } })
+            ),
+            parameter(
+                "unsigned",
+                &json!({ "Integer": { "start": 1_i64 << 47, "end": (1_i64 << 47) + 2 } }),
+            ),
+        ]));
+        assert_eq!(
+            violations(&api),
+            [
+                (
+                    "$.functions[0].parameters[0].typ.Integer".to_owned(),
+                    "the range 5..5 doesn't contain any value".to_owned()
+                ),
+                (
+                    "$.functions[0].parameters[1].typ.Integer.end".to_owned(),
+                    "281474976710656 exceeds the 48 bit range of integers".to_owned()
+                ),
+                (
+                    "$.functions[0].parameters[2].typ.Integer.start".to_owned(),
+                    "140737488355328 exceeds the 48 bit range of integers".to_owned()
+                ),
+                (
+                    "$.functions[0].parameters[2].typ.Integer.end".to_owned(),
+                    "140737488355330 exceeds the 48 bit range of integers".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_the_whole_range_of_integers() {
+        let min = TypeDescriptor::MIN_INTEGER;
+        let max = TypeDescriptor::MAX_SIGNED_INTEGER;
+        api(&json!([
+            parameter(
+                "all",
+                &json!({ "Integer": { "start": min, "end": max + 1 } })
+            ),
+            parameter(
+                "largest",
+                &json!({ "Integer": { "start": max, "end": max + 1 } })
+            ),
+        ]))
+        .validate()
+        .unwrap();
+        let api = api(&json!([parameter(
+            "below",
+            &json!({ "Integer": { "start": min - 1, "end": 0 } })
+        )]));
+        assert_eq!(
+            paths(&api),
+            ["$.functions[0].parameters[0].typ.Integer.start"]
+        );
+    }
+
+    #[test]
+    fn reports_required_parameter_after_optional_one() {
+        let mut slowly = parameter("slowly", &json!("Boolean"));
+        slowly["default"] = json!({ "Boolean": false });
+        let api = api(&json!([slowly, parameter("steps", &json!("Boolean"))]));
+        assert_eq!(paths(&api), ["$.functions[0].parameters[1].default"]);
+    }
 }
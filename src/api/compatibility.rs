@@ -0,0 +1,709 @@
+//! Finds out whether scripts written against one version of an [`Api`] keep working with another.
+//!
+//! Every difference which scripts or clients of the HTTP control server might notice is reported
+//! as a [`Change`]. A change is breaking if a script which worked with the old version might fail
+//! or behave differently with the new one, e.g. because a function has been removed or a
+//! parameter accepts fewer values than before.
+//!
+//! Adding an optional parameter is compatible, as existing calls don't pass it and keep their
+//! meaning. Changing the default value of an existing parameter is breaking though: calls
+//! omitting it still succeed, but silently do something else than before.
+//!
+//! Captions and descriptions are not being compared.
+
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+    ops::Range,
+};
+
+use super::{
+    Api, EventDescriptor, FunctionDescriptor, Identifier, ParameterDescriptor, TypeDescriptor,
+    TypeKind,
+};
+
+/// Whether scripts written against the old version keep working after a [`Change`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Compatibility {
+    /// scripts keep working
+    Compatible,
+    /// scripts might fail
+    Breaking,
+}
+
+impl Display for Compatibility {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Compatibility::Compatible => formatter.write_str("compatible"),
+            Compatibility::Breaking => formatter.write_str("breaking"),
+        }
+    }
+}
+
+/// A single difference between two versions of an api
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    /// whether scripts written against the old version keep working
+    pub compatibility: Compatibility,
+    /// the element which changed, e.g. `function move forward, parameter steps`
+    pub element: String,
+    /// what has changed
+    pub description: String,
+}
+
+impl Change {
+    /// Returns `true` if scripts written against the old version might fail with the new one.
+    #[must_use]
+    pub fn is_breaking(&self) -> bool {
+        self.compatibility == Compatibility::Breaking
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{}: {}: {}",
+            self.compatibility, self.element, self.description
+        )
+    }
+}
+
+/// Lists all changes from the `old` to the `new` version of an api.
+#[must_use]
+pub fn compare(old: &Api, new: &Api) -> Vec<Change> {
+    let mut comparison = Comparison {
+        old,
+        new,
+        changes: Vec::new(),
+        compared_types: HashSet::new(),
+    };
+    comparison.compare_api();
+    comparison.changes
+}
+
+/// The direction in which values of a type are being passed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Direction {
+    /// from the script to the engine (arguments); the new type must accept all former values
+    Input,
+    /// from the engine to the script (return values, event payloads); the new type must not
+    /// contain any values the script didn't expect before
+    Output,
+}
+
+struct Comparison<'api> {
+    old: &'api Api,
+    new: &'api Api,
+    changes: Vec<Change>,
+    /// named types are compared once per direction, no matter how often they're being used
+    compared_types: HashSet<(Identifier, Direction)>,
+}
+
+impl Comparison<'_> {
+    fn report(&mut self, compatibility: Compatibility, element: &str, description: String) {
+        let change = Change {
+            compatibility,
+            element: element.to_owned(),
+            description,
+        };
+        if !self.changes.contains(&change) {
+            self.changes.push(change);
+        }
+    }
+
+    fn compatible(&mut self, element: &str, description: String) {
+        self.report(Compatibility::Compatible, element, description);
+    }
+
+    fn breaking(&mut self, element: &str, description: String) {
+        self.report(Compatibility::Breaking, element, description);
+    }
+
+    fn compare_api(&mut self) {
+        let (old, new) = (self.old, self.new);
+
+        if old.name != new.name {
+            self.breaking(
+                "api",
+                format!(
+                    "has been renamed from `{}` to `{}`, so scripts need to import it under \
+                    a different name",
+                    old.name, new.name
+                ),
+            );
+        }
+
+        for definition in &old.types {
+            if new.type_definition(&definition.name).is_none() {
+                self.breaking(
+                    &format!("type {}", definition.name),
+                    "has been removed".to_owned(),
+                );
+            }
+        }
+        for definition in &new.types {
+            if old.type_definition(&definition.name).is_none() {
+                self.compatible(
+                    &format!("type {}", definition.name),
+                    "has been added".to_owned(),
+                );
+            }
+        }
+
+        for old_function in &old.functions {
+            match new.function(&old_function.name.0) {
+                Some(new_function) => self.compare_function(old_function, new_function),
+                None => self.breaking(
+                    &format!("function {}", old_function.name),
+                    "has been removed".to_owned(),
+                ),
+            }
+        }
+        for new_function in &new.functions {
+            if old.function(&new_function.name.0).is_none() {
+                self.compatible(
+                    &format!("function {}", new_function.name),
+                    "has been added".to_owned(),
+                );
+            }
+        }
+
+        // scripts refer to the exception class of an error, no matter which function raises it
+        let old_errors = old.errors();
+        let new_errors = new.errors();
+        for error in &old_errors {
+            if !new_errors
+                .iter()
+                .any(|new_error| new_error.name == error.name)
+            {
+                self.breaking(
+                    &format!("error {}", error.name),
+                    "has been removed".to_owned(),
+                );
+            }
+        }
+        for error in &new_errors {
+            if !old_errors
+                .iter()
+                .any(|old_error| old_error.name == error.name)
+            {
+                self.compatible(
+                    &format!("error {}", error.name),
+                    "has been added".to_owned(),
+                );
+            }
+        }
+
+        for old_event in &old.events {
+            match new.event(&old_event.name.0) {
+                Some(new_event) => self.compare_event(old_event, new_event),
+                None => self.breaking(
+                    &format!("event {}", old_event.name),
+                    "has been removed".to_owned(),
+                ),
+            }
+        }
+        for new_event in &new.events {
+            if old.event(&new_event.name.0).is_none() {
+                self.compatible(
+                    &format!("event {}", new_event.name),
+                    "has been added".to_owned(),
+                );
+            }
+        }
+
+        // types not being used by any function or event may still be created by scripts
+        for definition in &old.types {
+            let name = &definition.name;
+            let compared = [Direction::Input, Direction::Output]
+                .into_iter()
+                .any(|direction| self.compared_types.contains(&(name.clone(), direction)));
+            if !compared {
+                self.compare_named_type(name, Direction::Input);
+            }
+        }
+    }
+
+    fn compare_function(&mut self, old: &FunctionDescriptor, new: &FunctionDescriptor) {
+        let element = format!("function {}", old.name);
+
+        let count = old.parameters.len().max(new.parameters.len());
+        for index in 0..count {
+            match (old.parameters.get(index), new.parameters.get(index)) {
+                (Some(old_parameter), Some(new_parameter)) => {
+                    let element = format!("{element}, parameter {}", old_parameter.name);
+                    if old_parameter.name != new_parameter.name {
+                        self.breaking(
+                            &element,
+                            format!(
+                                "has been renamed to `{}`, which breaks keyword arguments",
+                                new_parameter.name
+                            ),
+                        );
+                    }
+                    self.compare_type(
+                        &element,
+                        &old_parameter.typ,
+                        &new_parameter.typ,
+                        Direction::Input,
+                    );
+                    match (
+                        old_parameter.default.as_ref(),
+                        new_parameter.default.as_ref(),
+                    ) {
+                        (Some(_), None) => self.breaking(
+                            &element,
+                            "lost its default value and cannot be omitted anymore".to_owned(),
+                        ),
+                        (None, Some(default)) => self.compatible(
+                            &element,
+                            format!("may be omitted now and defaults to {default}"),
+                        ),
+                        (Some(old_default), Some(new_default)) if old_default != new_default => {
+                            self.breaking(
+                                &element,
+                                format!(
+                                    "defaults to {new_default} instead of {old_default}, which \
+                                    changes calls omitting it"
+                                ),
+                            );
+                        }
+                        _ => {}
+                    }
+                }
+                (Some(old_parameter), None) => self.breaking(
+                    &format!("{element}, parameter {}", old_parameter.name),
+                    "has been removed".to_owned(),
+                ),
+                (None, Some(new_parameter)) => {
+                    let element = format!("{element}, parameter {}", new_parameter.name);
+                    if new_parameter.default.is_some() {
+                        self.compatible(&element, "optional parameter has been added".to_owned());
+                    } else {
+                        self.breaking(&element, "required parameter has been added".to_owned());
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+
+        // the HTTP control server reports read-only functions as part of the state
+        match (old.read_only, new.read_only) {
+            (true, false) => self.breaking(
+                &element,
+                "is no longer read-only and cannot be queried as part of the state anymore"
+                    .to_owned(),
+            ),
+            (false, true) => self.compatible(&element, "has become read-only".to_owned()),
+            _ => {}
+        }
+
+        let element = format!("{element}, return value");
+        match (old.returns.as_ref(), new.returns.as_ref()) {
+            (None, Some(_)) => self.compatible(&element, "has been added".to_owned()),
+            (Some(_), None) => self.breaking(&element, "has been removed".to_owned()),
+            (Some(old_returns), Some(new_returns)) => {
+                self.compare_type(
+                    &element,
+                    &old_returns.typ,
+                    &new_returns.typ,
+                    Direction::Output,
+                );
+            }
+            (None, None) => {}
+        }
+    }
+
+    fn compare_event(&mut self, old: &EventDescriptor, new: &EventDescriptor) {
+        let element = format!("event {}", old.name);
+
+        // handlers take the payload as positional arguments
+        if old.payload.len() != new.payload.len() {
+            self.breaking(
+                &element,
+                format!(
+                    "handlers receive {} value(s) instead of {}",
+                    new.payload.len(),
+                    old.payload.len()
+                ),
+            );
+        }
+        for (old_value, new_value) in old.payload.iter().zip(&new.payload) {
+            self.compare_type(
+                &format!("{element}, payload {}", old_value.name),
+                &old_value.typ,
+                &new_value.typ,
+                Direction::Output,
+            );
+        }
+    }
+
+    fn compare_type(
+        &mut self,
+        element: &str,
+        old: &TypeDescriptor,
+        new: &TypeDescriptor,
+        direction: Direction,
+    ) {
+        match *old {
+            TypeDescriptor::Integer(ref old_range) => {
+                if let TypeDescriptor::Integer(ref new_range) = *new {
+                    self.compare_ranges(element, old_range, new_range, direction);
+                    return;
+                }
+            }
+            TypeDescriptor::Float | TypeDescriptor::Boolean | TypeDescriptor::String => {
+                if old == new {
+                    return;
+                }
+            }
+            TypeDescriptor::List(ref old_element) => {
+                if let TypeDescriptor::List(ref new_element) = *new {
+                    self.compare_type(element, old_element, new_element, direction);
+                    return;
+                }
+            }
+            TypeDescriptor::Named(ref old_name) => {
+                if let TypeDescriptor::Named(ref new_name) = *new {
+                    if old_name == new_name {
+                        self.compare_named_type(old_name, direction);
+                        return;
+                    }
+                }
+            }
+        }
+
+        self.breaking(element, format!("type changed from {old} to {new}"));
+    }
+
+    fn compare_ranges(
+        &mut self,
+        element: &str,
+        old: &Range<i64>,
+        new: &Range<i64>,
+        direction: Direction,
+    ) {
+        if old == new {
+            return;
+        }
+
+        let widened = new.start <= old.start && new.end >= old.end;
+        let narrowed = new.start >= old.start && new.end <= old.end;
+        let compatible = match direction {
+            Direction::Input => widened,
+            Direction::Output => narrowed,
+        };
+        let what = if widened {
+            "widened"
+        } else if narrowed {
+            "narrowed"
+        } else {
+            "changed"
+        };
+        let description = format!(
+            "range {what} from {}..{} to {}..{}",
+            old.start, old.end, new.start, new.end
+        );
+
+        if compatible {
+            self.compatible(element, description);
+        } else {
+            self.breaking(element, description);
+        }
+    }
+
+    fn compare_named_type(&mut self, name: &Identifier, direction: Direction) {
+        if !self.compared_types.insert((name.clone(), direction)) {
+            return;
+        }
+        // a removed type has already been reported
+        let (Some(old), Some(new)) = (
+            self.old.type_definition(name),
+            self.new.type_definition(name),
+        ) else {
+            return;
+        };
+        let element = format!("type {name}");
+
+        match old.kind {
+            TypeKind::Record(ref old_fields) => {
+                let TypeKind::Record(ref new_fields) = new.kind else {
+                    self.breaking(&element, "changed from a record to an enum".to_owned());
+                    return;
+                };
+
+                for old_field in old_fields {
+                    let field_element = format!("{element}, field {}", old_field.name);
+                    match new_fields.iter().find(|field| field.name == old_field.name) {
+                        Some(new_field) => self.compare_type(
+                            &field_element,
+                            &old_field.typ,
+                            &new_field.typ,
+                            direction,
+                        ),
+                        None => self.breaking(&field_element, "has been removed".to_owned()),
+                    }
+                }
+                for new_field in new_fields {
+                    if old_fields.iter().all(|field| field.name != new_field.name) {
+                        let field_element = format!("{element}, field {}", new_field.name);
+                        match direction {
+                            Direction::Input => self.breaking(
+                                &field_element,
+                                "has been added, but scripts don't provide it".to_owned(),
+                            ),
+                            Direction::Output => {
+                                self.compatible(&field_element, "has been added".to_owned());
+                            }
+                        }
+                    }
+                }
+
+                // scripts may create records by passing the fields as positional arguments
+                let common_names =
+                    |fields: &[ParameterDescriptor], others: &[ParameterDescriptor]| {
+                        fields
+                            .iter()
+                            .filter(|field| others.iter().any(|other| other.name == field.name))
+                            .map(|field| field.name.clone())
+                            .collect::<Vec<_>>()
+                    };
+                if common_names(old_fields, new_fields) != common_names(new_fields, old_fields) {
+                    self.breaking(&element, "the order of the fields has changed".to_owned());
+                }
+            }
+            TypeKind::Enum(ref old_variants) => {
+                let TypeKind::Enum(ref new_variants) = new.kind else {
+                    self.breaking(&element, "changed from an enum to a record".to_owned());
+                    return;
+                };
+
+                for old_variant in old_variants {
+                    if new_variants
+                        .iter()
+                        .all(|variant| variant.name != old_variant.name)
+                    {
+                        self.breaking(
+                            &format!("{element}, variant {}", old_variant.name),
+                            "has been removed".to_owned(),
+                        );
+                    }
+                }
+                for new_variant in new_variants {
+                    if old_variants
+                        .iter()
+                        .all(|variant| variant.name != new_variant.name)
+                    {
+                        let variant_element = format!("{element}, variant {}", new_variant.name);
+                        match direction {
+                            Direction::Input => {
+                                self.compatible(&variant_element, "has been added".to_owned());
+                            }
+                            Direction::Output => self.breaking(
+                                &variant_element,
+                                "has been added and may be returned to scripts not expecting it"
+                                    .to_owned(),
+                            ),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{RichText, Value};
+
+    fn robot_api() -> Api {
+        Api::load("apis/robot.api.json").unwrap()
+    }
+
+    fn function<'api>(api: &'api mut Api, name: &str) -> &'api mut FunctionDescriptor {
+        api.functions
+            .iter_mut()
+            .find(|function| function.name.0 == name)
+            .unwrap()
+    }
+
+    /// Compares the robot api with a modified copy of it.
+    fn changes(modify: impl FnOnce(&mut Api)) -> Vec<Change> {
+        let old = robot_api();
+        let mut new = old.clone();
+        modify(&mut new);
+        compare(&old, &new)
+    }
+
+    fn change(compatibility: Compatibility, element: &str, description: &str) -> Change {
+        Change {
+            compatibility,
+            element: element.to_owned(),
+            description: description.to_owned(),
+        }
+    }
+
+    fn parameter(name: &str, default: Option<Value>) -> ParameterDescriptor {
+        ParameterDescriptor {
+            name: Identifier(name.to_owned()),
+            caption: RichText::Plain(name.to_owned()),
+            description: RichText::Plain(name.to_owned()),
+            typ: TypeDescriptor::Boolean,
+            default,
+        }
+    }
+
+    #[test]
+    fn finds_no_changes_in_same_api() {
+        assert_eq!(changes(|_| {}), []);
+    }
+
+    #[test]
+    fn reports_removed_function() {
+        let changes = changes(|api| {
+            api.functions
+                .retain(|function| function.name.0 != "position");
+        });
+        assert_eq!(
+            changes,
+            [change(
+                Compatibility::Breaking,
+                "function position",
+                "has been removed"
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_added_function_as_compatible() {
+        let changes = changes(|api| {
+            let mut function = function(api, "turn left").clone();
+            function.name = Identifier("turn around".to_owned());
+            api.functions.push(function);
+        });
+        assert_eq!(
+            changes,
+            [change(
+                Compatibility::Compatible,
+                "function turn around",
+                "has been added"
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_narrowed_input_range_as_breaking() {
+        let changes = changes(|api| {
+            function(api, "move forward").parameters[0].typ = TypeDescriptor::Integer(1..10);
+        });
+        assert_eq!(
+            changes,
+            [change(
+                Compatibility::Breaking,
+                "function move forward, parameter steps",
+                "range narrowed from 1..1000 to 1..10"
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_widened_input_range_as_compatible() {
+        let changes = changes(|api| {
+            function(api, "move forward").parameters[0].typ = TypeDescriptor::Integer(1..2000);
+        });
+        assert_eq!(
+            changes,
+            [change(
+                Compatibility::Compatible,
+                "function move forward, parameter steps",
+                "range widened from 1..1000 to 1..2000"
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_added_optional_parameter_as_compatible() {
+        let changes = changes(|api| {
+            let default = Some(Value::Boolean(false));
+            function(api, "move forward")
+                .parameters
+                .push(parameter("slowly", default));
+        });
+        assert_eq!(
+            changes,
+            [change(
+                Compatibility::Compatible,
+                "function move forward, parameter slowly",
+                "optional parameter has been added"
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_changed_default_as_breaking() {
+        let changes = changes(|api| {
+            function(api, "move forward").parameters[0].default = Some(Value::Integer(2));
+        });
+        assert_eq!(
+            changes,
+            [change(
+                Compatibility::Breaking,
+                "function move forward, parameter steps",
+                "defaults to 2 instead of 1, which changes calls omitting it"
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_added_required_parameter_as_breaking() {
+        let changes = changes(|api| {
+            function(api, "position")
+                .parameters
+                .push(parameter("slowly", None));
+        });
+        assert_eq!(
+            changes,
+            [change(
+                Compatibility::Breaking,
+                "function position, parameter slowly",
+                "required parameter has been added"
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_changed_return_type() {
+        let changes = changes(|api| {
+            function(api, "position").returns.as_mut().unwrap().typ = TypeDescriptor::String;
+        });
+        assert_eq!(
+            changes,
+            [change(
+                Compatibility::Breaking,
+                "function position, return value",
+                "type changed from position to string"
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_read_only_changes() {
+        let changes = changes(|api| {
+            function(api, "position").read_only = false;
+            function(api, "turn left").returns = function(api, "orientation").returns.clone();
+            function(api, "turn left").read_only = true;
+        });
+        assert!(changes.contains(&change(
+            Compatibility::Breaking,
+            "function position",
+            "is no longer read-only and cannot be queried as part of the state anymore"
+        )));
+        assert!(changes.contains(&change(
+            Compatibility::Compatible,
+            "function turn left",
+            "has become read-only"
+        )));
+    }
+}
@@ -0,0 +1,61 @@
+#![allow(
+    unused_crate_dependencies,
+    reason = "the dependencies are shared with the library"
+)]
+#![allow(missing_docs, reason = "the binary is documented by its usage")]
+#![allow(clippy::print_stdout, reason = "the changes are written to stdout")]
+#![allow(clippy::print_stderr, reason = "usage and load errors go to stderr")]
+
+//! Tells whether scripts written against one version of an api keep working with another.
+//!
+//! Usage: `api_compat <old api> <new api>`
+//!
+//! Prints every change and exits with `1` if any of them is breaking or with `2` if an api
+//! couldn't be loaded.
+
+use std::process::ExitCode;
+
+use gam3du::api::{self, Api};
+
+const USAGE: &str = "usage: api_compat <old api> <new api>";
+
+/// exit code if there are breaking changes
+const BREAKING: u8 = 1;
+/// exit code if the apis couldn't be compared at all
+const INVALID: u8 = 2;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [ref old_path, ref new_path] = *args.as_slice() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(INVALID);
+    };
+
+    let mut apis = Vec::new();
+    for path in [old_path, new_path] {
+        match Api::load(path) {
+            Ok(api) => apis.push(api),
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                return ExitCode::from(INVALID);
+            }
+        }
+    }
+    let [ref old, ref new] = *apis.as_slice() else {
+        unreachable!("both apis have been loaded");
+    };
+
+    let changes = api::compare(old, new);
+    for change in &changes {
+        println!("{change}");
+    }
+
+    let breaking = changes.iter().filter(|change| change.is_breaking()).count();
+    println!("{} change(s), {breaking} of them breaking", changes.len());
+
+    if breaking > 0 {
+        ExitCode::from(BREAKING)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,124 @@
+#![allow(
+    unused_crate_dependencies,
+    reason = "the dependencies are shared with the library"
+)]
+#![allow(missing_docs, reason = "the binary is documented by its usage")]
+#![allow(clippy::print_stdout, reason = "the report is written to stdout")]
+#![allow(clippy::print_stderr, reason = "usage and load errors go to stderr")]
+#![allow(clippy::unwrap_used, reason = "a report can always be serialized")]
+
+//! Runs a Python or Lua script against a level without opening a window and prints the outcome as
+//! JSON.
+//!
+//! Usage: `headless <script> [<level>] [--timeout <seconds>] [--max-commands <count>]
+//! [--unrestricted] [--locale <locale>]`
+//!
+//! Scripts ending in `.lua` are run by the Lua interpreter, all others by the Python interpreter.
+//! Scripts run within the student sandbox unless `--unrestricted` is given.
+//! Explanations of errors declared by the api use the given locale (e.g. `de`).
+
+use std::{path::PathBuf, process::ExitCode, time::Duration};
+
+use gam3du::{
+    api::{Api, FALLBACK_LOCALE},
+    headless,
+    level::Level,
+    logging::init_logger,
+    python::{Limits, Sandbox},
+};
+
+const LEVEL_PATH: &str = "levels/demo.level.json5";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const USAGE: &str =
+    "usage: headless <script> [<level>] [--timeout <seconds>] [--max-commands <count>] [--unrestricted] [--locale <locale>]";
+
+fn main() -> ExitCode {
+    init_logger();
+
+    let mut script = None;
+    let mut level_path = None;
+    let mut limits = Limits {
+        timeout: Some(DEFAULT_TIMEOUT),
+        max_commands: None,
+    };
+    let mut sandbox = Sandbox::student();
+    let mut locale = FALLBACK_LOCALE.to_owned();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--timeout" {
+            let Some(seconds) = args
+                .next()
+                .and_then(|seconds| seconds.parse().ok())
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+            else {
+                eprintln!("--timeout expects a number of seconds\n{USAGE}");
+                return ExitCode::FAILURE;
+            };
+            limits.timeout = Some(seconds);
+        } else if arg == "--max-commands" {
+            let Some(count) = args.next().and_then(|count| count.parse().ok()) else {
+                eprintln!("--max-commands expects a number of commands\n{USAGE}");
+                return ExitCode::FAILURE;
+            };
+            limits.max_commands = Some(count);
+        } else if arg == "--unrestricted" {
+            sandbox = Sandbox::Unrestricted;
+        } else if arg == "--locale" {
+            let Some(value) = args.next() else {
+                eprintln!("--locale expects a locale like `de`\n{USAGE}");
+                return ExitCode::FAILURE;
+            };
+            locale = value;
+        } else if script.is_none() {
+            script = Some(PathBuf::from(arg));
+        } else if level_path.is_none() {
+            level_path = Some(arg);
+        } else {
+            eprintln!("unexpected argument `{arg}`\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let Some(script) = script else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    let level = match Level::load(level_path.as_deref().unwrap_or(LEVEL_PATH)) {
+        Ok(level) => level,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let api = match Api::load("apis/robot.api.json") {
+        Ok(api) => api,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    // hide all functions the level doesn't allow
+    let api = match level.restrict(&api) {
+        Ok(api) => api.localized(&locale),
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let is_lua = script
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("lua"));
+    let report = if is_lua {
+        headless::run_lua(script, &level, &api, limits, sandbox)
+    } else {
+        headless::run_python(script, &level, &api, limits, sandbox)
+    };
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+
+    // returning from `main` also ends a script which couldn't be stopped
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,50 @@
+#![allow(
+    unused_crate_dependencies,
+    reason = "the dependencies are shared with the library"
+)]
+#![allow(missing_docs, reason = "the binary is documented by its module docs")]
+#![allow(clippy::print_stderr, reason = "load and write errors go to stderr")]
+
+//! Writes the Lua definitions of the robot api, which editors use for completion and hints.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    process::ExitCode,
+};
+
+use gam3du::{
+    api::{Api, FALLBACK_LOCALE},
+    bindgen,
+};
+
+const API_PATH: &str = "apis/robot.api.json";
+const DEFINITIONS_PATH: &str = "lua/robot_api.lua";
+
+fn main() -> ExitCode {
+    // the documentation will be generated in the given language
+    let locale = std::env::args()
+        .skip_while(|arg| arg != "--locale")
+        .nth(1)
+        .unwrap_or_else(|| FALLBACK_LOCALE.to_owned());
+
+    let api = match Api::load(API_PATH) {
+        Ok(api) => api.localized(&locale),
+        Err(error) => {
+            eprintln!("{API_PATH}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(error) = write(DEFINITIONS_PATH, &api) {
+        eprintln!("{DEFINITIONS_PATH}: {error}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn write(path: &str, api: &Api) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    bindgen::lua::generate(&mut out, api)?;
+    out.flush()
+}
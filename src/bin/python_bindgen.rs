@@ -1,65 +1,62 @@
-#![allow(unused_crate_dependencies)]
-#![allow(missing_docs)]
-#![allow(clippy::print_stdout)]
-#![allow(clippy::unwrap_used)]
-
-use std::io::BufWriter;
-
-use gam3du::{api::Api, bindgen};
-
-fn main() {
-    // let move_forward = FunctionDescriptor {
-    //     name: Identifier("move forward".into()),
-    //     caption: RichText(
-    //         "Makes the robot move to the next tile in its current orientation".into(),
-    //     ),
-    //     description: RichText(
-    //         "Makes the robot move to the next tile in its current orientation".into(),
-    //     ),
-    //     parameters: Vec::new(),
-    //     returns: None,
-    // };
-
-    // let turn_left = FunctionDescriptor {
-    //     name: Identifier("turn left".into()),
-    //     caption: RichText("Turns the robot 45° in a counter-clockwise direction".into()),
-    //     description: RichText("Turns the robot 45° in a counter-clockwise direction".into()),
-    //     parameters: Vec::new(),
-    //     returns: None,
-    // };
-
-    // let turn_right = FunctionDescriptor {
-    //     name: Identifier("turn right".into()),
-    //     caption: RichText("Turns the robot 45° in a clockwise direction".into()),
-    //     description: RichText("Turns the robot 45° in a clockwise direction".into()),
-    //     parameters: Vec::new(),
-    //     returns: None,
-    // };
-
-    // let api = Api {
-    //     name: Identifier("robot".into()),
-    //     caption: RichText(
-    //         "A simple robot that can be moved across a 2D-plane and draw lines".into(),
-    //     ),
-    //     description: RichText(
-    //         "Once upon a time there was a lonely robot with the serial number `#C0D1E`. …".into(),
-    //     ),
-    //     functions: vec![move_forward, turn_left, turn_right],
-    // };
-
-    // let json_string = serde_json::to_string_pretty(&api).unwrap();
-    // println!("{json_string}");
-
-    // let json_string = json5::to_string(&api).unwrap();
-
-    let api_json = std::fs::read_to_string("apis/robot.api.json").unwrap();
-    let api: Api = serde_json::from_str(&api_json).unwrap();
-    println!("{api:#?}");
+#![allow(
+    unused_crate_dependencies,
+    reason = "the dependencies are shared with the library"
+)]
+#![allow(missing_docs, reason = "the binary is documented by its module docs")]
+#![allow(clippy::print_stderr, reason = "load and write errors go to stderr")]
+
+//! Writes the Python module and stub of the robot api, which editors use for completion and hints.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    process::ExitCode,
+};
+
+use gam3du::{
+    api::{Api, FALLBACK_LOCALE},
+    bindgen,
+};
+
+const API_PATH: &str = "apis/robot.api.json";
+const MODULE_PATH: &str = "python/robot_api.py";
+const STUB_PATH: &str = "python/robot_api.pyi";
+
+fn main() -> ExitCode {
+    // the documentation will be generated in the given language
+    let locale = std::env::args()
+        .skip_while(|arg| arg != "--locale")
+        .nth(1)
+        .unwrap_or_else(|| FALLBACK_LOCALE.to_owned());
+
+    let api = match Api::load(API_PATH) {
+        Ok(api) => api.localized(&locale),
+        Err(error) => {
+            eprintln!("{API_PATH}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let outputs: [(&str, Generator); 2] = [
+        (MODULE_PATH, bindgen::python::generate),
+        (STUB_PATH, bindgen::python::generate_stub),
+    ];
+    let mut code = ExitCode::SUCCESS;
+    for (path, generate) in outputs {
+        if let Err(error) = write(path, &api, generate) {
+            eprintln!("{path}: {error}");
+            code = ExitCode::FAILURE;
+        }
+    }
+    code
+}
 
-    // let mut out = String::new();
-    let out_file = std::fs::File::create("python/robot_api.py").unwrap();
-    let mut out = BufWriter::new(out_file);
-    bindgen::python::generate(&mut out, &api).unwrap();
+/// Generates one of the files from the api.
+type Generator = fn(&mut BufWriter<File>, &Api) -> io::Result<()>;
 
-    // println!("{out}");
+/// Writes the output of a generator into a file.
+fn write(path: &str, api: &Api, generate: Generator) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    generate(&mut out, api)?;
+    out.flush()
 }
@@ -8,74 +8,182 @@
 #![allow(clippy::missing_panics_doc)]
 #![allow(missing_docs)]
 #![allow(clippy::print_stdout)]
+#![allow(clippy::print_stderr, reason = "usage and load errors go to stderr")]
 #![allow(clippy::unwrap_used)]
 #![allow(clippy::expect_used)]
 #![allow(clippy::indexing_slicing)]
 #![allow(clippy::panic)]
 
-use std::sync::mpsc::Sender;
-use std::{sync::mpsc::channel, thread};
+//! Opens a window showing the robot of the demo level, which is controlled by `python/robot.py`
+//! and by clients of the HTTP control server.
+//!
+//! Usage: `python_robot [--http-address <address>] [--http-port <port>] [--no-http]`
+//!
+//! The control server only listens on `localhost:8000` unless told otherwise; pass
+//! `--http-address 0.0.0.0` to make it reachable from other machines. `--no-http` doesn't start it
+//! at all.
 
-use gam3du::api::{Api, Identifier};
-use gam3du::logging::init_logger;
-use gam3du::python::runner;
-use gam3du::{framework, Command};
-use tiny_http::{Response, Server};
+use std::{process::ExitCode, sync::mpsc::channel, thread};
 
-fn main() {
+use gam3du::api::Api;
+use gam3du::level::Level;
+use gam3du::logging::init_logger;
+use gam3du::python::{runner, CancelHandle, Limits, Sandbox};
+use gam3du::{
+    command::event_channel,
+    framework, http,
+    monitor::{Activity, Monitor, OutputStream},
+};
+use tiny_http::Server;
+
+const LEVEL_PATH: &str = "levels/demo.level.json5";
+const DEFAULT_HTTP_ADDRESS: &str = "127.0.0.1";
+const DEFAULT_HTTP_PORT: u16 = 8000;
+const USAGE: &str =
+    "usage: python_robot [--http-address <address>] [--http-port <port>] [--no-http]";
+
+fn main() -> ExitCode {
     //ecs_test();
 
     init_logger();
 
-    let api_json = std::fs::read_to_string("apis/robot.api.json").unwrap();
-    let api: Api = serde_json::from_str(&api_json).unwrap();
+    // fail before opening the window if another instance already took the port
+    let server = match http_server() {
+        Ok(server) => server,
+        Err(message) => {
+            eprintln!("{message}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let api = match Api::load("apis/robot.api.json") {
+        Ok(api) => api,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let level = match Level::load(LEVEL_PATH) {
+        Ok(level) => level,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    // hide all functions the level doesn't allow
+    let api = match level.restrict(&api) {
+        Ok(api) => api,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
 
     let (command_sender, command_receiver) = channel();
+    let (event_sender, event_receiver) = event_channel(&api);
+
+    // lets clients of the web server follow what is going on
+    let monitor = Monitor::default();
+
+    // the script's output is only published, so it's printed from here
+    let _output_thread = {
+        let activities = monitor.subscribe();
+        thread::spawn(move || {
+            for activity in activities {
+                if let Activity::Output { stream, text } = activity {
+                    match stream {
+                        OutputStream::Stdout => println!("{text}"),
+                        OutputStream::Stderr => eprintln!("{text}"),
+                    }
+                }
+            }
+        })
+    };
 
+    let cancel = CancelHandle::new();
     let python_thread = {
-        let source_path = "python/test.py";
+        let source_path = "python/robot.py";
         let command_sender = command_sender.clone();
         let api = api.clone();
-        thread::spawn(move || runner(&source_path, command_sender, &api))
+        let cancel = cancel.clone();
+        let monitor = monitor.clone();
+        thread::spawn(move || {
+            // the error has already been logged by the runner
+            let _result = runner(
+                source_path,
+                command_sender,
+                event_receiver,
+                &api,
+                Limits::default(),
+                &cancel,
+                &Sandbox::student(),
+                &monitor,
+            );
+        })
     };
 
-    let webserver_tread = {
+    // the web server will be shut down along with the process
+    let _webserver_thread = server.map(|server| {
         let command_sender = command_sender.clone();
         let api = api.clone();
-        thread::spawn(move || http_server(&command_sender, &api))
-    };
-
-    pollster::block_on(framework::start("demo scene".into(), command_receiver));
+        let monitor = monitor.clone();
+        thread::spawn(move || http::serve(&server, &command_sender, &api, &monitor))
+    });
+
+    pollster::block_on(framework::start(
+        "demo scene".into(),
+        command_receiver,
+        event_sender,
+        monitor,
+        level,
+    ));
     // FIXME on Windows the window will still be unresponsively lingering until the control was given back to the OS (maybe a bug in `winit`)
 
+    // the script might still be running, but there's nobody left to watch it
+    cancel.cancel();
     python_thread.join().unwrap();
-    webserver_tread.join().unwrap();
+    ExitCode::SUCCESS
 }
 
-fn http_server(command_sender: &Sender<Command>, api: &Api) {
-    let server = Server::http("0.0.0.0:8000").unwrap();
-
-    for request in server.incoming_requests() {
-        let url = request.url();
-        let Some(url) = url.strip_prefix(&format!("/{}/", api.name)) else {
-            request
-                .respond(Response::from_string("unknown api").with_status_code(404))
-                .unwrap();
-            continue;
-        };
-
-        let command = Command {
-            name: Identifier(url.to_owned()),
-        };
-
-        let response = Response::from_string(format!("{command:?}"));
-
-        command_sender
-            .send(Command {
-                name: Identifier(url.to_owned()),
-            })
-            .unwrap();
-
-        request.respond(response).unwrap();
+/// Starts the HTTP control server as told by the command line, unless it has been switched off.
+fn http_server() -> Result<Option<Server>, String> {
+    let mut http_address = DEFAULT_HTTP_ADDRESS.to_owned();
+    let mut http_port = DEFAULT_HTTP_PORT;
+    let mut http_enabled = true;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--http-address" {
+            http_address = args.next().ok_or_else(|| {
+                format!("--http-address expects an address like `127.0.0.1`\n{USAGE}")
+            })?;
+        } else if arg == "--http-port" {
+            http_port = args
+                .next()
+                .and_then(|port| port.parse().ok())
+                .ok_or_else(|| format!("--http-port expects a port number\n{USAGE}"))?;
+        } else if arg == "--no-http" {
+            http_enabled = false;
+        } else {
+            return Err(format!("unexpected argument `{arg}`\n{USAGE}"));
+        }
+    }
+    if !http_enabled {
+        return Ok(None);
     }
+
+    // brackets keep the port apart from an IPv6 address
+    let address = if http_address.contains(':') {
+        format!("[{http_address}]:{http_port}")
+    } else {
+        format!("{http_address}:{http_port}")
+    };
+    let server = Server::http(&address).map_err(|error| {
+        format!(
+            "failed to start the HTTP control server at {address}: {error}\n\
+            use --http-port to choose another port or --no-http to go without"
+        )
+    })?;
+    log::info!("the HTTP control server listens at http://{address}");
+    Ok(Some(server))
 }
@@ -1 +1,3 @@
+pub mod lua;
+pub mod openapi;
 pub mod python;
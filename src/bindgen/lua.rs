@@ -0,0 +1,417 @@
+//! Generates Lua bindings for an [`Api`].
+//!
+//! The engine provides the api as a table at runtime, so the generated file only contains
+//! documentation and annotations in the format of the Lua language server (`---@meta`). Editors
+//! will use it for autocompletion and inline help.
+
+use std::io::{self, Write};
+
+use crate::api::{
+    Api, EventDescriptor, FunctionDescriptor, Identifier, ParameterDescriptor, TypeDefinition,
+    TypeDescriptor, TypeKind, Value,
+};
+
+/// indentation of nested blocks
+const INDENT: &str = "    ";
+
+/// keywords which can't be used as identifiers
+const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// global functions and libraries which would be shadowed
+const BUILTINS: &[&str] = &[
+    "assert", "error", "ipairs", "math", "next", "pairs", "pcall", "print", "require", "select",
+    "self", "string", "table", "tonumber", "tostring", "type", "utf8",
+];
+
+/// Generates the annotations of the given api for the Lua language server.
+pub fn generate(out: &mut impl Write, api: &Api) -> io::Result<()> {
+    let module = module_name(api);
+
+    writeln!(out, "---@meta {module}")?;
+    writeln!(out)?;
+    write_comment(
+        out,
+        "",
+        &paragraphs(&api.caption.to_string(), &api.description.to_string()),
+    )?;
+    writeln!(out, "---@class {module}")?;
+    writeln!(out, "local {module} = {{}}")?;
+    writeln!(out)?;
+
+    for definition in &api.types {
+        generate_type(out, api, definition)?;
+    }
+
+    for function in &api.functions {
+        generate_function(out, api, function)?;
+    }
+
+    for event in &api.events {
+        generate_event(out, api, event)?;
+    }
+    if !api.events.is_empty() {
+        write_comment(
+            out,
+            "",
+            &[
+                "Waits for events and calls their handlers".into(),
+                "Events are also handled after each call of another function of this module. \
+                 Without `seconds`, this function waits until the script gets stopped."
+                    .into(),
+            ],
+        )?;
+        writeln!(
+            out,
+            "---@param seconds? number Number of seconds to wait for, at least `0`."
+        )?;
+        writeln!(out, "function {module}.wait_for_events(seconds) end")?;
+        writeln!(out)?;
+    }
+
+    writeln!(out, "return {module}")?;
+
+    Ok(())
+}
+
+/// Generates a class for a record or a table of constants for an enum.
+///
+/// Records are passed as plain tables, enums as the technical names of their variants.
+pub fn generate_type(
+    out: &mut impl Write,
+    api: &Api,
+    definition: &TypeDefinition,
+) -> io::Result<()> {
+    let name = class_name(&definition.name);
+    write_comment(
+        out,
+        "",
+        &paragraphs(
+            &definition.caption.to_string(),
+            &definition.description.to_string(),
+        ),
+    )?;
+
+    match definition.kind {
+        TypeKind::Record(ref fields) => {
+            writeln!(out, "---@class {name}")?;
+            for field in fields {
+                writeln!(
+                    out,
+                    "---@field {field} {typ} {docs}",
+                    field = identifier(&field.name),
+                    typ = typ(&field.typ),
+                    docs = parameter_docs(field, &field.description.to_string())
+                )?;
+            }
+        }
+        TypeKind::Enum(ref variants) => {
+            writeln!(out, "---@enum {name}")?;
+            writeln!(out, "{module}.{name} = {{", module = module_name(api))?;
+            for variant in variants {
+                write_comment(
+                    out,
+                    INDENT,
+                    &paragraphs(
+                        &variant.caption.to_string(),
+                        &variant.description.to_string(),
+                    ),
+                )?;
+                writeln!(
+                    out,
+                    "{INDENT}{member} = {value},",
+                    member = enum_member(&variant.name),
+                    value = literal_string(&variant.name.0)
+                )?;
+            }
+            writeln!(out, "}}")?;
+        }
+    }
+    writeln!(out)?;
+
+    Ok(())
+}
+
+pub fn generate_function(
+    out: &mut impl Write,
+    api: &Api,
+    function: &FunctionDescriptor,
+) -> io::Result<()> {
+    let FunctionDescriptor {
+        ref name,
+        ref caption,
+        ref description,
+        ref parameters,
+        ref returns,
+        ref errors,
+        read_only: _,
+    } = *function;
+
+    let mut docs = paragraphs(&caption.to_string(), &description.to_string());
+    if !errors.is_empty() {
+        let mut lines = vec!["Fails with".to_owned()];
+        lines.extend(
+            errors
+                .iter()
+                .map(|error| format!("- `{}`: {}", error.name, error.caption)),
+        );
+        docs.push(lines.join("\n"));
+    }
+    write_comment(out, "", &docs)?;
+
+    for parameter in parameters {
+        let mut text = parameter_docs(parameter, &parameter.description.to_string());
+        if let Some(ref default) = parameter.default {
+            text = format!(
+                "{text} Defaults to `{}`.",
+                literal(default, &parameter.typ, &api.types)
+            );
+        }
+        writeln!(
+            out,
+            "---@param {name}{optional} {typ} {text}",
+            name = identifier(&parameter.name),
+            optional = if parameter.default.is_some() { "?" } else { "" },
+            typ = typ(&parameter.typ)
+        )?;
+    }
+    if let Some(ref returns) = *returns {
+        writeln!(
+            out,
+            "---@return {typ} {name} {docs}",
+            typ = typ(&returns.typ),
+            name = identifier(&returns.name),
+            docs = parameter_docs(returns, &returns.description.to_string())
+        )?;
+    }
+
+    let parameters: Vec<_> = parameters
+        .iter()
+        .map(|parameter| identifier(&parameter.name))
+        .collect();
+    writeln!(
+        out,
+        "function {module}.{name}({parameters}) end",
+        module = module_name(api),
+        name = identifier(name),
+        parameters = parameters.join(", ")
+    )?;
+    writeln!(out)?;
+
+    Ok(())
+}
+
+/// Generates the function which registers handlers for the given event.
+pub fn generate_event(out: &mut impl Write, api: &Api, event: &EventDescriptor) -> io::Result<()> {
+    let payload = event
+        .payload
+        .iter()
+        .map(|parameter| format!("{}: {}", identifier(&parameter.name), typ(&parameter.typ)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let handler = format!("fun({payload})");
+
+    let mut docs = vec![format!(
+        "Registers a function to be called on the event: {}",
+        event.caption
+    )];
+    let description = event.description.to_string();
+    if !description.is_empty() {
+        docs.push(description);
+    }
+    write_comment(out, "", &docs)?;
+    for parameter in &event.payload {
+        writeln!(
+            out,
+            "---The handler receives `{name}`: {docs}",
+            name = identifier(&parameter.name),
+            docs = parameter_docs(parameter, &parameter.description.to_string())
+        )?;
+    }
+    writeln!(out, "---@param handler {handler}")?;
+    writeln!(out, "---@return {handler}")?;
+    writeln!(
+        out,
+        "function {module}.{name}(handler) end",
+        module = module_name(api),
+        name = event_handler(&event.name)
+    )?;
+    writeln!(out)?;
+
+    Ok(())
+}
+
+/// Writes the paragraphs of a documentation comment, separated by empty comment lines.
+fn write_comment(out: &mut impl Write, indent: &str, paragraphs: &[String]) -> io::Result<()> {
+    for (index, paragraph) in paragraphs.iter().enumerate() {
+        if index > 0 {
+            writeln!(out, "{indent}---")?;
+        }
+        for line in paragraph.lines() {
+            writeln!(out, "{indent}---{line}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the summary and — if it adds anything — the description of a documented element.
+fn paragraphs(caption: &str, description: &str) -> Vec<String> {
+    let mut paragraphs = vec![caption.to_owned()];
+    if !description.is_empty() && description != caption {
+        paragraphs.push(description.to_owned());
+    }
+    paragraphs
+}
+
+/// Describes a parameter using the given text and the range of valid values if restricted.
+fn parameter_docs(parameter: &ParameterDescriptor, text: &str) -> String {
+    let mut docs = if text.is_empty() {
+        parameter.caption.to_string()
+    } else {
+        text.to_owned()
+    };
+    if !docs.ends_with('.') {
+        docs.push('.');
+    }
+    if let TypeDescriptor::Integer(ref range) = parameter.typ {
+        if let Some(last) = range.end.checked_sub(1) {
+            docs = format!("{docs} Ranges from `{}` to `{last}`.", range.start);
+        }
+    }
+    docs
+}
+
+/// Returns the name of the global table scripts use to access the given api.
+#[must_use]
+pub fn module_name(api: &Api) -> String {
+    format!("{}_api", snake_case(&api.name))
+}
+
+/// Converts an identifier into the `snake_case` naming convention used for Lua functions and
+/// variables.
+///
+/// Names which clash with keywords or builtins get a trailing underscore.
+#[must_use]
+pub fn identifier(identifier: &Identifier) -> String {
+    let name = snake_case(identifier);
+    if KEYWORDS.contains(&name.as_str()) || BUILTINS.contains(&name.as_str()) {
+        format!("{name}_")
+    } else {
+        name
+    }
+}
+
+fn snake_case(identifier: &Identifier) -> String {
+    let name = identifier.0.replace(' ', "_");
+    if name.starts_with(|first: char| first.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+/// Returns the name of the function which registers handlers for the given event.
+#[must_use]
+pub fn event_handler(event: &Identifier) -> String {
+    format!("on_{}", event.0.replace(' ', "_"))
+}
+
+/// Converts an identifier into the `UPPER_CASE` naming convention used for constants.
+#[must_use]
+pub fn enum_member(identifier: &Identifier) -> String {
+    snake_case(identifier).to_uppercase()
+}
+
+/// Converts an identifier into the `CamelCase` naming convention used for classes.
+#[must_use]
+pub fn class_name(identifier: &Identifier) -> String {
+    let name: String = identifier
+        .0
+        .split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                format!("{}{}", first.to_ascii_uppercase(), chars.as_str())
+            })
+        })
+        .collect();
+    if name.starts_with(|first: char| first.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+/// Returns a Lua string literal of the given text.
+fn literal_string(text: &str) -> String {
+    // JSON string literals are valid Lua string literals
+    serde_json::to_string(text).expect("strings can always be serialized")
+}
+
+/// Converts a value of the given type into a Lua expression.
+#[must_use]
+pub fn literal(value: &Value, typ: &TypeDescriptor, types: &[TypeDefinition]) -> String {
+    match *value {
+        Value::Integer(integer) => integer.to_string(),
+        // the debug representation always contains a decimal point
+        Value::Float(float) if float.is_finite() => format!("{float:?}"),
+        Value::Float(float) if float.is_nan() => "0/0".into(),
+        Value::Float(float) if float.is_sign_negative() => "-math.huge".into(),
+        Value::Float(_) => "math.huge".into(),
+        Value::Boolean(boolean) => boolean.to_string(),
+        Value::String(ref string) | Value::Enum(Identifier(ref string)) => literal_string(string),
+        Value::List(ref elements) => {
+            let element_type = match *typ {
+                TypeDescriptor::List(ref element_type) => &**element_type,
+                _ => typ,
+            };
+            let elements: Vec<_> = elements
+                .iter()
+                .map(|element| literal(element, element_type, types))
+                .collect();
+            format!("{{{}}}", elements.join(", "))
+        }
+        Value::Record(ref values) => {
+            let TypeDescriptor::Named(ref name) = *typ else {
+                return "nil".into();
+            };
+            // keep the order of the definition rather than the alphabetical one of the value
+            let fields = types
+                .iter()
+                .find(|definition| definition.name == *name)
+                .and_then(|definition| match definition.kind {
+                    TypeKind::Record(ref fields) => Some(fields.as_slice()),
+                    TypeKind::Enum(_) => None,
+                })
+                .unwrap_or_default();
+            let fields: Vec<_> = fields
+                .iter()
+                .filter_map(|field| {
+                    values.get(&field.name).map(|field_value| {
+                        format!(
+                            "{} = {}",
+                            identifier(&field.name),
+                            literal(field_value, &field.typ, types)
+                        )
+                    })
+                })
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+    }
+}
+
+/// Returns the type annotation of the given type.
+#[must_use]
+pub fn typ(descriptor: &TypeDescriptor) -> String {
+    match *descriptor {
+        TypeDescriptor::Integer(_) => "integer".into(),
+        TypeDescriptor::Float => "number".into(),
+        TypeDescriptor::Boolean => "boolean".into(),
+        TypeDescriptor::String => "string".into(),
+        TypeDescriptor::List(ref element_type) => format!("{}[]", typ(element_type)),
+        TypeDescriptor::Named(ref name) => class_name(name),
+    }
+}
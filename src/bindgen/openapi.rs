@@ -0,0 +1,605 @@
+//! Generates an [OpenAPI](https://spec.openapis.org/oas/v3.1.0) document for an [`Api`].
+//!
+//! The document describes how to call the api via the HTTP control server (see [`crate::http`]):
+//! each function is an operation `POST /<api>/<function>` taking its arguments as a json object.
+//! The state of the engine is available as `GET /<api>/state` and its activity as a stream of
+//! events at [`EVENTS_PATH`]. Values are represented as described by [`Value::to_json`].
+//!
+//! [`Value::to_json`]: crate::api::Value::to_json
+
+use std::io::{self, Write};
+
+use serde_json::{json, Map, Value as Json};
+
+use crate::{
+    api::{
+        Api, FunctionDescriptor, Identifier, ParameterDescriptor, TypeDefinition, TypeDescriptor,
+        TypeKind,
+    },
+    http::EVENTS_PATH,
+};
+
+/// path at which the HTTP control server serves the document
+pub const DOCUMENT_PATH: &str = "/openapi.json";
+
+/// version of the specification the document follows
+const OPENAPI_VERSION: &str = "3.1.0";
+
+/// Writes the document of the given api as json.
+pub fn generate(out: &mut impl Write, api: &Api) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, &document(api))?;
+    writeln!(out)
+}
+
+/// Returns the document of the given api.
+#[must_use]
+pub fn document(api: &Api) -> Json {
+    let mut paths: Map<String, Json> = api
+        .functions
+        .iter()
+        .map(|function| (path(api, function), json!({ "post": operation(function) })))
+        .collect();
+    if api.state_functions().next().is_some() {
+        // a function called `state` shares the path, but not the method
+        paths.entry(state_path(api)).or_insert_with(|| json!({}))["get"] = state_operation(api);
+    }
+    paths.insert(EVENTS_PATH.to_owned(), json!({ "get": events_operation() }));
+    let schemas: Map<String, Json> = api
+        .types
+        .iter()
+        .map(|definition| (schema_name(&definition.name), type_schema(definition)))
+        .collect();
+
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": api.name.0,
+            "summary": api.caption.to_string(),
+            "description": api.description.to_string(),
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+        },
+    })
+}
+
+/// Returns the path at which the given function can be called.
+#[must_use]
+pub fn path(api: &Api, function: &FunctionDescriptor) -> String {
+    // identifiers consist of `a-z`, `0-9` and spaces, so the latter are the only ones to escape
+    format!("/{}/{}", api.name, function.name).replace(' ', "%20")
+}
+
+/// Returns the path at which the state of the engine can be queried.
+#[must_use]
+pub fn state_path(api: &Api) -> String {
+    format!("/{}/state", api.name)
+}
+
+/// Returns the key of a type within the schemas of the document, e.g. `LinePattern`.
+///
+/// Keys may only consist of letters, digits, `.`, `-` and `_`.
+#[must_use]
+pub fn schema_name(name: &Identifier) -> String {
+    name.0
+        .split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                format!("{}{}", first.to_ascii_uppercase(), chars.as_str())
+            })
+        })
+        .collect()
+}
+
+fn operation(function: &FunctionDescriptor) -> Json {
+    let mut description = function.description.to_string();
+    if !function.errors.is_empty() {
+        let errors: Vec<String> = function
+            .errors
+            .iter()
+            .map(|error| format!("- `{}`: {}", error.name, error.caption))
+            .collect();
+        description = format!("{description}\n\nFails with\n{}", errors.join("\n"));
+    }
+
+    let mut responses = json!({
+        "200": response(function),
+        "400": error_response("The request is no valid json", &["malformed request"]),
+        "422": error_response(
+            "The arguments don't fit the parameters of the function",
+            &["invalid arguments"],
+        ),
+        "503": error_response("The engine has been shut down", &["shut down"]),
+    });
+    if !function.errors.is_empty() {
+        let kinds: Vec<&str> = function
+            .errors
+            .iter()
+            .map(|error| error.name.0.as_str())
+            .collect();
+        responses["409"] = error_response("The engine refused to execute the function", &kinds);
+    }
+
+    let mut operation = json!({
+        "operationId": function.name.0.replace(' ', "_"),
+        "summary": function.caption.to_string(),
+        "description": description,
+        "responses": responses,
+    });
+    if let Some(request_body) = request_body(function) {
+        operation["requestBody"] = request_body;
+    }
+    operation
+}
+
+/// Describes the query of the values of all read-only functions without parameters.
+fn state_operation(api: &Api) -> Json {
+    let properties: Map<String, Json> = api
+        .state_functions()
+        .filter_map(|function| {
+            let returns = function.returns.as_ref()?;
+            let mut schema = parameter_schema(returns);
+            schema["description"] = json!(function.caption.to_string());
+            Some((function.name.0.clone(), schema))
+        })
+        .collect();
+    let required: Vec<&String> = properties.keys().collect();
+    json!({
+        // the ids of functions are in lower case, so they never clash with this one
+        "operationId": "getState",
+        "summary": "Tells the state of the engine",
+        "description": "Tells the values of all functions which neither change anything nor take \
+            any arguments",
+        "responses": {
+            "200": {
+                "description": "The values keyed by the names of the functions",
+                "content": {
+                    "application/json": {
+                        "schema": {
+                            "type": "object",
+                            "properties": properties,
+                            "required": required,
+                        },
+                    },
+                },
+            },
+            "503": error_response("The engine has been shut down", &["shut down"]),
+        },
+    })
+}
+
+/// Describes the stream of the activity of the engine and its scripts.
+fn events_operation() -> Json {
+    json!({
+        "operationId": "streamEvents",
+        "summary": "Streams what the engine and its scripts are doing",
+        "description": "Sends a server-sent event for each processed function call, completed \
+            animation, line of output and script error. The name of each event is the `type` of \
+            the json object within its data.",
+        "responses": {
+            "200": {
+                "description": "The stream of events, which lasts until the client disconnects",
+                "content": {
+                    "text/event-stream": {
+                        "schema": { "type": "string" },
+                    },
+                },
+            },
+        },
+    })
+}
+
+/// Describes the arguments as a json object with one property per parameter.
+///
+/// Returns `None` if the function has no parameters.
+fn request_body(function: &FunctionDescriptor) -> Option<Json> {
+    if function.parameters.is_empty() {
+        return None;
+    }
+    let properties: Map<String, Json> = function
+        .parameters
+        .iter()
+        .map(|parameter| (parameter.name.0.clone(), parameter_schema(parameter)))
+        .collect();
+    let required: Vec<&str> = function
+        .parameters
+        .iter()
+        .filter(|parameter| parameter.default.is_none())
+        .map(|parameter| parameter.name.0.as_str())
+        .collect();
+
+    let mut schema = json!({
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    });
+    if !required.is_empty() {
+        schema["required"] = json!(required);
+    }
+    Some(json!({
+        // a missing body is the same as passing no arguments at all
+        "required": !required.is_empty(),
+        "content": {
+            "application/json": { "schema": schema },
+        },
+    }))
+}
+
+/// Describes the reply to a successful call, which wraps the returned value as `result`.
+fn response(function: &FunctionDescriptor) -> Json {
+    let (description, result) = match function.returns {
+        Some(ref returns) => (returns.caption.to_string(), parameter_schema(returns)),
+        None => (
+            "The function has been executed".to_owned(),
+            json!({ "type": "null" }),
+        ),
+    };
+    json!({
+        "description": description,
+        "content": {
+            "application/json": {
+                "schema": {
+                    "type": "object",
+                    "properties": { "result": result },
+                    "required": ["result"],
+                },
+            },
+        },
+    })
+}
+
+/// Describes the reply to a failed call with one of the given kinds of errors.
+fn error_response(description: &str, kinds: &[&str]) -> Json {
+    json!({
+        "description": description,
+        "content": {
+            "application/json": {
+                "schema": {
+                    "type": "object",
+                    "properties": {
+                        "error": {
+                            "type": "object",
+                            "properties": {
+                                "kind": { "type": "string", "enum": kinds },
+                                "message": { "type": "string" },
+                            },
+                            "required": ["kind", "message"],
+                        },
+                    },
+                    "required": ["error"],
+                },
+            },
+        },
+    })
+}
+
+fn type_schema(definition: &TypeDefinition) -> Json {
+    let mut schema = match definition.kind {
+        TypeKind::Record(ref fields) => {
+            let properties: Map<String, Json> = fields
+                .iter()
+                .map(|field| (field.name.0.clone(), parameter_schema(field)))
+                .collect();
+            let required: Vec<&str> = fields.iter().map(|field| field.name.0.as_str()).collect();
+            json!({
+                "type": "object",
+                "properties": properties,
+                "required": required,
+                "additionalProperties": false,
+            })
+        }
+        TypeKind::Enum(ref variants) => {
+            let names: Vec<&str> = variants
+                .iter()
+                .map(|variant| variant.name.0.as_str())
+                .collect();
+            json!({
+                "type": "string",
+                "enum": names,
+            })
+        }
+    };
+    schema["title"] = json!(definition.caption.to_string());
+    schema["description"] = json!(definition.description.to_string());
+    schema
+}
+
+/// Returns the schema of a parameter, including its documentation and default value.
+fn parameter_schema(parameter: &ParameterDescriptor) -> Json {
+    let mut schema = schema(&parameter.typ);
+    schema["description"] = json!(parameter.description.to_string());
+    if let Some(ref default) = parameter.default {
+        schema["default"] = default.to_json();
+    }
+    schema
+}
+
+/// Returns the [JSON Schema](https://json-schema.org/) of the given type.
+#[must_use]
+pub fn schema(typ: &TypeDescriptor) -> Json {
+    match *typ {
+        TypeDescriptor::Integer(ref range) => json!({
+            "type": "integer",
+            "minimum": range.start,
+            // the end of the range is exclusive
+            "maximum": range.end - 1,
+        }),
+        TypeDescriptor::Float => json!({ "type": "number" }),
+        TypeDescriptor::Boolean => json!({ "type": "boolean" }),
+        TypeDescriptor::String => json!({ "type": "string" }),
+        TypeDescriptor::List(ref element_type) => json!({
+            "type": "array",
+            "items": schema(element_type),
+        }),
+        TypeDescriptor::Named(ref name) => json!({
+            "$ref": format!("#/components/schemas/{}", schema_name(name)),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// An api with a function for each kind of parameter, one without any and one for the state.
+    fn api() -> Api {
+        let text = |text: &str| json!({ "name": text, "caption": text, "description": text });
+        let api: Api = serde_json::from_value(json!({
+            "name": "test",
+            "caption": "A test",
+            "description": "Tests the document",
+            "types": [
+                {
+                    "name": "orientation",
+                    "caption": "Where the robot looks",
+                    "description": "One of the four directions",
+                    "kind": { "Enum": [text("north"), text("south east")] },
+                },
+                {
+                    "name": "position",
+                    "caption": "A tile",
+                    "description": "The coordinates of a tile",
+                    "kind": { "Record": [
+                        { "name": "x", "caption": "x", "description": "column", "typ": "String" },
+                        { "name": "y", "caption": "y", "description": "row", "typ": "Boolean" },
+                    ] },
+                },
+            ],
+            "functions": [
+                {
+                    "name": "move to",
+                    "caption": "Moves the robot",
+                    "description": "Moves the robot to a tile",
+                    "parameters": [
+                        {
+                            "name": "target",
+                            "caption": "goal",
+                            "description": "Where to go",
+                            "typ": { "Named": "position" },
+                        },
+                        {
+                            "name": "facing",
+                            "caption": "direction",
+                            "description": "Direction to face",
+                            "typ": { "Named": "orientation" },
+                            "default": { "Enum": "south east" },
+                        },
+                        {
+                            "name": "stops",
+                            "caption": "stops",
+                            "description": "Steps between stops",
+                            "typ": { "List": { "Integer": { "start": 1, "end": 5 } } },
+                            "default": { "List": [{ "Integer": 1 }, { "Integer": 4 }] },
+                        },
+                    ],
+                    "errors": [text("blocked")],
+                },
+                {
+                    "name": "stop",
+                    "caption": "Stops the robot",
+                    "description": "Stops the robot",
+                    "parameters": [],
+                },
+                {
+                    "name": "position",
+                    "caption": "Tells the position",
+                    "description": "Tells the position of the robot",
+                    "parameters": [],
+                    "returns": {
+                        "name": "position",
+                        "caption": "The current position",
+                        "description": "The tile the robot is on",
+                        "typ": { "Named": "position" },
+                    },
+                    "read_only": true,
+                },
+            ],
+        }))
+        .unwrap();
+        api.validate().unwrap();
+        api
+    }
+
+    /// Looks up a part of the document by its JSON Pointer (RFC 6901).
+    fn at<'json>(document: &'json Json, pointer: &str) -> &'json Json {
+        document
+            .pointer(pointer)
+            .unwrap_or_else(|| panic!("`{pointer}` is missing from {document:#}"))
+    }
+
+    fn keys(object: &Json) -> Vec<&str> {
+        let mut keys: Vec<&str> = object
+            .as_object()
+            .expect("an object")
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    #[test]
+    fn describes_one_operation_per_function() {
+        let api = api();
+        let document = document(&api);
+        assert_eq!(at(&document, "/openapi"), "3.1.0");
+        assert_eq!(at(&document, "/info/title"), "test");
+        assert_eq!(at(&document, "/info/summary"), "A test");
+        assert_eq!(
+            keys(at(&document, "/paths")),
+            [
+                "/events",
+                "/test/move%20to",
+                "/test/position",
+                "/test/state",
+                "/test/stop"
+            ]
+        );
+        for function in &api.functions {
+            let operations = at(&document, "/paths").get(path(&api, function)).unwrap();
+            assert_eq!(keys(operations), ["post"]);
+            assert_eq!(
+                operations.pointer("/post/operationId"),
+                Some(&json!(function.name.0.replace(' ', "_")))
+            );
+        }
+        assert_eq!(keys(at(&document, "/paths/~1test~1state")), ["get"]);
+        assert_eq!(keys(at(&document, "/paths/~1events")), ["get"]);
+    }
+
+    #[test]
+    fn describes_arguments() {
+        let document = document(&api());
+        let body = at(&document, "/paths/~1test~1move%20to/post/requestBody");
+        assert_eq!(at(body, "/required"), true);
+        assert_eq!(
+            at(body, "/content/application~1json/schema"),
+            &json!({
+                "type": "object",
+                "properties": {
+                    "target": {
+                        "$ref": "#/components/schemas/Position",
+                        "description": "Where to go",
+                    },
+                    "facing": {
+                        "$ref": "#/components/schemas/Orientation",
+                        "description": "Direction to face",
+                        "default": "south east",
+                    },
+                    "stops": {
+                        "type": "array",
+                        // the end of the range is exclusive
+                        "items": { "type": "integer", "minimum": 1, "maximum": 4 },
+                        "description": "Steps between stops",
+                        "default": [1, 4],
+                    },
+                },
+                "required": ["target"],
+                "additionalProperties": false,
+            })
+        );
+    }
+
+    #[test]
+    fn describes_named_types() {
+        let document = document(&api());
+        assert_eq!(
+            at(&document, "/components/schemas/Orientation"),
+            &json!({
+                "type": "string",
+                "enum": ["north", "south east"],
+                "title": "Where the robot looks",
+                "description": "One of the four directions",
+            })
+        );
+        assert_eq!(
+            at(&document, "/components/schemas/Position"),
+            &json!({
+                "type": "object",
+                "properties": {
+                    "x": { "type": "string", "description": "column" },
+                    "y": { "type": "boolean", "description": "row" },
+                },
+                "required": ["x", "y"],
+                "additionalProperties": false,
+                "title": "A tile",
+                "description": "The coordinates of a tile",
+            })
+        );
+    }
+
+    #[test]
+    fn describes_results_and_errors() {
+        let document = document(&api());
+        let stop = at(&document, "/paths/~1test~1stop/post");
+        assert_eq!(stop.get("requestBody"), None);
+        assert_eq!(keys(at(stop, "/responses")), ["200", "400", "422", "503"]);
+        assert_eq!(
+            at(
+                stop,
+                "/responses/200/content/application~1json/schema/properties/result"
+            ),
+            &json!({ "type": "null" })
+        );
+
+        let move_to = at(&document, "/paths/~1test~1move%20to/post/responses");
+        assert_eq!(
+            at(
+                move_to,
+                "/409/content/application~1json/schema/properties/error/properties/kind/enum"
+            ),
+            &json!(["blocked"])
+        );
+
+        let position = at(&document, "/paths/~1test~1position/post/responses/200");
+        assert_eq!(
+            at(
+                position,
+                "/content/application~1json/schema/properties/result"
+            ),
+            &json!({
+                "$ref": "#/components/schemas/Position",
+                "description": "The tile the robot is on",
+            })
+        );
+    }
+
+    #[test]
+    fn shares_the_path_of_the_state_with_a_function() {
+        let mut api = api();
+        let mut state = api.function("stop").unwrap().clone();
+        state.name = Identifier("state".to_owned());
+        api.functions.push(state);
+        let document = document(&api);
+        let operations = at(&document, "/paths/~1test~1state");
+        assert_eq!(keys(operations), ["get", "post"]);
+        assert_eq!(at(operations, "/get/operationId"), "getState");
+        assert_eq!(at(operations, "/post/operationId"), "state");
+    }
+
+    #[test]
+    fn describes_the_state() {
+        let document = document(&api());
+        assert_eq!(
+            at(
+                &document,
+                "/paths/~1test~1state/get/responses/200/content/application~1json/schema"
+            ),
+            &json!({
+                "type": "object",
+                "properties": {
+                    "position": {
+                        "$ref": "#/components/schemas/Position",
+                        "description": "Tells the position",
+                    },
+                },
+                "required": ["position"],
+            })
+        );
+    }
+}
@@ -1,69 +1,1003 @@
+//! Generates Python bindings for an [`Api`].
+//!
+//! The generated module is a shim which works in- and outside of the engine: Within the engine,
+//! it re-exports the native module (e.g. `_robot_api`). Run by a plain Python interpreter, it
+//! forwards each call to the HTTP control server of a running engine instead. Its documentation
+//! and type hints are meant for editors and language servers, which will use them for
+//! autocompletion and inline help. An accompanying stub (`.pyi`) carries the same information for
+//! tools which prefer stubs over sources.
+
 use std::io::{self, Write};
 
-use crate::api::{Api, FunctionDescriptor, Identifier, ParameterDescriptor, TypeDescriptor};
+use crate::api::{
+    Api, EventDescriptor, FunctionDescriptor, Identifier, ParameterDescriptor, TypeDefinition,
+    TypeDescriptor, TypeKind, Value,
+};
+
+/// indentation of nested blocks as recommended by PEP 8
+const INDENT: &str = "    ";
+
+/// keywords which can't be used as identifiers
+const KEYWORDS: &[&str] = &[
+    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif",
+    "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda",
+    "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with", "yield",
+];
+
+/// builtin functions, which would be shadowed, and names the generated code relies on
+const BUILTINS: &[&str] = &[
+    "abs",
+    "all",
+    "any",
+    "bin",
+    "bool",
+    "bytes",
+    "callable",
+    "chr",
+    "dict",
+    "dir",
+    "divmod",
+    "enumerate",
+    "filter",
+    "float",
+    "format",
+    "hash",
+    "help",
+    "hex",
+    "id",
+    "input",
+    "int",
+    "isinstance",
+    "iter",
+    "len",
+    "list",
+    "map",
+    "max",
+    "min",
+    "next",
+    "object",
+    "oct",
+    "open",
+    "ord",
+    "pow",
+    "print",
+    "range",
+    "repr",
+    "reversed",
+    "round",
+    "set",
+    "slice",
+    "sorted",
+    "str",
+    "sum",
+    "super",
+    "tuple",
+    "type",
+    "vars",
+    "zip",
+    "self",
+    "handler",
+];
+
+/// builtin classes, constants and imported names which would be shadowed by a class
+const CLASS_NAMES: &[&str] = &[
+    "None",
+    "True",
+    "False",
+    "Ellipsis",
+    "NotImplemented",
+    "BaseException",
+    "Exception",
+    "Enum",
+    "Callable",
+    "Literal",
+];
+
+/// environment variable telling the shim where to find the engine's HTTP control server
+const URL_VARIABLE: &str = "GAM3DU_URL";
+
+/// address of the engine's HTTP control server if [`URL_VARIABLE`] isn't set
+const DEFAULT_URL: &str = "http://localhost:8000";
+
+/// Forwards calls of the shim to the HTTP control server.
+///
+/// `API_ERROR` and `API_NAME` will be replaced by the base class of all errors and the quoted
+/// name of the api.
+const HTTP_CLIENT: &str = r#"def _call(function, arguments, returns):
+    # only reached outside of the engine, as the native module replaces all functions
+    import json
+    import os
+    import urllib.error
+    import urllib.parse
+    import urllib.request
+
+    base_url = os.environ.get("URL_VARIABLE", "DEFAULT_URL").rstrip("/")
+    path = urllib.parse.quote(API_NAME) + "/" + urllib.parse.quote(function)
+    request = urllib.request.Request(
+        f"{base_url}/{path}",
+        data=json.dumps(arguments).encode(),
+        headers={"Content-Type": "application/json"},
+        method="POST",
+    )
+    try:
+        with urllib.request.urlopen(request) as response:
+            reply = json.loads(response.read())
+    except urllib.error.HTTPError as error:
+        try:
+            failure = json.loads(error.read())["error"]
+        except (ValueError, KeyError, TypeError):
+            raise API_ERROR(f"the engine failed with status {error.code}") from None
+        raise _ERRORS.get(failure["kind"], API_ERROR)(failure["message"]) from None
+    except urllib.error.URLError as error:
+        raise API_ERROR(f"the engine is not reachable at {base_url}: {error.reason}") from None
+
+    if returns is None:
+        return None
+    return _from_json(reply["result"], returns)
+
+
+def _to_json(value, hint):
+    if isinstance(hint, list):
+        return [_to_json(element, hint[0]) for element in value]
+    if hint in _ENUMS:
+        # accepts members as well as their values
+        return hint(value).value
+    fields = _FIELDS.get(hint)
+    if fields is not None:
+        def field(attribute):
+            return value[attribute] if isinstance(value, dict) else getattr(value, attribute)
+
+        return {
+            name: _to_json(field(attribute), field_hint)
+            for name, (attribute, field_hint) in fields.items()
+        }
+    return value
 
+
+def _from_json(value, hint):
+    if isinstance(hint, list):
+        return [_from_json(element, hint[0]) for element in value]
+    if hint in _ENUMS:
+        return hint(value)
+    fields = _FIELDS.get(hint)
+    if fields is not None:
+        return hint(**{
+            attribute: _from_json(value[name], field_hint)
+            for name, (attribute, field_hint) in fields.items()
+        })
+    return value
+"#;
+
+/// Whether bindings are generated for a regular module or for a stub
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Module,
+    Stub,
+}
+
+/// Generates a Python module containing the documentation and type hints of the given api.
 pub fn generate(out: &mut impl Write, api: &Api) -> io::Result<()> {
-    // TODO add documentation comments for api
+    generate_all(out, api, Target::Module)
+}
+
+/// Generates a stub (`.pyi`) containing the type hints of the given api.
+pub fn generate_stub(out: &mut impl Write, api: &Api) -> io::Result<()> {
+    generate_all(out, api, Target::Stub)
+}
+
+fn generate_all(out: &mut impl Write, api: &Api, target: Target) -> io::Result<()> {
+    let mut docs = paragraphs(&api.caption.to_string(), &api.description.to_string());
+    if target == Target::Module {
+        docs.push(format!(
+            "Within the engine, this module uses the native module `{native}`. Elsewhere, it \
+             calls the HTTP control server of a running engine, which is expected at \
+             `{DEFAULT_URL}` unless the environment variable `{URL_VARIABLE}` tells otherwise. \
+             Events will only be delivered within the engine.",
+            native = native_module_name(api)
+        ));
+    }
+    write_docstring(out, "", &docs)?;
+    writeln!(out)?;
+
+    if target == Target::Module {
+        generate_prelude(out, api)?;
+    } else if has_enums(api) {
+        writeln!(out, "from enum import Enum")?;
+    }
+    let mut typing = Vec::new();
+    if !api.events.is_empty() {
+        typing.push("Callable");
+    }
+    if uses_enum_parameters(api) {
+        typing.push("Literal");
+    }
+    if !typing.is_empty() {
+        let typing = typing.join(", ");
+        if target == Target::Module {
+            // `typing` is only needed for annotations, but would drag in modules like `os`,
+            // which aren't available in the sandbox of the engine
+            writeln!(out)?;
+            writeln!(out, "TYPE_CHECKING = False")?;
+            writeln!(out, "if TYPE_CHECKING:")?;
+            writeln!(out, "{INDENT}from typing import {typing}")?;
+        } else {
+            writeln!(out, "from typing import {typing}")?;
+        }
+    }
+    writeln!(out)?;
+    if target == Target::Module {
+        writeln!(out)?;
+    }
+
+    api.types
+        .iter()
+        .try_for_each(|definition| write_type(out, definition, target))?;
+    write_errors(out, api, target)?;
+    if target == Target::Module {
+        write_type_hints(out, api)?;
+    }
 
     api.functions
         .iter()
-        .try_for_each(|function| generate_function(out, function))?;
+        .try_for_each(|function| write_function(out, api, function, target))?;
+
+    write_events(out, api, target)?;
+
+    if target == Target::Module {
+        let client = HTTP_CLIENT
+            .replace("URL_VARIABLE", URL_VARIABLE)
+            .replace("DEFAULT_URL", DEFAULT_URL)
+            .replace("API_ERROR", &api_error_class(&api.name))
+            .replace("API_NAME", &literal_string(&api.name.0));
+        writeln!(out, "{client}")?;
+        writeln!(out)?;
+        writeln!(out, "try:")?;
+        writeln!(
+            out,
+            "{INDENT}from {native} import *  # noqa: F403",
+            native = native_module_name(api)
+        )?;
+        writeln!(out, "except ImportError:")?;
+        writeln!(out, "{INDENT}pass")?;
+    }
 
     Ok(())
 }
 
-pub fn generate_function(out: &mut impl Write, function: &FunctionDescriptor) -> io::Result<()> {
+/// Writes the tables the HTTP client uses to convert values of named types to and from JSON and to
+/// raise the errors declared by the api.
+fn write_type_hints(out: &mut impl Write, api: &Api) -> io::Result<()> {
+    let enums: Vec<_> = api
+        .types
+        .iter()
+        .filter(|definition| matches!(definition.kind, TypeKind::Enum(_)))
+        .map(|definition| class_name(&definition.name))
+        .collect();
+    match *enums.as_slice() {
+        [ref single] => writeln!(out, "_ENUMS = ({single},)")?,
+        _ => writeln!(out, "_ENUMS = ({})", enums.join(", "))?,
+    }
+
+    let records: Vec<_> = api
+        .types
+        .iter()
+        .filter_map(|definition| match definition.kind {
+            TypeKind::Record(ref fields) => Some((class_name(&definition.name), fields)),
+            TypeKind::Enum(_) => None,
+        })
+        .collect();
+    let errors: Vec<_> = api
+        .errors()
+        .into_iter()
+        .map(|error| {
+            format!(
+                "{}: {}",
+                literal_string(&error.name.0),
+                error_class(&api.name, &error.name)
+            )
+        })
+        .collect();
+    writeln!(out, "_ERRORS = {{{}}}", errors.join(", "))?;
+
+    if records.is_empty() {
+        writeln!(out, "_FIELDS = {{}}")?;
+    } else {
+        writeln!(out, "_FIELDS = {{")?;
+        for (name, fields) in records {
+            let fields: Vec<_> = fields
+                .iter()
+                .map(|field| {
+                    format!(
+                        "{}: ({}, {})",
+                        literal_string(&field.name.0),
+                        literal_string(&identifier(&field.name)),
+                        hint(&field.typ)
+                    )
+                })
+                .collect();
+            writeln!(out, "{INDENT}{name}: {{{}}},", fields.join(", "))?;
+        }
+        writeln!(out, "}}")?;
+    }
+    writeln!(out)?;
+    writeln!(out)?;
+
+    Ok(())
+}
+
+/// Generates the imports the classes of [`generate_types`] depend upon.
+///
+/// Annotations are never evaluated, so that classes may refer to each other regardless of their
+/// order.
+pub fn generate_prelude(out: &mut impl Write, api: &Api) -> io::Result<()> {
+    writeln!(out, "from __future__ import annotations")?;
+    if has_enums(api) {
+        writeln!(out, "from enum import Enum")?;
+    }
+    Ok(())
+}
+
+/// Generates a class for each named type of the api.
+///
+/// Records become plain classes with one attribute per field, enums derive from `Enum` and use
+/// the technical names of their variants as values.
+pub fn generate_types(out: &mut impl Write, api: &Api) -> io::Result<()> {
+    api.types
+        .iter()
+        .try_for_each(|definition| write_type(out, definition, Target::Module))
+}
+
+/// Generates the exception hierarchy of the api.
+///
+/// All errors the engine reports derive from a common base class; each declared error gets its
+/// own subclass, so that scripts can handle them selectively.
+pub fn generate_errors(out: &mut impl Write, api: &Api) -> io::Result<()> {
+    write_errors(out, api, Target::Module)
+}
+
+fn write_errors(out: &mut impl Write, api: &Api, target: Target) -> io::Result<()> {
+    let base = api_error_class(&api.name);
+    writeln!(out, "class {base}(Exception):")?;
+    write_docstring(
+        out,
+        INDENT,
+        &[format!(
+            "Base class of all errors reported by the {} api",
+            api.name
+        )],
+    )?;
+    writeln!(out)?;
+    if target == Target::Module {
+        writeln!(out)?;
+    }
+
+    for error in api.errors() {
+        writeln!(
+            out,
+            "class {name}({base}):",
+            name = error_class(&api.name, &error.name)
+        )?;
+        write_docstring(
+            out,
+            INDENT,
+            &paragraphs(&error.caption.to_string(), &error.description.to_string()),
+        )?;
+        writeln!(out)?;
+        if target == Target::Module {
+            writeln!(out)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_type(out: &mut impl Write, definition: &TypeDefinition, target: Target) -> io::Result<()> {
+    let name = class_name(&definition.name);
+    let mut docs = paragraphs(
+        &definition.caption.to_string(),
+        &definition.description.to_string(),
+    );
+
+    match definition.kind {
+        TypeKind::Record(ref fields) => {
+            docs.push(section(
+                "Attributes",
+                fields.iter().map(|field| {
+                    (
+                        identifier(&field.name),
+                        parameter_docs(field, &field.description.to_string()),
+                    )
+                }),
+            ));
+
+            writeln!(out, "class {name}:")?;
+            write_docstring(out, INDENT, &docs)?;
+            writeln!(out)?;
+
+            for field in fields {
+                writeln!(
+                    out,
+                    "{INDENT}{name}: {typ}",
+                    name = identifier(&field.name),
+                    typ = typ(&field.typ)
+                )?;
+            }
+            writeln!(out)?;
+
+            let parameters: Vec<_> = fields
+                .iter()
+                .map(|field| format!("{}: {}", identifier(&field.name), typ(&field.typ)))
+                .collect();
+            let fields: Vec<_> = fields.iter().map(|field| identifier(&field.name)).collect();
+
+            write!(
+                out,
+                "{INDENT}def __init__(self, {}) -> None:",
+                parameters.join(", ")
+            )?;
+            if target == Target::Stub {
+                writeln!(out, " ...")?;
+                writeln!(out, "{INDENT}def __repr__(self) -> str: ...")?;
+                writeln!(out, "{INDENT}def __eq__(self, other: object) -> bool: ...")?;
+            } else {
+                writeln!(out)?;
+                for field in &fields {
+                    writeln!(out, "{INDENT}{INDENT}self.{field} = {field}")?;
+                }
+                writeln!(out)?;
+
+                let repr = fields
+                    .iter()
+                    .map(|field| format!("{field}={{self.{field}!r}}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                writeln!(out, "{INDENT}def __repr__(self) -> str:")?;
+                writeln!(out, "{INDENT}{INDENT}return f\"{name}({repr})\"")?;
+                writeln!(out)?;
+
+                let equal = fields
+                    .iter()
+                    .map(|field| format!("self.{field} == other.{field}"))
+                    .collect::<Vec<_>>()
+                    .join(" and ");
+                writeln!(out, "{INDENT}def __eq__(self, other: object) -> bool:")?;
+                writeln!(
+                    out,
+                    "{INDENT}{INDENT}return isinstance(other, {name}) and {equal}"
+                )?;
+            }
+        }
+        TypeKind::Enum(ref variants) => {
+            writeln!(out, "class {name}(Enum):")?;
+            write_docstring(out, INDENT, &docs)?;
+            for variant in variants {
+                writeln!(out)?;
+                writeln!(
+                    out,
+                    "{INDENT}{member} = \"{value}\"",
+                    member = enum_member(&variant.name),
+                    value = variant.name
+                )?;
+                write_docstring(
+                    out,
+                    INDENT,
+                    &paragraphs(
+                        &variant.caption.to_string(),
+                        &variant.description.to_string(),
+                    ),
+                )?;
+            }
+        }
+    }
+    writeln!(out)?;
+    if target == Target::Module {
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+fn write_function(
+    out: &mut impl Write,
+    api: &Api,
+    function: &FunctionDescriptor,
+    target: Target,
+) -> io::Result<()> {
     let FunctionDescriptor {
         ref name,
-        caption: _,
-        description: _,
+        ref caption,
+        ref description,
         ref parameters,
         ref returns,
+        ref errors,
+        read_only: _,
     } = *function;
 
-    // TODO add documentation comments for function and parameters
-
     write!(out, "def {name}(", name = identifier(name))?;
-
     for (index, parameter) in parameters.iter().enumerate() {
         if index > 0 {
             write!(out, ", ")?;
         }
-        generate_parameter(out, parameter)?;
+        write_parameter(out, parameter, &api.types, target)?;
     }
+    let returned = returns
+        .as_ref()
+        .map_or_else(|| "None".into(), |returns| typ(&returns.typ));
+    writeln!(out, ") -> {returned}:")?;
 
-    write!(out, ")")?;
-
+    let mut docs = paragraphs(&caption.to_string(), &description.to_string());
+    if !parameters.is_empty() {
+        docs.push(section(
+            "Args",
+            parameters.iter().map(|parameter| {
+                let mut text = parameter_docs(parameter, &parameter.description.to_string());
+                if let Some(ref default) = parameter.default {
+                    text = format!(
+                        "{text} Defaults to `{}`.",
+                        literal(default, &parameter.typ, &api.types)
+                    );
+                }
+                (identifier(&parameter.name), text)
+            }),
+        ));
+    }
     if let Some(ref returns) = *returns {
-        write!(out, " -> {typ}", typ = typ(&returns.typ))?;
+        docs.push(section(
+            "Returns",
+            [(
+                typ(&returns.typ),
+                parameter_docs(returns, &returns.description.to_string()),
+            )],
+        ));
+    }
+    if !errors.is_empty() {
+        docs.push(section(
+            "Raises",
+            errors.iter().map(|error| {
+                (
+                    error_class(&api.name, &error.name),
+                    error.caption.to_string(),
+                )
+            }),
+        ));
+    }
+    write_docstring(out, INDENT, &docs)?;
+    match target {
+        Target::Module => {
+            let arguments: Vec<_> = parameters
+                .iter()
+                .map(|parameter| {
+                    format!(
+                        "{}: _to_json({}, {})",
+                        literal_string(&parameter.name.0),
+                        identifier(&parameter.name),
+                        hint(&parameter.typ)
+                    )
+                })
+                .collect();
+            let converter = returns
+                .as_ref()
+                .map_or_else(|| "None".into(), |returns| hint(&returns.typ));
+            writeln!(
+                out,
+                "{INDENT}return _call({function}, {{{arguments}}}, {converter})",
+                function = literal_string(&name.0),
+                arguments = arguments.join(", ")
+            )?;
+            writeln!(out)?;
+            writeln!(out)?;
+        }
+        Target::Stub => {
+            writeln!(out, "{INDENT}...")?;
+            writeln!(out)?;
+        }
     }
-    writeln!(out, ":")?;
 
-    writeln!(out, "\tpass")?;
+    Ok(())
+}
+
+fn write_events(out: &mut impl Write, api: &Api, target: Target) -> io::Result<()> {
+    if api.events.is_empty() {
+        return Ok(());
+    }
+
+    for event in &api.events {
+        write_event(out, event, target)?;
+    }
+
+    writeln!(
+        out,
+        "def wait_for_events(seconds: float | None = {}) -> None:",
+        {
+            if target == Target::Stub {
+                "..."
+            } else {
+                "None"
+            }
+        }
+    )?;
+    write_docstring(
+        out,
+        INDENT,
+        &[
+            "Waits for events and calls their handlers".into(),
+            "Events are also handled after each call of another function of this module. \
+             Without `seconds`, this function waits until the script gets stopped."
+                .into(),
+            section(
+                "Args",
+                [(
+                    "seconds".into(),
+                    "Number of seconds to wait for, at least `0`.".into(),
+                )],
+            ),
+        ],
+    )?;
+    match target {
+        Target::Module => {
+            writeln!(out, "{INDENT}import time")?;
+            writeln!(out, "{INDENT}if seconds is not None:")?;
+            writeln!(out, "{INDENT}{INDENT}time.sleep(seconds)")?;
+            writeln!(out, "{INDENT}{INDENT}return")?;
+            writeln!(
+                out,
+                "{INDENT}# no events arrive outside of the engine; wait until being interrupted"
+            )?;
+            writeln!(out, "{INDENT}while True:")?;
+            writeln!(out, "{INDENT}{INDENT}time.sleep(60)")?;
+            writeln!(out)?;
+            writeln!(out)?;
+        }
+        Target::Stub => writeln!(out, "{INDENT}...")?,
+    }
+
+    Ok(())
+}
+
+fn write_event(out: &mut impl Write, event: &EventDescriptor, target: Target) -> io::Result<()> {
+    let payload = event
+        .payload
+        .iter()
+        .map(|parameter| typ(&parameter.typ))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let handler = format!("Callable[[{payload}], None]");
+
+    writeln!(
+        out,
+        "def {name}(handler: {handler}) -> {handler}:",
+        name = event_decorator(&event.name)
+    )?;
+    let mut docs = vec![
+        format!(
+            "Registers the decorated function as handler of the event: {}",
+            event.caption
+        ),
+        event.description.to_string(),
+    ];
+    docs.retain(|paragraph| !paragraph.is_empty());
+    if !event.payload.is_empty() {
+        docs.push(section(
+            "The handler receives",
+            event.payload.iter().map(|parameter| {
+                (
+                    identifier(&parameter.name),
+                    parameter_docs(parameter, &parameter.description.to_string()),
+                )
+            }),
+        ));
+    }
+    write_docstring(out, INDENT, &docs)?;
+    if target == Target::Stub {
+        writeln!(out, "{INDENT}...")?;
+    } else {
+        writeln!(out, "{INDENT}return handler")?;
+    }
     writeln!(out)?;
+    if target == Target::Module {
+        writeln!(out)?;
+    }
 
     Ok(())
 }
 
-pub fn generate_parameter(out: &mut impl Write, parameter: &ParameterDescriptor) -> io::Result<()> {
+fn write_parameter(
+    out: &mut impl Write,
+    parameter: &ParameterDescriptor,
+    types: &[TypeDefinition],
+    target: Target,
+) -> io::Result<()> {
     let ParameterDescriptor {
         ref name,
         caption: _,
         description: _,
-        typ: _,
+        ref typ,
+        ref default,
     } = *parameter;
 
-    write!(out, "{name}", name = identifier(name))?;
+    write!(
+        out,
+        "{name}: {typ}",
+        name = identifier(name),
+        typ = input_type(typ, types)
+    )?;
+    if let Some(ref default) = *default {
+        match target {
+            Target::Module => write!(out, " = {}", literal(default, typ, types))?,
+            Target::Stub => write!(out, " = ...")?,
+        }
+    }
 
     Ok(())
 }
 
+/// Writes a docstring following PEP 257.
+///
+/// The first paragraph is the summary line. Docstrings with a single paragraph fit into a single
+/// line.
+fn write_docstring(out: &mut impl Write, indent: &str, paragraphs: &[String]) -> io::Result<()> {
+    let [ref summary, ref rest @ ..] = *paragraphs else {
+        return Ok(());
+    };
+    if rest.is_empty() {
+        return writeln!(out, "{indent}\"\"\"{}\"\"\"", docstring(summary));
+    }
+
+    writeln!(out, "{indent}\"\"\"{}", docstring(summary))?;
+    for paragraph in rest {
+        writeln!(out)?;
+        for line in paragraph.lines() {
+            if line.is_empty() {
+                writeln!(out)?;
+            } else {
+                writeln!(out, "{indent}{}", docstring(line))?;
+            }
+        }
+    }
+    writeln!(out, "{indent}\"\"\"")
+}
+
+/// Returns the summary and — if it adds anything — the description of a documented element.
+fn paragraphs(caption: &str, description: &str) -> Vec<String> {
+    let mut paragraphs = vec![caption.to_owned()];
+    if !description.is_empty() && description != caption {
+        paragraphs.push(description.to_owned());
+    }
+    paragraphs
+}
+
+/// Returns a section of a docstring in the style of the Google Python Style Guide.
+fn section(title: &str, entries: impl IntoIterator<Item = (String, String)>) -> String {
+    let mut lines = vec![format!("{title}:")];
+    lines.extend(
+        entries
+            .into_iter()
+            .map(|(name, text)| format!("{INDENT}{name}: {text}")),
+    );
+    lines.join("\n")
+}
+
+/// Describes a parameter using the given text and the range of valid values if restricted.
+fn parameter_docs(parameter: &ParameterDescriptor, text: &str) -> String {
+    let mut docs = if text.is_empty() {
+        parameter.caption.to_string()
+    } else {
+        text.to_owned()
+    };
+    if !docs.ends_with('.') {
+        docs.push('.');
+    }
+    if let TypeDescriptor::Integer(ref range) = parameter.typ {
+        if let Some(last) = range.end.checked_sub(1) {
+            docs = format!("{docs} Ranges from `{}` to `{last}`.", range.start);
+        }
+    }
+    docs
+}
+
+fn has_enums(api: &Api) -> bool {
+    api.types
+        .iter()
+        .any(|definition| matches!(definition.kind, TypeKind::Enum(_)))
+}
+
+/// Returns `true` if any function accepts the values of an enum, which will be spelled out as
+/// `Literal`.
+fn uses_enum_parameters(api: &Api) -> bool {
+    api.functions
+        .iter()
+        .flat_map(|function| &function.parameters)
+        .any(|parameter| input_type(&parameter.typ, &api.types).contains("Literal["))
+}
+
+/// Converts an identifier into the `snake_case` naming convention used for Python functions and
+/// variables.
+///
+/// Names which clash with keywords or builtins get a trailing underscore as recommended by PEP 8.
 #[must_use]
 pub fn identifier(identifier: &Identifier) -> String {
-    // TODO add safeguards against reserved keywords
-    identifier.0.replace(' ', "_")
+    let name = snake_case(identifier);
+    if KEYWORDS.contains(&name.as_str()) || BUILTINS.contains(&name.as_str()) {
+        format!("{name}_")
+    } else {
+        name
+    }
+}
+
+fn snake_case(identifier: &Identifier) -> String {
+    let name = identifier.0.replace(' ', "_");
+    if name.starts_with(|first: char| first.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+/// Returns the name of the module scripts import to use the given api.
+#[must_use]
+pub fn module_name(api: &Api) -> String {
+    format!("{}_api", snake_case(&api.name))
+}
+
+/// Returns the name of the native module the engine provides for the given api.
+///
+/// Scripts don't import it directly, but use the module of [`module_name`], which re-exports it.
+#[must_use]
+pub fn native_module_name(api: &Api) -> String {
+    format!("_{}", module_name(api))
+}
+
+/// Returns the name of the exception class all errors of the given api derive from.
+#[must_use]
+pub fn api_error_class(api: &Identifier) -> String {
+    format!("{}Error", camel_case(api))
+}
+
+/// Returns the name of the exception class of an error declared by the given api.
+#[must_use]
+pub fn error_class(api: &Identifier, error: &Identifier) -> String {
+    format!("{}{}Error", camel_case(api), camel_case(error))
+}
+
+/// Escapes a text to be placed between triple quotes.
+#[must_use]
+pub fn docstring(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Returns the name of the decorator which registers handlers for the given event.
+#[must_use]
+pub fn event_decorator(event: &Identifier) -> String {
+    format!("on_{}", event.0.replace(' ', "_"))
 }
 
+/// Converts an identifier into the `UPPER_CASE` naming convention used for members of Python enums.
+#[must_use]
+pub fn enum_member(identifier: &Identifier) -> String {
+    snake_case(identifier).to_uppercase()
+}
+
+/// Converts an identifier into the `CamelCase` naming convention used for Python classes.
+///
+/// Names which clash with builtin classes or constants get a trailing underscore.
+#[must_use]
+pub fn class_name(identifier: &Identifier) -> String {
+    let name = camel_case(identifier);
+    if CLASS_NAMES.contains(&name.as_str()) {
+        format!("{name}_")
+    } else {
+        name
+    }
+}
+
+fn camel_case(identifier: &Identifier) -> String {
+    let name: String = identifier
+        .0
+        .split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                format!("{}{}", first.to_ascii_uppercase(), chars.as_str())
+            })
+        })
+        .collect();
+    if name.starts_with(|first: char| first.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+/// Converts a value of the given type into a Python expression.
+///
+/// # Panics
+///
+/// Panics if the value doesn't match its type, which a validated [`Api`] rules out.
+#[must_use]
+pub fn literal(value: &Value, typ: &TypeDescriptor, types: &[TypeDefinition]) -> String {
+    match *value {
+        Value::Integer(integer) => integer.to_string(),
+        // the debug representation always contains a decimal point
+        Value::Float(float) if float.is_finite() => format!("{float:?}"),
+        Value::Float(float) => format!("float(\"{float}\")"),
+        Value::Boolean(true) => "True".into(),
+        Value::Boolean(false) => "False".into(),
+        Value::String(ref string) => literal_string(string),
+        Value::List(ref elements) => {
+            let TypeDescriptor::List(ref element_type) = *typ else {
+                mismatch(value, typ)
+            };
+            let elements: Vec<_> = elements
+                .iter()
+                .map(|element| literal(element, element_type, types))
+                .collect();
+            format!("[{}]", elements.join(", "))
+        }
+        Value::Record(ref values) => {
+            let TypeDescriptor::Named(ref name) = *typ else {
+                mismatch(value, typ)
+            };
+            let Some(fields) = types
+                .iter()
+                .find(|definition| definition.name == *name)
+                .and_then(|definition| match definition.kind {
+                    TypeKind::Record(ref fields) => Some(fields),
+                    TypeKind::Enum(_) => None,
+                })
+            else {
+                mismatch(value, typ)
+            };
+            // keep the order of the definition rather than the alphabetical one of the value
+            let arguments: Vec<_> = fields
+                .iter()
+                .filter_map(|field| {
+                    values.get(&field.name).map(|field_value| {
+                        format!(
+                            "{}={}",
+                            identifier(&field.name),
+                            literal(field_value, &field.typ, types)
+                        )
+                    })
+                })
+                .collect();
+            format!("{}({})", class_name(name), arguments.join(", "))
+        }
+        Value::Enum(ref variant) => {
+            let TypeDescriptor::Named(ref name) = *typ else {
+                mismatch(value, typ)
+            };
+            format!("{}.{}", class_name(name), enum_member(variant))
+        }
+    }
+}
+
+#[expect(
+    clippy::panic,
+    reason = "the bindings of an unvalidated api would be wrong anyway"
+)]
+fn mismatch(value: &Value, typ: &TypeDescriptor) -> ! {
+    panic!("the value {value} doesn't match its type {typ}; validate the api first")
+}
+
+/// Returns a Python string literal of the given text.
+fn literal_string(text: &str) -> String {
+    // JSON string literals are valid Python string literals
+    serde_json::to_string(text).expect("strings can always be serialized")
+}
+
+/// Returns the expression the HTTP client of the shim uses to convert values of the given type.
+fn hint(descriptor: &TypeDescriptor) -> String {
+    match *descriptor {
+        TypeDescriptor::List(ref element_type) => format!("[{}]", hint(element_type)),
+        TypeDescriptor::Integer(_)
+        | TypeDescriptor::Float
+        | TypeDescriptor::Boolean
+        | TypeDescriptor::String
+        | TypeDescriptor::Named(_) => typ(descriptor),
+    }
+}
+
+/// Returns the type hint of values the engine hands over to the script.
 #[must_use]
 pub fn typ(descriptor: &TypeDescriptor) -> String {
     match *descriptor {
@@ -72,5 +1006,225 @@ pub fn typ(descriptor: &TypeDescriptor) -> String {
         TypeDescriptor::Boolean => "bool".into(),
         TypeDescriptor::String => "str".into(),
         TypeDescriptor::List(ref element_type) => format!("list[{}]", typ(element_type)),
+        TypeDescriptor::Named(ref name) => class_name(name),
+    }
+}
+
+/// Returns the type hint of values a script may pass to the engine.
+///
+/// Enums may also be passed by the technical names of their variants.
+#[must_use]
+pub fn input_type(descriptor: &TypeDescriptor, types: &[TypeDefinition]) -> String {
+    match *descriptor {
+        TypeDescriptor::List(ref element_type) => {
+            format!("list[{}]", input_type(element_type, types))
+        }
+        TypeDescriptor::Named(ref name) => {
+            let variants = types
+                .iter()
+                .find(|definition| definition.name == *name)
+                .and_then(|definition| match definition.kind {
+                    TypeKind::Enum(ref variants) => Some(variants),
+                    TypeKind::Record(_) => None,
+                });
+            match variants {
+                Some(variants) => {
+                    let names = variants
+                        .iter()
+                        .map(|variant| format!("\"{}\"", variant.name))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    format!("{} | Literal[{names}]", class_name(name))
+                }
+                None => class_name(name),
+            }
+        }
+        TypeDescriptor::Integer(_)
+        | TypeDescriptor::Float
+        | TypeDescriptor::Boolean
+        | TypeDescriptor::String => typ(descriptor),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// An api using keywords and builtins as names, enums, records and lists with defaults.
+    fn api() -> Api {
+        let coordinate = json!({ "Integer": { "start": 0, "end": 10 } });
+        let api: Api = serde_json::from_value(json!({
+            "name": "robot",
+            "caption": "A robot",
+            "description": "A robot",
+            "types": [
+                {
+                    "name": "orientation",
+                    "caption": "Where the robot looks",
+                    "description": "Where the robot looks",
+                    "kind": { "Enum": [
+                        { "name": "north", "caption": "up", "description": "up" },
+                        {
+                            "name": "south east",
+                            "caption": "down right",
+                            "description": "down right",
+                        },
+                    ] },
+                },
+                {
+                    "name": "position",
+                    "caption": "A tile",
+                    "description": "A tile",
+                    "kind": { "Record": [
+                        { "name": "x", "caption": "x", "description": "x", "typ": coordinate },
+                        { "name": "y", "caption": "y", "description": "y", "typ": coordinate },
+                    ] },
+                },
+            ],
+            "functions": [{
+                "name": "from",
+                "caption": "Moves the robot",
+                "description": "Moves the robot somewhere",
+                "parameters": [
+                    {
+                        "name": "list",
+                        "caption": "steps",
+                        "description": "Steps to take",
+                        "typ": { "List": { "Integer": { "start": 1, "end": 5 } } },
+                        "default": { "List": [{ "Integer": 1 }, { "Integer": 2 }] },
+                    },
+                    {
+                        "name": "facing",
+                        "caption": "direction",
+                        "description": "Direction to face",
+                        "typ": { "Named": "orientation" },
+                        "default": { "Enum": "south east" },
+                    },
+                    {
+                        "name": "target",
+                        "caption": "goal",
+                        "description": "Where to go",
+                        "typ": { "Named": "position" },
+                        "default": { "Record": { "y": { "Integer": 2 }, "x": { "Integer": 1 } } },
+                    },
+                ],
+            }],
+        }))
+        .unwrap();
+        api.validate().unwrap();
+        api
+    }
+
+    fn module(api: &Api) -> String {
+        let mut out = Vec::new();
+        generate(&mut out, api).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn stub(api: &Api) -> String {
+        let mut out = Vec::new();
+        generate_stub(&mut out, api).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn renames_keywords_and_builtins() {
+        let name = |name: &str| identifier(&Identifier(name.to_owned()));
+        assert_eq!(name("from"), "from_");
+        assert_eq!(name("list"), "list_");
+        assert_eq!(name("handler"), "handler_");
+        assert_eq!(name("move forward"), "move_forward");
+        assert_eq!(name("3d"), "_3d");
+        assert_eq!(class_name(&Identifier("none".to_owned())), "None_");
+        assert_eq!(class_name(&Identifier("tile kind".to_owned())), "TileKind");
+        assert_eq!(
+            enum_member(&Identifier("south east".to_owned())),
+            "SOUTH_EAST"
+        );
+    }
+
+    #[test]
+    fn spells_out_enums_as_literals() {
+        let module = module(&api());
+        assert!(
+            module.contains("    from typing import Literal\n"),
+            "{module}"
+        );
+        assert!(
+            module.contains(
+                "def from_(list_: list[int] = [1, 2], \
+                 facing: Orientation | Literal[\"north\", \"south east\"] = \
+                 Orientation.SOUTH_EAST, \
+                 target: Position = Position(x=1, y=2)) -> None:\n"
+            ),
+            "{module}"
+        );
+        assert!(
+            module.contains("    SOUTH_EAST = \"south east\"\n"),
+            "{module}"
+        );
+        assert!(
+            module.contains(
+                "    return _call(\"from\", {\"list\": _to_json(list_, [int]), \
+                 \"facing\": _to_json(facing, Orientation), \
+                 \"target\": _to_json(target, Position)}, None)\n"
+            ),
+            "{module}"
+        );
+    }
+
+    #[test]
+    fn documents_defaults() {
+        let module = module(&api());
+        let docstring = "    \"\"\"Moves the robot
+
+    Moves the robot somewhere
+
+    Args:
+        list_: Steps to take. Defaults to `[1, 2]`.
+        facing: Direction to face. Defaults to `Orientation.SOUTH_EAST`.
+        target: Where to go. Defaults to `Position(x=1, y=2)`.
+    \"\"\"
+";
+        assert!(module.contains(docstring), "{module}");
+    }
+
+    #[test]
+    fn generates_stubs() {
+        let stub = stub(&api());
+        assert!(stub.contains("from enum import Enum\n"), "{stub}");
+        assert!(stub.contains("from typing import Literal\n"), "{stub}");
+        assert!(
+            stub.contains(
+                "def from_(list_: list[int] = ..., \
+                 facing: Orientation | Literal[\"north\", \"south east\"] = ..., \
+                 target: Position = ...) -> None:\n"
+            ),
+            "{stub}"
+        );
+        assert!(
+            stub.contains("    def __init__(self, x: int, y: int) -> None: ...\n"),
+            "{stub}"
+        );
+        assert!(
+            stub.contains("        target: Where to go. Defaults to `Position(x=1, y=2)`.\n"),
+            "{stub}"
+        );
+        // stubs only declare, so they neither call the engine nor import the native module
+        assert!(!stub.contains("_call"), "{stub}");
+        assert!(!stub.contains("import *"), "{stub}");
+        assert!(!stub.contains("TYPE_CHECKING"), "{stub}");
+    }
+
+    #[test]
+    #[should_panic(expected = "the value north doesn't match its type bool")]
+    fn refuses_mismatched_literals() {
+        drop(literal(
+            &Value::Enum(Identifier("north".to_owned())),
+            &TypeDescriptor::Boolean,
+            &[],
+        ));
     }
 }
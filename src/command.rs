@@ -0,0 +1,397 @@
+//! The command model shared by all front-ends (Python, HTTP, …) to call api functions of the engine.
+//!
+//! The opposite direction is covered by [`Event`]s, which the engine raises to notify scripts.
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    sync::mpsc::{channel, Receiver, Sender},
+};
+
+use log::{debug, error};
+use serde::Serialize;
+
+use crate::api::{
+    Api, FunctionDescriptor, Identifier, ParameterDescriptor, TypeDefinition, TypeDescriptor, Value,
+};
+
+/// A request to the engine to execute an api function.
+#[derive(Debug)]
+pub struct Command {
+    /// technical name of the function to execute
+    pub name: Identifier,
+    /// argument values in the order of the function's parameters
+    pub arguments: Vec<Value>,
+    /// names of the function's parameters, which the arguments belong to
+    pub parameters: Vec<Identifier>,
+    /// channel to send the function's return value back to the caller
+    pub reply: Reply,
+}
+
+impl Command {
+    /// Creates a new command after checking the arguments against the function's parameters.
+    /// Named types are looked up in the given `api`, which the function belongs to.
+    ///
+    /// Trailing arguments may be omitted if their parameters have a default value.
+    ///
+    /// Returns the command along with the receiving end of its reply channel.
+    pub fn new(
+        api: &Api,
+        function: &FunctionDescriptor,
+        mut arguments: Vec<Value>,
+    ) -> Result<(Self, Receiver<CommandResult>), ArgumentError> {
+        if arguments.len() > function.parameters.len() {
+            return Err(ArgumentError::Count {
+                expected: function.parameters.len(),
+                actual: arguments.len(),
+            });
+        }
+
+        for parameter in &function.parameters[arguments.len()..] {
+            let default = parameter
+                .default
+                .clone()
+                .ok_or_else(|| ArgumentError::Missing {
+                    parameter: parameter.name.clone(),
+                })?;
+            arguments.push(default);
+        }
+
+        check_types(api, &function.parameters, &arguments)?;
+
+        let (sender, receiver) = channel();
+        let command = Self {
+            name: function.name.clone(),
+            arguments,
+            parameters: function
+                .parameters
+                .iter()
+                .map(|parameter| parameter.name.clone())
+                .collect(),
+            reply: Reply {
+                returns: function.returns.as_ref().map(|returns| returns.typ.clone()),
+                types: api.types.clone(),
+                errors: function
+                    .errors
+                    .iter()
+                    .map(|error| error.name.clone())
+                    .collect(),
+                sender,
+            },
+        };
+
+        Ok((command, receiver))
+    }
+
+    /// Returns the argument of the parameter with the given name or `None` if the function
+    /// doesn't declare such a parameter.
+    #[must_use]
+    pub fn argument(&self, parameter: &str) -> Option<&Value> {
+        self.parameters
+            .iter()
+            .position(|name| name.0 == parameter)
+            .and_then(|index| self.arguments.get(index))
+    }
+}
+
+/// Checks each argument against the type of its parameter.
+fn check_types(
+    api: &Api,
+    parameters: &[ParameterDescriptor],
+    arguments: &[Value],
+) -> Result<(), ArgumentError> {
+    for (parameter, argument) in parameters.iter().zip(arguments) {
+        if !parameter.typ.accepts(argument, &api.types) {
+            return Err(ArgumentError::Type {
+                parameter: parameter.name.clone(),
+                expected: parameter.typ.clone(),
+                actual: argument.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The outcome of a [`Command`]: either the function's return value or the reason why the
+/// command has been refused.
+pub type CommandResult = Result<Option<Value>, CommandError>;
+
+/// Sending half of a [`Command`]'s reply channel.
+#[derive(Debug)]
+pub struct Reply {
+    returns: Option<TypeDescriptor>,
+    /// named types the return type might refer to
+    types: Vec<TypeDefinition>,
+    /// kinds of errors the function declared
+    errors: Vec<Identifier>,
+    sender: Sender<CommandResult>,
+}
+
+impl Reply {
+    /// Sends the outcome of the command back to the caller.
+    ///
+    /// A successful return value is expected to match the function's declared return type,
+    /// an error is expected to be of a kind the function declared.
+    pub fn send(self, result: CommandResult) {
+        match result {
+            Ok(ref value) => match (self.returns.as_ref(), value.as_ref()) {
+                (None, None) => {}
+                (Some(returns), Some(value)) if returns.accepts(value, &self.types) => {}
+                (returns, value) => {
+                    error!("return value {value:?} does not match the declared type {returns:?}");
+                }
+            },
+            Err(ref error) => {
+                if !self.errors.contains(&error.kind) {
+                    error!("the kind of error `{}` has not been declared", error.kind);
+                }
+            }
+        }
+
+        if self.sender.send(result).is_err() {
+            debug!("the caller is no longer waiting for a reply");
+        }
+    }
+}
+
+/// The reason why the engine refused to execute a [`Command`].
+#[derive(Clone, Debug, Serialize)]
+pub struct CommandError {
+    /// technical name of the kind of error (e.g. `out of bounds`)
+    pub kind: Identifier,
+    /// a human readable explanation what went wrong
+    pub message: String,
+}
+
+impl CommandError {
+    /// Creates an error of the given kind, which should be declared by the refused function.
+    #[must_use]
+    pub fn new(kind: &str, message: impl Into<String>) -> Self {
+        Self {
+            kind: Identifier(kind.to_owned()),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for CommandError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.message)
+    }
+}
+
+impl Error for CommandError {}
+
+/// A notification from the engine to a script that something happened (e.g. the robot bumped
+/// into a wall).
+#[derive(Clone, Debug)]
+pub struct Event {
+    /// technical name of the event
+    pub name: Identifier,
+    /// payload values in the order of the event's declaration
+    pub payload: Vec<Value>,
+}
+
+impl Event {
+    /// Creates an event; its payload gets checked against the api once it's emitted.
+    #[must_use]
+    pub fn new(name: &str, payload: Vec<Value>) -> Self {
+        Self {
+            name: Identifier(name.to_owned()),
+            payload,
+        }
+    }
+}
+
+/// Creates a channel to deliver the events declared by the given api to a script.
+#[must_use]
+pub fn event_channel(api: &Api) -> (EventSender, Receiver<Event>) {
+    let (sender, receiver) = channel();
+    let sender = EventSender {
+        api: api.clone(),
+        sender,
+    };
+    (sender, receiver)
+}
+
+/// Sending half of an event channel.
+///
+/// Events the api doesn't declare will be dropped, so the engine may raise all of its events
+/// regardless of the api the script has been given.
+#[derive(Clone, Debug)]
+pub struct EventSender {
+    api: Api,
+    sender: Sender<Event>,
+}
+
+impl EventSender {
+    /// Sends the event to the script after checking its payload against the declaration.
+    pub fn emit(&self, event: Event) {
+        let Some(descriptor) = self.api.event(&event.name.0) else {
+            debug!("the api doesn't declare the event `{}`", event.name);
+            return;
+        };
+
+        if event.payload.len() != descriptor.payload.len() {
+            error!(
+                "event `{}` expects {} payload value(s), but got {}",
+                event.name,
+                descriptor.payload.len(),
+                event.payload.len()
+            );
+            return;
+        }
+        if let Err(error) = check_types(&self.api, &descriptor.payload, &event.payload) {
+            error!("invalid payload for event `{}`: {error}", event.name);
+            return;
+        }
+
+        if self.sender.send(event).is_err() {
+            debug!("the script is no longer listening to events");
+        }
+    }
+}
+
+/// Reasons why a list of arguments doesn't fit a function's parameters.
+#[derive(Clone, Debug)]
+pub enum ArgumentError {
+    /// there are more arguments than parameters
+    Count { expected: usize, actual: usize },
+    /// an argument without a default value has been omitted
+    Missing { parameter: Identifier },
+    /// an argument is not a member of its parameter's type
+    Type {
+        parameter: Identifier,
+        expected: TypeDescriptor,
+        actual: Value,
+    },
+}
+
+impl Display for ArgumentError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ArgumentError::Count { expected, actual } => {
+                write!(
+                    formatter,
+                    "expected {expected} argument(s), but got {actual}"
+                )
+            }
+            ArgumentError::Missing { ref parameter } => {
+                write!(formatter, "missing argument `{parameter}`")
+            }
+            ArgumentError::Type {
+                ref parameter,
+                ref expected,
+                ref actual,
+            } => write!(
+                formatter,
+                "argument `{parameter}` must be of type {expected}, but got {actual}"
+            ),
+        }
+    }
+}
+
+impl Error for ArgumentError {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn robot_api() -> Api {
+        Api::load("apis/robot.api.json").unwrap()
+    }
+
+    fn command(name: &str, arguments: Vec<Value>) -> Result<Command, ArgumentError> {
+        let api = robot_api();
+        let function = api.function(name).unwrap();
+        Command::new(&api, function, arguments).map(|(command, _reply)| command)
+    }
+
+    fn position(x: i64, y: i64) -> Value {
+        Value::Record(BTreeMap::from([
+            (Identifier("x".to_owned()), Value::Integer(x)),
+            (Identifier("y".to_owned()), Value::Integer(y)),
+        ]))
+    }
+
+    #[test]
+    fn fills_in_defaults() {
+        let command = command("move forward", Vec::new()).unwrap();
+        assert_eq!(command.arguments, [Value::Integer(1)]);
+        assert_eq!(command.argument("steps"), Some(&Value::Integer(1)));
+        assert_eq!(command.argument("times"), None);
+    }
+
+    #[test]
+    fn keeps_given_arguments() {
+        let command = command("move forward", vec![Value::Integer(3)]).unwrap();
+        assert_eq!(command.arguments, [Value::Integer(3)]);
+        assert_eq!(command.parameters, [Identifier("steps".to_owned())]);
+    }
+
+    #[test]
+    fn rejects_missing_arguments() {
+        let error = command("line pattern", Vec::new()).unwrap_err();
+        assert_eq!(error.to_string(), "missing argument `position`");
+    }
+
+    #[test]
+    fn rejects_too_many_arguments() {
+        let error = command("position", vec![Value::Integer(1)]).unwrap_err();
+        assert_eq!(error.to_string(), "expected 0 argument(s), but got 1");
+    }
+
+    #[test]
+    fn rejects_type_mismatches() {
+        let error = command("move forward", vec![Value::Boolean(true)]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "argument `steps` must be of type integer in 1..1000, but got true"
+        );
+        // out of range
+        assert!(matches!(
+            command("move forward", vec![Value::Integer(0)]),
+            Err(ArgumentError::Type { .. })
+        ));
+        // a record lacking a field
+        let mut fields = BTreeMap::new();
+        fields.insert(Identifier("x".to_owned()), Value::Integer(1));
+        assert!(matches!(
+            command("line pattern", vec![Value::Record(fields)]),
+            Err(ArgumentError::Type { .. })
+        ));
+        command("line pattern", vec![position(1, 2)]).unwrap();
+    }
+
+    #[test]
+    fn replies_to_the_caller() {
+        let api = robot_api();
+        let function = api.function("position").unwrap();
+        let (command, receiver) = Command::new(&api, function, Vec::new()).unwrap();
+        command.reply.send(Ok(Some(position(1, 2))));
+        assert_eq!(receiver.recv().unwrap().unwrap(), Some(position(1, 2)));
+    }
+
+    #[test]
+    fn emits_declared_events() {
+        let (sender, receiver) = event_channel(&robot_api());
+        sender.emit(Event::new("bumped into wall", vec![position(1, 2)]));
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(event.name.0, "bumped into wall");
+        assert_eq!(event.payload, [position(1, 2)]);
+    }
+
+    #[test]
+    fn drops_invalid_events() {
+        let (sender, receiver) = event_channel(&robot_api());
+        // not declared
+        sender.emit(Event::new("fell asleep", Vec::new()));
+        // wrong number of payload values
+        sender.emit(Event::new("bumped into wall", Vec::new()));
+        // wrong type of payload
+        sender.emit(Event::new("key pressed", vec![Value::Integer(1)]));
+        receiver.try_recv().unwrap_err();
+    }
+}
@@ -11,13 +11,20 @@ use wgpu;
 use winit::{
     application::ApplicationHandler,
     dpi::PhysicalSize,
-    event::{DeviceEvent, DeviceId, KeyEvent, WindowEvent},
+    event::{DeviceEvent, DeviceId, ElementState, KeyEvent, WindowEvent},
     event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
     keyboard::{Key, NamedKey},
     window::{Window, WindowAttributes, WindowId},
 };
 
-use crate::scene::{Command, Scene};
+use crate::{
+    api::Value,
+    command::{Event, EventSender},
+    level::Level,
+    monitor::Monitor,
+    scene::Scene,
+    Command,
+};
 
 /// Wrapper type which manages the surface and surface configuration.
 ///
@@ -222,10 +229,21 @@ struct Application {
     frame_time: Instant,
     receiver: Receiver<Command>,
     current_command: Option<Command>,
+    /// notifies the script about things happening within the scene
+    events: EventSender,
+    /// tells observers what the scene is doing
+    monitor: Monitor,
+    level: Level,
 }
 
 impl Application {
-    async fn new(title: String, receiver: Receiver<Command>) -> Self {
+    async fn new(
+        title: String,
+        receiver: Receiver<Command>,
+        events: EventSender,
+        monitor: Monitor,
+        level: Level,
+    ) -> Self {
         let mut surface = SurfaceWrapper::new();
         let context = ExampleContext::init_async(&mut surface).await;
 
@@ -239,6 +257,9 @@ impl Application {
             frame_time: Instant::now(),
             receiver,
             current_command: None,
+            events,
+            monitor,
+            level,
         }
     }
 }
@@ -261,6 +282,7 @@ impl ApplicationHandler for Application {
                 &self.context.adapter,
                 &self.context.device,
                 &self.context.queue,
+                &self.level,
             ));
         }
     }
@@ -324,11 +346,23 @@ impl ApplicationHandler for Application {
                     logical_key,
                     text: _,
                     location: _,
-                    state: _,
-                    repeat: _,
+                    state,
+                    repeat,
                     ..
                 } = key_event;
 
+                if state == ElementState::Pressed && !repeat {
+                    let key = match logical_key {
+                        Key::Named(ref key) => Some(format!("{key:?}")),
+                        Key::Character(ref key) => Some(key.to_string()),
+                        Key::Unidentified(_) | Key::Dead(_) => None,
+                    };
+                    if let Some(key) = key {
+                        self.events
+                            .emit(Event::new("key pressed", vec![Value::String(key)]));
+                    }
+                }
+
                 match logical_key {
                     Key::Named(key) => {
                         trace!("WindowEvent::KeyboardInput::logical_key::Named({key:?})");
@@ -450,10 +484,11 @@ impl ApplicationHandler for Application {
                     }
                 }
 
-                if let Some(current_command) = self.current_command.take() {
-                    if let Some(scene) = self.example.as_mut() {
-                        if scene.is_idle() {
-                            scene.process_command(&current_command);
+                if let Some(scene) = self.example.as_mut() {
+                    scene.report_animation(&self.monitor);
+                    if scene.is_idle() {
+                        if let Some(current_command) = self.current_command.take() {
+                            scene.process_command(current_command, &self.events, &self.monitor);
                         }
                     }
                 }
@@ -541,7 +576,17 @@ impl ApplicationHandler for Application {
     }
 }
 
-pub async fn start(title: String, receiver: Receiver<Command>) {
+/// Opens a window and runs the scene of the given level until the window gets closed.
+///
+/// Commands will be received from `receiver` and the events of the scene sent to `events`.
+/// Everything the scene does gets published to the `monitor`.
+pub async fn start(
+    title: String,
+    receiver: Receiver<Command>,
+    events: EventSender,
+    monitor: Monitor,
+    level: Level,
+) {
     let event_loop = EventLoop::new().unwrap();
 
     // ControlFlow::Poll continuously runs the event loop, even if the OS hasn't
@@ -553,7 +598,7 @@ pub async fn start(title: String, receiver: Receiver<Command>) {
     // input, and uses significantly less power/CPU time than ControlFlow::Poll.
     // event_loop.set_control_flow(ControlFlow::Wait);
 
-    let app = Application::new(title, receiver);
+    let app = Application::new(title, receiver, events, monitor, level);
     log::info!("Entering event loop...");
     event_loop.run_app(&mut app.await).unwrap();
 }
@@ -0,0 +1,200 @@
+//! Runs a Python or Lua script against a [`World`] without opening a window.
+//!
+//! Commands are applied to the world as soon as they arrive and get acknowledged right away,
+//! so a script runs as fast as the interpreter allows. Events raised by a command are sent to
+//! the script before acknowledging it. This is meant for automated checks
+//! (e.g. grading homework) where nobody is watching the animations.
+
+use std::{
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{
+    api::Api,
+    command::{event_channel, Event},
+    level::{Level, TilePosition},
+    lua,
+    monitor::{Activity, Monitor},
+    python::{self, Abort, CancelHandle, Limits, Sandbox, ScriptError, ScriptErrorKind},
+    world::{LinePattern, World},
+    Command, Orientation,
+};
+
+/// how long to wait for a command before checking whether the script has finished
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// how long to wait for an aborted script to actually stop
+const GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// The state of the world after the script has ended
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    /// final position of the robot
+    pub position: TilePosition,
+    /// final orientation of the robot
+    pub orientation: Orientation,
+    /// line patterns of all tiles indexed by `[y][x]`
+    pub line_patterns: Vec<Vec<LinePattern>>,
+    /// number of commands the script has sent, including rejected ones
+    pub command_count: usize,
+    /// the script has been stopped as it didn't finish in time
+    pub timed_out: bool,
+    /// the script has been stopped as it sent too many commands
+    pub budget_exceeded: bool,
+    /// the error which ended the script
+    pub error: Option<ScriptError>,
+    /// the lines the script has printed, as the report is all that gets printed in headless mode
+    pub output: Vec<String>,
+}
+
+/// Runs the Python script at the given path against a fresh world of the given level.
+///
+/// The script will be aborted if it exceeds any of the given `limits` and may only use what the
+/// `sandbox` allows.
+#[must_use]
+pub fn run_python(
+    source_path: PathBuf,
+    level: &Level,
+    api: &Api,
+    limits: Limits,
+    sandbox: Sandbox,
+) -> Report {
+    run(source_path, level, api, limits, sandbox, python::runner)
+}
+
+/// Runs the Lua script at the given path against a fresh world of the given level.
+///
+/// The script will be aborted if it exceeds any of the given `limits` and may only use what the
+/// `sandbox` allows.
+#[must_use]
+pub fn run_lua(
+    source_path: PathBuf,
+    level: &Level,
+    api: &Api,
+    limits: Limits,
+    sandbox: Sandbox,
+) -> Report {
+    run(source_path, level, api, limits, sandbox, lua::runner)
+}
+
+/// Signature shared by the runners of all supported languages
+type Runner = fn(
+    PathBuf,
+    Sender<Command>,
+    Receiver<Event>,
+    &Api,
+    Limits,
+    &CancelHandle,
+    &Sandbox,
+    &Monitor,
+) -> Result<(), Box<ScriptError>>;
+
+fn run(
+    source_path: PathBuf,
+    level: &Level,
+    api: &Api,
+    limits: Limits,
+    sandbox: Sandbox,
+    runner: Runner,
+) -> Report {
+    let mut world = World::new(level);
+    let mut command_count = 0;
+
+    let file = source_path.display().to_string();
+    let cancel = CancelHandle::new();
+    let (sender, receiver) = channel();
+    let (event_sender, event_receiver) = event_channel(api);
+    let monitor = Monitor::default();
+    let activities = monitor.subscribe();
+    let script: JoinHandle<Result<(), Box<ScriptError>>> = {
+        let api = api.clone();
+        let cancel = cancel.clone();
+        let monitor = monitor.clone();
+        thread::spawn(move || {
+            runner(
+                source_path,
+                sender,
+                event_receiver,
+                &api,
+                limits,
+                &cancel,
+                &sandbox,
+                &monitor,
+            )
+        })
+    };
+
+    // the runner enforces the timeout itself; this only guards against a stuck interpreter
+    let deadline = limits
+        .timeout
+        .map(|timeout| Instant::now() + timeout + GRACE_PERIOD);
+    // the interpreter keeps its sender alive until it shuts down, so the end of the script
+    // needs to be polled for
+    let mut shut_down = false;
+    while !shut_down && !script.is_finished() {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break;
+        }
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(command) => {
+                command_count += 1;
+                if let Some(answer) = world.query(&command) {
+                    command.reply.send(answer.map(Some));
+                    continue;
+                }
+                let outcome = world.process_command(&command);
+                for event in world.events(&outcome) {
+                    event_sender.emit(event);
+                }
+                command.reply.send(outcome.map(|_actions| None));
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => shut_down = true,
+        }
+    }
+
+    let error = if shut_down || script.is_finished() {
+        match script.join() {
+            Ok(result) => result.err().map(|error| *error),
+            Err(_panic) => Some(ScriptError::new(
+                ScriptErrorKind::Runtime,
+                file,
+                "the interpreter panicked",
+            )),
+        }
+    } else {
+        // the interpreter didn't respond to the timeout and will be left behind
+        Some(ScriptError::new(
+            ScriptErrorKind::Timeout,
+            file,
+            "the script could not be stopped",
+        ))
+    };
+    let reason = cancel.reason();
+
+    Report {
+        position: [world.position().x, world.position().y],
+        orientation: world.orientation(),
+        line_patterns: world
+            .tiles()
+            .chunks(world.size().x as usize)
+            .map(|row| row.iter().map(|tile| tile.line_pattern).collect())
+            .collect(),
+        command_count,
+        timed_out: matches!(reason, Some(Abort::Timeout(_))),
+        budget_exceeded: matches!(reason, Some(Abort::CommandBudget(_))),
+        error,
+        output: activities
+            .try_iter()
+            .filter_map(|activity| match activity {
+                Activity::Output { text, .. } => Some(text),
+                _ => None,
+            })
+            .collect(),
+    }
+}
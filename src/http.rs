@@ -0,0 +1,348 @@
+//! Serves an [`Api`] via HTTP, so that programs outside of the engine can control it.
+//!
+//! A function is called via `POST /<api>/<function>` with its arguments as a json object, e.g.
+//! `{"steps": 3}`. Omitted arguments take their default value; an empty body passes none at all.
+//! Once the engine has executed the command, the server replies with `{"result": …}` or with
+//! `{"error": {"kind": …, "message": …}}` and one of these status codes:
+//!
+//! - `400` if the body is no valid json
+//! - `404` if there is no such function
+//! - `405` if the function isn't called via `POST`
+//! - `422` if the arguments don't fit the parameters of the function
+//! - `409` if the engine refused the command with one of the errors the function declares
+//! - `503` if the engine has shut down
+//!
+//! `GET /<api>/state` replies with the values of all read-only functions without parameters (see
+//! [`Api::state_functions`]) as a json object keyed by the names of the functions. An api may still
+//! declare a function called `state`, which gets called via `POST /<api>/state` as usual.
+//!
+//! `GET /events` (see [`EVENTS_PATH`]) streams everything published to the [`Monitor`] as
+//! [server-sent events](https://html.spec.whatwg.org/multipage/server-sent-events.html): each
+//! [`Activity`] is a message whose `event` is the kind of activity and whose `data` is the activity
+//! as json, e.g.
+//!
+//! ```text
+//! event: command
+//! data: {"type":"command","function":"move forward","arguments":[1],"result":null,"error":null}
+//! ```
+//!
+//! Values are represented as described by [`Value::to_json`]. The server also describes the api by
+//! the document at [`openapi::DOCUMENT_PATH`].
+
+use std::{
+    io::Write,
+    sync::mpsc::{Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::Duration,
+};
+
+use log::{debug, warn};
+use serde_json::{json, Value as Json};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    api::{Api, FunctionDescriptor, Value},
+    bindgen::openapi,
+    command::{Command, CommandResult},
+    monitor::{Activity, Monitor},
+};
+
+/// path at which the server streams the activity published to the monitor
+pub const EVENTS_PATH: &str = "/events";
+
+/// how long the event stream may stay silent before a comment is sent to keep it alive
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Answers requests to call the functions of the given api by sending [`Command`]s to the engine.
+///
+/// Commands are sent in the order their requests arrive. Each request waits for the engine on a
+/// thread of its own, so that the server stays responsive meanwhile. The same goes for each client
+/// of the event stream, which receives the activity published to the `monitor`.
+pub fn serve(server: &Server, command_sender: &Sender<Command>, api: &Api, monitor: &Monitor) {
+    // lets clients discover the functions and how to call them
+    let document = openapi::document(api);
+    let state_path = openapi::state_path(api);
+
+    for mut request in server.incoming_requests() {
+        // the query string has no meaning to any of the paths
+        let path = request
+            .url()
+            .split_once('?')
+            .map_or(request.url(), |(path, _query)| path)
+            .to_owned();
+        if path == openapi::DOCUMENT_PATH {
+            respond(request, 200, &document);
+            continue;
+        }
+        if path == EVENTS_PATH && *request.method() == Method::Get {
+            let activities = monitor.subscribe();
+            thread::spawn(move || stream(request, &activities));
+            continue;
+        }
+        if path == state_path && *request.method() == Method::Get {
+            match query_state(command_sender, api) {
+                Ok(replies) => {
+                    thread::spawn(move || respond_with_state(request, replies));
+                }
+                Err(failure) => failure.respond(request),
+            }
+            continue;
+        }
+        match call(&mut request, &path, command_sender, api) {
+            Ok(reply) => {
+                thread::spawn(move || match reply.recv() {
+                    Ok(result) => respond_with_result(request, result),
+                    Err(_disconnected) => Failure::shut_down().respond(request),
+                });
+            }
+            Err(failure) => failure.respond(request),
+        }
+    }
+}
+
+/// Sends the command requested by the given request to the engine.
+///
+/// The `path` is the one of the request's url without its query string.
+fn call(
+    request: &mut Request,
+    path: &str,
+    command_sender: &Sender<Command>,
+    api: &Api,
+) -> Result<Receiver<CommandResult>, Failure> {
+    let Some(function_name) = path
+        .strip_prefix(&format!("/{}/", api.name))
+        .map(percent_decode)
+    else {
+        return Err(Failure::new(
+            404,
+            "unknown api",
+            format!("there is no api at `{path}`"),
+        ));
+    };
+    let Some(function) = api.function(&function_name) else {
+        return Err(Failure::new(
+            404,
+            "unknown function",
+            format!("the {} api has no function `{function_name}`", api.name),
+        ));
+    };
+    if *request.method() != Method::Post {
+        return Err(Failure::new(
+            405,
+            "method not allowed",
+            format!("`{function_name}` must be called via POST"),
+        ));
+    }
+
+    let arguments = arguments(request, api, function)?;
+    let (command, reply) = Command::new(api, function, arguments)
+        .map_err(|error| Failure::invalid_arguments(error.to_string()))?;
+    command_sender
+        .send(command)
+        .map_err(|_closed| Failure::shut_down())?;
+    Ok(reply)
+}
+
+/// Sends a command for each function the state consists of to the engine.
+fn query_state(
+    command_sender: &Sender<Command>,
+    api: &Api,
+) -> Result<Vec<(String, Receiver<CommandResult>)>, Failure> {
+    api.state_functions()
+        .map(|function| {
+            let (command, reply) = Command::new(api, function, Vec::new())
+                .map_err(|error| Failure::new(500, "invalid api", error.to_string()))?;
+            command_sender
+                .send(command)
+                .map_err(|_closed| Failure::shut_down())?;
+            Ok((function.name.0.clone(), reply))
+        })
+        .collect()
+}
+
+/// Reads the arguments of a function from the json object within the body of the request.
+fn arguments(
+    request: &mut Request,
+    api: &Api,
+    function: &FunctionDescriptor,
+) -> Result<Vec<Value>, Failure> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .read_to_string(&mut body)
+        .map_err(|error| Failure::malformed(format!("failed to read the request: {error}")))?;
+    let json = if body.trim().is_empty() {
+        json!({})
+    } else {
+        serde_json::from_str(&body)
+            .map_err(|error| Failure::malformed(format!("the body is no valid json: {error}")))?
+    };
+    let Some(object) = json.as_object() else {
+        return Err(Failure::invalid_arguments(
+            "the arguments must be passed as a json object",
+        ));
+    };
+
+    if let Some(unknown) = object.keys().find(|key| {
+        !function
+            .parameters
+            .iter()
+            .any(|parameter| parameter.name.0 == **key)
+    }) {
+        return Err(Failure::invalid_arguments(format!(
+            "`{}` has no parameter `{unknown}`",
+            function.name
+        )));
+    }
+
+    function
+        .parameters
+        .iter()
+        .map(|parameter| match object.get(&parameter.name.0) {
+            None | Some(&Json::Null) => parameter.default.clone().ok_or_else(|| {
+                Failure::invalid_arguments(format!("argument `{}` is missing", parameter.name))
+            }),
+            Some(argument) => {
+                Value::from_json(argument, &parameter.typ, &api.types).map_err(|message| {
+                    Failure::invalid_arguments(format!("argument `{}`: {message}", parameter.name))
+                })
+            }
+        })
+        .collect()
+}
+
+fn respond_with_result(request: Request, result: CommandResult) {
+    match result {
+        Ok(value) => {
+            let returned = value.as_ref().map_or(Json::Null, Value::to_json);
+            respond(request, 200, &json!({ "result": returned }));
+        }
+        Err(error) => Failure::new(409, &error.kind.0, error.message).respond(request),
+    }
+}
+
+fn respond_with_state(request: Request, replies: Vec<(String, Receiver<CommandResult>)>) {
+    let mut state = serde_json::Map::new();
+    for (name, reply) in replies {
+        match reply.recv() {
+            Ok(Ok(value)) => {
+                state.insert(name, value.as_ref().map_or(Json::Null, Value::to_json));
+            }
+            Ok(Err(error)) => {
+                Failure::new(409, &error.kind.0, error.message).respond(request);
+                return;
+            }
+            Err(_disconnected) => {
+                Failure::shut_down().respond(request);
+                return;
+            }
+        }
+    }
+    respond(request, 200, &Json::Object(state));
+}
+
+/// Sends each activity as a server-sent event until the client disconnects.
+fn stream(request: Request, activities: &Receiver<Activity>) {
+    // responses of `tiny_http` are buffered, so the events are written to the connection directly
+    let mut writer = request.into_writer();
+    let mut message = "HTTP/1.1 200 OK\r\n\
+        Content-Type: text/event-stream\r\n\
+        Cache-Control: no-cache\r\n\
+        Connection: close\r\n\
+        \r\n"
+        .to_owned();
+    loop {
+        if let Err(error) = writer
+            .write_all(message.as_bytes())
+            .and_then(|()| writer.flush())
+        {
+            debug!("the client of the event stream is gone: {error}");
+            return;
+        }
+        message = match activities.recv_timeout(KEEP_ALIVE_INTERVAL) {
+            Ok(activity) => match serde_json::to_string(&activity) {
+                Ok(data) => format!("event: {}\ndata: {data}\n\n", activity.kind()),
+                Err(error) => {
+                    warn!("failed to serialize {activity:?}: {error}");
+                    String::new()
+                }
+            },
+            // also reveals clients which are gone
+            Err(RecvTimeoutError::Timeout) => ": keep alive\n\n".to_owned(),
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+    }
+}
+
+/// Sends the given json to the client.
+fn respond(request: Request, status: u16, body: &Json) {
+    let mut response = Response::from_string(body.to_string()).with_status_code(status);
+    if let Ok(header) = Header::from_bytes("Content-Type", "application/json") {
+        response.add_header(header);
+    }
+    if let Err(error) = request.respond(response) {
+        warn!("failed to respond to an HTTP request: {error}");
+    }
+}
+
+/// The reason why a request failed
+struct Failure {
+    status: u16,
+    /// technical name of the kind of error, e.g. `unknown function` or `blocked`
+    kind: String,
+    message: String,
+}
+
+impl Failure {
+    fn new(status: u16, kind: &str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            kind: kind.to_owned(),
+            message: message.into(),
+        }
+    }
+
+    fn malformed(message: impl Into<String>) -> Self {
+        Self::new(400, "malformed request", message)
+    }
+
+    fn invalid_arguments(message: impl Into<String>) -> Self {
+        Self::new(422, "invalid arguments", message)
+    }
+
+    fn shut_down() -> Self {
+        Self::new(503, "shut down", "the engine has been shut down")
+    }
+
+    fn respond(self, request: Request) {
+        let body = json!({
+            "error": {
+                "kind": self.kind,
+                "message": self.message,
+            },
+        });
+        respond(request, self.status, &body);
+    }
+}
+
+/// Decodes `%XX`-escapes within the path of an url (e.g. `%20` for a space).
+fn percent_decode(url: &str) -> String {
+    let mut bytes = Vec::with_capacity(url.len());
+    let mut rest = url.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        if byte == b'%' {
+            if let Some(decoded) = tail
+                .get(..2)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                bytes.push(decoded);
+                rest = tail.get(2..).unwrap_or_default();
+                continue;
+            }
+        }
+        bytes.push(byte);
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
@@ -0,0 +1,384 @@
+//! A level describes the initial state of the world and the rules a script has to obey.
+//!
+//! Levels are stored as [json5](https://json5.org/) files so that exercises can be authored
+//! without recompiling the engine.
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    fs::read_to_string,
+    io,
+    path::Path,
+};
+
+use glam::{IVec3, UVec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{Api, Identifier},
+    Orientation,
+};
+
+/// Position of a tile; `[0, 0]` is the tile at the origin.
+pub type TilePosition = [i32; 2];
+
+/// Largest number of tiles along either axis; enough for any exercise while keeping the world
+/// small enough to be simulated and rendered.
+pub const MAX_SIZE: u32 = 256;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Level {
+    /// number of tiles along the x- and y-axis
+    pub size: [u32; 2],
+    /// position and orientation of the robot when the level starts
+    pub start: Pose,
+    /// tiles the robot cannot enter
+    #[serde(default)]
+    pub blocked: Vec<TilePosition>,
+    /// walls between two adjacent tiles
+    #[serde(default)]
+    pub walls: Vec<Wall>,
+    /// tiles the robot shall reach
+    #[serde(default)]
+    pub goals: Vec<TilePosition>,
+    /// names of the api functions a script may use; all functions are allowed if this is missing
+    #[serde(default)]
+    pub allowed_functions: Option<Vec<Identifier>>,
+}
+
+/// Position and orientation of the robot
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pose {
+    pub position: TilePosition,
+    #[serde(default)]
+    pub orientation: Orientation,
+}
+
+/// A wall separating two tiles sharing an edge
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Wall {
+    pub between: [TilePosition; 2],
+}
+
+impl Level {
+    /// Reads and checks a level from a json5 file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LevelError> {
+        let source = read_to_string(path).map_err(LevelError::Io)?;
+        Self::parse(&source)
+    }
+
+    fn parse(source: &str) -> Result<Self, LevelError> {
+        let level: Self = json5::from_str(source).map_err(LevelError::Parse)?;
+        level.check()?;
+        Ok(level)
+    }
+
+    /// number of tiles along the x- and y-axis
+    #[must_use]
+    pub fn size(&self) -> UVec2 {
+        UVec2::from(self.size)
+    }
+
+    /// Returns `true` if the position lies within the bounds of this level.
+    #[must_use]
+    pub fn contains(&self, position: TilePosition) -> bool {
+        let [x, y] = position;
+        u32::try_from(x).is_ok_and(|x| x < self.size[0])
+            && u32::try_from(y).is_ok_and(|y| y < self.size[1])
+    }
+
+    /// Returns `true` if the script is allowed to call the given function.
+    #[must_use]
+    pub fn allows(&self, function: &Identifier) -> bool {
+        match self.allowed_functions {
+            Some(ref allowed) => allowed.contains(function),
+            None => true,
+        }
+    }
+
+    /// Returns a copy of the api which only contains the functions allowed by this level.
+    ///
+    /// Fails if the level allows a function the api doesn't know, as it has most likely been
+    /// misspelled and would stay hidden otherwise.
+    pub fn restrict(&self, api: &Api) -> Result<Api, LevelError> {
+        if let Some(unknown) = self
+            .allowed_functions
+            .iter()
+            .flatten()
+            .find(|name| api.function(&name.0).is_none())
+        {
+            return Err(LevelError::Invalid(format!(
+                "the {} api has no function `{unknown}` to allow",
+                api.name
+            )));
+        }
+
+        let mut api = api.clone();
+        api.functions.retain(|function| self.allows(&function.name));
+        Ok(api)
+    }
+
+    fn check(&self) -> Result<(), LevelError> {
+        if self.size.contains(&0) {
+            return Err(LevelError::Invalid(
+                "the level must not be empty".to_owned(),
+            ));
+        }
+        if self.size.iter().any(|&length| length > MAX_SIZE) {
+            return Err(LevelError::Invalid(format!(
+                "the level must not exceed {MAX_SIZE} tiles along either axis, but has a size of \
+                {}x{}",
+                self.size[0], self.size[1]
+            )));
+        }
+
+        let outside = |position: TilePosition, what: &str| {
+            LevelError::Invalid(format!(
+                "{what} at {x}/{y} lies outside the level",
+                x = position[0],
+                y = position[1]
+            ))
+        };
+
+        if !self.contains(self.start.position) {
+            return Err(outside(self.start.position, "start position"));
+        }
+        if self.blocked.contains(&self.start.position) {
+            return Err(LevelError::Invalid("start position is blocked".to_owned()));
+        }
+        if let Some(&position) = self.blocked.iter().find(|&&tile| !self.contains(tile)) {
+            return Err(outside(position, "blocked tile"));
+        }
+        if let Some(&position) = self.goals.iter().find(|&&tile| !self.contains(tile)) {
+            return Err(outside(position, "goal"));
+        }
+
+        for wall in &self.walls {
+            let [first, second] = wall.between;
+            if !self.contains(first) {
+                return Err(outside(first, "wall"));
+            }
+            if !self.contains(second) {
+                return Err(outside(second, "wall"));
+            }
+            if (first[0] - second[0]).abs() + (first[1] - second[1]).abs() != 1 {
+                return Err(LevelError::Invalid(format!(
+                    "wall between {first:?} and {second:?} doesn't separate adjacent tiles"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Pose {
+    #[must_use]
+    pub fn position(&self) -> IVec3 {
+        IVec3::new(self.position[0], self.position[1], 0)
+    }
+}
+
+/// Reasons why a level couldn't be loaded.
+#[derive(Debug)]
+pub enum LevelError {
+    /// the file couldn't be read
+    Io(io::Error),
+    /// the file is not a valid level description
+    Parse(json5::Error),
+    /// the level contains contradicting or out-of-bounds elements
+    Invalid(String),
+}
+
+impl Display for LevelError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            LevelError::Io(ref error) => write!(formatter, "failed to read level: {error}"),
+            LevelError::Parse(ref error) => write!(formatter, "failed to parse level: {error}"),
+            LevelError::Invalid(ref message) => write!(formatter, "invalid level: {message}"),
+        }
+    }
+}
+
+impl Error for LevelError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            LevelError::Io(ref error) => Some(error),
+            LevelError::Parse(ref error) => Some(error),
+            LevelError::Invalid(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINIMAL: &str = "{ size: [3, 2], start: { position: [0, 0] } }";
+
+    fn invalid_message(source: &str) -> String {
+        match Level::parse(source) {
+            Err(LevelError::Invalid(message)) => message,
+            other => panic!("expected an invalid level, but got {other:?}"),
+        }
+    }
+
+    fn robot_api() -> Api {
+        Api::load("apis/robot.api.json").unwrap()
+    }
+
+    #[test]
+    fn loads_demo_level() {
+        let level = Level::load("levels/demo.level.json5").unwrap();
+        assert_eq!(level.size, [10, 10]);
+        assert_eq!(level.start.position, [5, 5]);
+        assert_eq!(level.start.orientation, Orientation::E);
+        assert_eq!(level.blocked, vec![[2, 2], [2, 3]]);
+        assert_eq!(level.goals, vec![[1, 8]]);
+        assert_eq!(level.walls.len(), 2);
+    }
+
+    #[test]
+    fn applies_defaults() {
+        let level = Level::parse(MINIMAL).unwrap();
+        assert_eq!(level.start.orientation, Orientation::default());
+        assert!(level.blocked.is_empty());
+        assert!(level.walls.is_empty());
+        assert!(level.goals.is_empty());
+        assert!(level.allows(&Identifier("anything".to_owned())));
+    }
+
+    #[test]
+    fn fails_on_missing_file() {
+        assert!(matches!(
+            Level::load("levels/missing.level.json5"),
+            Err(LevelError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let source = "{ size: [3, 2], start: { position: [0, 0] }, lava: [] }";
+        assert!(matches!(Level::parse(source), Err(LevelError::Parse(_))));
+    }
+
+    #[test]
+    fn contains_only_tiles_within_bounds() {
+        let level = Level::parse(MINIMAL).unwrap();
+        assert!(level.contains([0, 0]));
+        assert!(level.contains([2, 1]));
+        assert!(!level.contains([3, 1]));
+        assert!(!level.contains([2, 2]));
+        assert!(!level.contains([-1, 0]));
+    }
+
+    #[test]
+    fn rejects_empty_level() {
+        assert_eq!(
+            invalid_message("{ size: [0, 2], start: { position: [0, 0] } }"),
+            "the level must not be empty"
+        );
+    }
+
+    #[test]
+    fn rejects_huge_level() {
+        assert_eq!(
+            invalid_message("{ size: [3, 4000000000], start: { position: [0, 0] } }"),
+            "the level must not exceed 256 tiles along either axis, but has a size of 3x4000000000"
+        );
+        Level::parse("{ size: [256, 256], start: { position: [0, 0] } }").unwrap();
+    }
+
+    #[test]
+    fn rejects_start_outside() {
+        assert_eq!(
+            invalid_message("{ size: [3, 2], start: { position: [3, 0] } }"),
+            "start position at 3/0 lies outside the level"
+        );
+    }
+
+    #[test]
+    fn rejects_blocked_start() {
+        assert_eq!(
+            invalid_message("{ size: [3, 2], start: { position: [1, 1] }, blocked: [[1, 1]] }"),
+            "start position is blocked"
+        );
+    }
+
+    #[test]
+    fn rejects_elements_outside() {
+        assert_eq!(
+            invalid_message("{ size: [3, 2], start: { position: [0, 0] }, blocked: [[0, 2]] }"),
+            "blocked tile at 0/2 lies outside the level"
+        );
+        assert_eq!(
+            invalid_message("{ size: [3, 2], start: { position: [0, 0] }, goals: [[-1, 0]] }"),
+            "goal at -1/0 lies outside the level"
+        );
+        assert_eq!(
+            invalid_message(
+                "{ size: [3, 2], start: { position: [0, 0] }, \
+                    walls: [{ between: [[2, 1], [3, 1]] }] }"
+            ),
+            "wall at 3/1 lies outside the level"
+        );
+    }
+
+    #[test]
+    fn rejects_walls_between_distant_tiles() {
+        assert_eq!(
+            invalid_message(
+                "{ size: [3, 2], start: { position: [0, 0] }, \
+                    walls: [{ between: [[0, 0], [1, 1]] }] }"
+            ),
+            "wall between [0, 0] and [1, 1] doesn't separate adjacent tiles"
+        );
+    }
+
+    #[test]
+    fn restricts_api_to_allowed_functions() {
+        let level = Level::parse(
+            r#"{ size: [3, 2], start: { position: [0, 0] }, allowed_functions: ["turn left"] }"#,
+        )
+        .unwrap();
+        let api = level.restrict(&robot_api()).unwrap();
+        let names: Vec<&str> = api
+            .functions
+            .iter()
+            .map(|function| function.name.0.as_str())
+            .collect();
+        assert_eq!(names, ["turn left"]);
+    }
+
+    #[test]
+    fn keeps_all_functions_without_restriction() {
+        let api = robot_api();
+        let restricted = Level::parse(MINIMAL).unwrap().restrict(&api).unwrap();
+        assert_eq!(restricted.functions.len(), api.functions.len());
+    }
+
+    #[test]
+    fn rejects_unknown_allowed_functions() {
+        let level = Level::parse(
+            r#"{ size: [3, 2], start: { position: [0, 0] },
+                allowed_functions: ["turn left", "move forwards"] }"#,
+        )
+        .unwrap();
+        match level.restrict(&robot_api()) {
+            Err(LevelError::Invalid(message)) => assert_eq!(
+                message,
+                "the robot api has no function `move forwards` to allow"
+            ),
+            other => panic!("expected an unknown function, but got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn demo_level_fits_robot_api() {
+        let level = Level::load("levels/demo.level.json5").unwrap();
+        level.restrict(&robot_api()).unwrap();
+    }
+}
@@ -18,14 +18,22 @@
 pub mod api;
 pub mod application;
 pub mod bindgen;
+pub mod command;
 mod ecs;
 pub mod framework;
+pub mod headless;
+pub mod http;
+pub mod level;
 pub mod logging;
+pub mod lua;
+pub mod monitor;
 pub mod python;
 mod scene;
 pub mod transform;
+pub mod world;
 
-pub use scene::Command;
+pub use command::Command;
 use std::sync::atomic::AtomicU16;
+pub use world::Orientation;
 
 pub(crate) static ROTATION: AtomicU16 = AtomicU16::new(0);
@@ -0,0 +1,352 @@
+//! Runs Lua scripts which control the engine by calling the functions of an [`Api`].
+//!
+//! This is the Lua counterpart of [`crate::python`]: scripts use the same api, send the same
+//! [`Command`]s and are subject to the same [`Limits`], so a class may switch between both
+//! languages without changing its tasks. The interpreter is an embedded Lua 5.4.
+
+use std::{
+    fs::read_to_string,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{Receiver, Sender},
+        Arc,
+    },
+    time::Instant,
+};
+
+use log::{error, info};
+use mlua::{HookTriggers, Lua, LuaOptions, StdLib};
+
+use crate::{
+    api::Api,
+    bindgen,
+    command::{CommandError, Event},
+    monitor::{Activity, Monitor, OutputStream},
+    python::{Abort, CancelHandle, Limits, Sandbox, ScriptError, ScriptErrorKind},
+    Command,
+};
+
+mod api_module;
+
+/// number of instructions after which the interpreter checks whether to abort the script
+const CHECK_INTERVAL: u32 = 1000;
+
+/// Runs a Lua script which may control the engine by calling the functions of the given api.
+///
+/// The api is available as global table (e.g. `robot_api`) and via `require`.
+/// Each api call will be sent as a [`Command`] to the engine and blocks the script until the
+/// engine replied.
+/// [`Event`]s raised by the engine will be passed to the handlers the script registered for them
+/// whenever the script calls into the api.
+/// The script will be aborted if it exceeds any of the given `limits` or if it gets cancelled
+/// via the `cancel` handle, which can be asked for the reason afterwards.
+/// A restricted `sandbox` only provides the Lua libraries which don't touch the host.
+/// Whatever the script prints and the error which ended it get published to the `monitor`.
+///
+/// # Errors
+///
+/// Returns a [`ScriptError`] describing the problem if the script couldn't be loaded, failed or
+/// has been aborted.
+#[expect(
+    clippy::too_many_arguments,
+    reason = "the runners of all languages share this signature"
+)]
+pub fn runner(
+    source_path: impl AsRef<Path>,
+    sender: Sender<Command>,
+    events: Receiver<Event>,
+    api: &Api,
+    limits: Limits,
+    cancel: &CancelHandle,
+    sandbox: &Sandbox,
+    monitor: &Monitor,
+) -> Result<(), Box<ScriptError>> {
+    let path_string = source_path.as_ref().display().to_string();
+    let source = read_to_string(source_path).map_err(|error| {
+        Box::new(ScriptError::new(
+            ScriptErrorKind::Io,
+            &path_string,
+            format!("failed to read {path_string}: {error}"),
+        ))
+    })?;
+
+    let supervisor = Supervisor::new(limits, cancel.clone());
+    let result = run(
+        &source,
+        &path_string,
+        sender,
+        events,
+        api,
+        &supervisor,
+        sandbox,
+        monitor,
+    )
+    .map_err(|error| {
+        Box::new(script_error(
+            &error,
+            &path_string,
+            &source,
+            api,
+            cancel.reason(),
+        ))
+    });
+
+    match result {
+        Ok(()) => info!("Success: {path_string}"),
+        Err(ref error) => {
+            error!("{error}");
+            monitor.publish(&Activity::ScriptError((**error).clone()));
+        }
+    }
+    result
+}
+
+#[expect(
+    clippy::too_many_arguments,
+    reason = "takes the arguments of the runner, with the source read already"
+)]
+fn run(
+    source: &str,
+    path: &str,
+    sender: Sender<Command>,
+    events: Receiver<Event>,
+    api: &Api,
+    supervisor: &Supervisor,
+    sandbox: &Sandbox,
+    monitor: &Monitor,
+) -> mlua::Result<()> {
+    let libraries = match *sandbox {
+        Sandbox::Unrestricted => StdLib::ALL_SAFE,
+        Sandbox::Restricted { .. } => {
+            StdLib::COROUTINE | StdLib::MATH | StdLib::STRING | StdLib::TABLE | StdLib::UTF8
+        }
+    };
+    let lua = Lua::new_with(libraries, LuaOptions::default())?;
+
+    let module_name = bindgen::lua::module_name(api);
+    let module = api_module::make_module(&lua, api, sender, events, supervisor.clone())?;
+    let globals = lua.globals();
+    globals.set(module_name.as_str(), module.clone())?;
+    match *sandbox {
+        Sandbox::Unrestricted => {
+            let package: mlua::Table<'_> = globals.get("package")?;
+            let loaded: mlua::Table<'_> = package.get("loaded")?;
+            loaded.set(module_name.as_str(), module)?;
+        }
+        Sandbox::Restricted { .. } => {
+            // these load files from the host
+            globals.set("dofile", mlua::Nil)?;
+            globals.set("loadfile", mlua::Nil)?;
+            restrict_load(&lua)?;
+            // the api is the only module a restricted script may require
+            let module = lua.create_registry_value(module)?;
+            let require = lua.create_function(move |lua, name: String| {
+                if name == module_name {
+                    lua.registry_value::<mlua::Table<'_>>(&module)
+                } else {
+                    Err(mlua::Error::runtime(format!(
+                        "module '{name}' is not available in the sandbox"
+                    )))
+                }
+            })?;
+            globals.set("require", require)?;
+        }
+    }
+
+    // publishes each line instead of printing it; whoever runs the script decides where it goes
+    let monitor = monitor.clone();
+    let print = lua.create_function(move |lua, values: mlua::MultiValue<'_>| {
+        let tostring: mlua::Function<'_> = lua.globals().get("tostring")?;
+        let texts = values
+            .into_iter()
+            .map(|value| tostring.call::<_, String>(value))
+            .collect::<mlua::Result<Vec<_>>>()?;
+        let text = texts.join("\t");
+        monitor.publish(&Activity::Output {
+            stream: OutputStream::Stdout,
+            text,
+        });
+        Ok(())
+    })?;
+    globals.set("print", print)?;
+
+    supervise(&lua, supervisor.clone(), CHECK_INTERVAL);
+
+    // the `@` marks the name as a path, which Lua will use for the locations within messages
+    lua.load(source).set_name(format!("@{path}")).exec()?;
+    // the script may have caught the abort and returned before failing again
+    supervisor.check()
+}
+
+/// Only lets scripts load source code, no precompiled chunks.
+///
+/// The Lua VM doesn't verify bytecode, so crafted chunks could corrupt the memory of the engine.
+fn restrict_load(lua: &Lua) -> mlua::Result<()> {
+    let globals = lua.globals();
+    let string: mlua::Table<'_> = globals.get("string")?;
+    string.set("dump", mlua::Nil)?;
+
+    let load = lua.create_registry_value(globals.get::<_, mlua::Function<'_>>("load")?)?;
+    let restricted = lua.create_function(move |lua, arguments: mlua::MultiValue<'_>| {
+        // `load(chunk, name, mode, env)` treats a given `nil` environment differently than a
+        // missing one, so only the mode gets replaced
+        let mut arguments = arguments.into_vec();
+        if arguments.len() < 3 {
+            arguments.resize(3, mlua::Nil);
+        }
+        if let Some(mode) = arguments.get_mut(2) {
+            *mode = mlua::Value::String(lua.create_string("t")?);
+        }
+        lua.registry_value::<mlua::Function<'_>>(&load)?
+            .call::<_, mlua::MultiValue<'_>>(mlua::MultiValue::from_vec(arguments))
+    })?;
+    globals.set("load", restricted)
+}
+
+/// Lets the interpreter check the `supervisor` every `interval` instructions.
+///
+/// Scripts may catch the error of an abort with `pcall` and carry on, so once aborted, the check
+/// fails at every single instruction, until there is no more `pcall` left to catch it.
+fn supervise(lua: &Lua, supervisor: Supervisor, interval: u32) {
+    lua.set_hook(
+        HookTriggers::new().every_nth_instruction(interval),
+        move |lua, _debug| {
+            let result = supervisor.check();
+            if result.is_err() && interval > 1 {
+                supervise(lua, supervisor.clone(), 1);
+            }
+            result
+        },
+    );
+}
+
+/// Enforces the limits of a single run; shared by the runner and all api functions.
+#[derive(Clone, Debug)]
+struct Supervisor {
+    limits: Limits,
+    cancel: CancelHandle,
+    deadline: Option<Instant>,
+    command_count: Arc<AtomicUsize>,
+}
+
+impl Supervisor {
+    fn new(limits: Limits, cancel: CancelHandle) -> Self {
+        Self {
+            deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+            limits,
+            cancel,
+            command_count: Arc::default(),
+        }
+    }
+
+    /// Fails if the script shall not continue.
+    fn check(&self) -> mlua::Result<()> {
+        if let (Some(deadline), Some(timeout)) = (self.deadline, self.limits.timeout) {
+            if Instant::now() >= deadline {
+                self.cancel.abort(Abort::Timeout(timeout));
+            }
+        }
+        match self.cancel.reason() {
+            Some(reason) => Err(mlua::Error::runtime(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Accounts for another command to be sent and fails if this exceeds the budget.
+    fn count_command(&self) -> mlua::Result<()> {
+        self.check()?;
+        let count = self.command_count.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(max_commands) = self.limits.max_commands {
+            if count > max_commands {
+                self.cancel.abort(Abort::CommandBudget(max_commands));
+                self.check()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describes why a script failed.
+fn script_error(
+    error: &mlua::Error,
+    file: &str,
+    source: &str,
+    api: &Api,
+    reason: Option<Abort>,
+) -> ScriptError {
+    // the error raised by an api function is wrapped once for each call it passed through
+    let mut cause = error;
+    let mut traceback = None;
+    while let mlua::Error::CallbackError {
+        cause: ref inner,
+        traceback: ref inner_traceback,
+    } = *cause
+    {
+        cause = inner;
+        traceback = Some(inner_traceback.as_str());
+    }
+
+    let (kind, message) = match reason {
+        Some(reason @ Abort::Timeout(_)) => (ScriptErrorKind::Timeout, reason.to_string()),
+        Some(reason) => (ScriptErrorKind::Aborted, reason.to_string()),
+        None => {
+            let kind = match *cause {
+                mlua::Error::SyntaxError { .. } => ScriptErrorKind::Syntax,
+                _ if cause.downcast_ref::<CommandError>().is_some()
+                    || cause.downcast_ref::<api_module::ApiMisuse>().is_some() =>
+                {
+                    ScriptErrorKind::ApiMisuse
+                }
+                _ => ScriptErrorKind::Runtime,
+            };
+            let message = match *cause {
+                mlua::Error::SyntaxError { ref message, .. }
+                | mlua::Error::RuntimeError(ref message) => message.clone(),
+                _ => cause.to_string(),
+            };
+            (kind, message)
+        }
+    };
+
+    let mut script_error = ScriptError::new(kind, file, message);
+    script_error.line = std::iter::once(script_error.message.as_str())
+        .chain(traceback)
+        .find_map(|text| line(text, file));
+    script_error.source_line = script_error
+        .line
+        .and_then(|line| source.lines().nth(line.checked_sub(1)?))
+        .map(|line| line.trim().to_owned());
+    script_error.traceback = traceback.map(str::to_owned);
+    if kind == ScriptErrorKind::Syntax {
+        "Lua couldn't understand this line. Look for missing brackets, quotes or an `end`, here \
+            or in the lines above."
+            .clone_into(&mut script_error.explanation);
+    }
+
+    // errors declared by the api come with their own explanation
+    if let Some(description) = cause
+        .downcast_ref::<CommandError>()
+        .and_then(|command_error| {
+            api.functions
+                .iter()
+                .flat_map(|function| &function.errors)
+                .find(|declared| declared.name == command_error.kind)
+                .map(|declared| declared.description.to_string())
+        })
+    {
+        script_error.explanation = description;
+    }
+
+    script_error
+}
+
+/// Extracts the line number from a location like `script.lua:12:` within the given text.
+fn line(text: &str, file: &str) -> Option<usize> {
+    let prefix = format!("{file}:");
+    text.match_indices(&prefix).find_map(|(index, _)| {
+        let rest = text.get(index + prefix.len()..)?;
+        let digits = rest.split(':').next()?;
+        digits.parse().ok()
+    })
+}
@@ -0,0 +1,465 @@
+//! Builds the Lua table of an [`Api`] at runtime.
+//!
+//! Every [`FunctionDescriptor`] of the api becomes a function within this table. Calling it will
+//! convert its arguments and send a corresponding [`Command`] to the engine. Missing trailing
+//! arguments (or `nil`) get their default value. The call blocks until the engine has finished
+//! executing the command and returns the value the engine replied with.
+//!
+//! Records are passed as plain tables, enums as the technical names of their variants, which are
+//! also available as constants (e.g. `robot_api.Orientation.NORTH_EAST`).
+//!
+//! Every [`EventDescriptor`] becomes a function (e.g. `on_bumped_into_wall`), which registers
+//! a handler for that event. Events are being dispatched after each function call and while the
+//! script is waiting for them in `wait_for_events`.
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+use mlua::{Function, Lua, MultiValue, Table, Value as LuaValue};
+
+use crate::{
+    api::{Api, EventDescriptor, FunctionDescriptor, Identifier, TypeDescriptor, TypeKind, Value},
+    bindgen,
+    command::Event,
+    Command,
+};
+
+use super::Supervisor;
+
+/// how often a script waiting for the engine checks whether it has been aborted
+const ABORT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// key of the registry table which maps event names to the list of their handlers
+const HANDLERS: &str = "gam3du.handlers";
+
+/// The script called an api function in a wrong way
+#[derive(Debug)]
+pub(super) struct ApiMisuse(String);
+
+impl Display for ApiMisuse {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.0)
+    }
+}
+
+impl Error for ApiMisuse {}
+
+fn misuse(message: impl Into<String>) -> mlua::Error {
+    mlua::Error::external(ApiMisuse(message.into()))
+}
+
+/// State shared by all functions of the table
+struct Bindings {
+    api: Api,
+    sender: Sender<Command>,
+    supervisor: Supervisor,
+    events: Mutex<Receiver<Event>>,
+    /// set while handlers are running, as those must not be interrupted by other handlers
+    dispatching: AtomicBool,
+}
+
+impl Bindings {
+    fn events(&self) -> MutexGuard<'_, Receiver<Event>> {
+        self.events.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Creates a new table containing one function for each function of the given api, one
+/// function to register handlers for each of its events and the constants of its enums.
+pub(super) fn make_module<'lua>(
+    lua: &'lua Lua,
+    api: &Api,
+    sender: Sender<Command>,
+    events: Receiver<Event>,
+    supervisor: Supervisor,
+) -> mlua::Result<Table<'lua>> {
+    let module = lua.create_table()?;
+    let bindings = Arc::new(Bindings {
+        api: api.clone(),
+        sender,
+        supervisor,
+        events: Mutex::new(events),
+        dispatching: AtomicBool::new(false),
+    });
+
+    for definition in &api.types {
+        let TypeKind::Enum(ref variants) = definition.kind else {
+            continue;
+        };
+        let constants = lua.create_table()?;
+        for variant in variants {
+            constants.set(
+                bindgen::lua::enum_member(&variant.name),
+                variant.name.0.as_str(),
+            )?;
+        }
+        module.set(bindgen::lua::class_name(&definition.name), constants)?;
+    }
+
+    for function in &api.functions {
+        let name = bindgen::lua::identifier(&function.name);
+        let descriptor = function.clone();
+        let bindings = Arc::clone(&bindings);
+        let callable = lua.create_function(move |lua, arguments: MultiValue<'_>| {
+            call(lua, &bindings, &descriptor, arguments)
+        })?;
+        module.set(name, callable)?;
+    }
+
+    lua.set_named_registry_value(HANDLERS, lua.create_table()?)?;
+    for event in &api.events {
+        module.set(
+            bindgen::lua::event_handler(&event.name),
+            make_register(lua, event)?,
+        )?;
+    }
+    if !api.events.is_empty() {
+        let bindings = Arc::clone(&bindings);
+        let wait = lua.create_function(move |lua, seconds: Option<f64>| {
+            wait_for_events(lua, &bindings, seconds)
+        })?;
+        module.set("wait_for_events", wait)?;
+    }
+
+    Ok(module)
+}
+
+/// Converts the arguments, sends the command and waits for the engine to reply.
+fn call<'lua>(
+    lua: &'lua Lua,
+    bindings: &Bindings,
+    function: &FunctionDescriptor,
+    arguments: MultiValue<'lua>,
+) -> mlua::Result<LuaValue<'lua>> {
+    let name = bindgen::lua::identifier(&function.name);
+    if arguments.len() > function.parameters.len() {
+        return Err(misuse(format!(
+            "{name}() takes at most {expected} arguments, but got {actual}",
+            expected = function.parameters.len(),
+            actual = arguments.len()
+        )));
+    }
+
+    let mut arguments = arguments.into_iter();
+    let arguments = function
+        .parameters
+        .iter()
+        .map(|parameter| {
+            let parameter_name = bindgen::lua::identifier(&parameter.name);
+            match arguments.next() {
+                None | Some(LuaValue::Nil) => parameter.default.clone().ok_or_else(|| {
+                    misuse(format!(
+                        "{name}() missing required argument: '{parameter_name}'"
+                    ))
+                }),
+                Some(argument) => {
+                    to_value(lua, &argument, &parameter.typ, &bindings.api).map_err(|error| {
+                        misuse(format!("{name}(): argument `{parameter_name}` {error}"))
+                    })
+                }
+            }
+        })
+        .collect::<mlua::Result<Vec<_>>>()?;
+
+    let (command, reply) = Command::new(&bindings.api, function, arguments)
+        .map_err(|error| misuse(format!("{name}(): {error}")))?;
+
+    bindings.supervisor.count_command()?;
+    bindings
+        .sender
+        .send(command)
+        .map_err(|_closed| mlua::Error::runtime("the engine has been shut down"))?;
+
+    // waiting might take a while, so the script must stay abortable meanwhile
+    let result = loop {
+        match reply.recv_timeout(ABORT_POLL_INTERVAL) {
+            Ok(result) => break result,
+            Err(RecvTimeoutError::Timeout) => bindings.supervisor.check()?,
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(mlua::Error::runtime(
+                    "the engine did not reply to the command",
+                ))
+            }
+        }
+    };
+
+    let returned = match (result, function.returns.as_ref()) {
+        (Ok(Some(value)), Some(returns)) => to_lua(lua, value, &returns.typ, &bindings.api),
+        // the reply has already complained about an unexpected return value
+        (Ok(_), _) => Ok(LuaValue::Nil),
+        (Err(error), _) => Err(mlua::Error::external(error)),
+    };
+
+    // the command might have raised some events, which should be handled right away
+    dispatch_events(lua, bindings)?;
+    returned
+}
+
+/// Creates a function which registers a handler for the given event.
+fn make_register<'lua>(lua: &'lua Lua, event: &EventDescriptor) -> mlua::Result<Function<'lua>> {
+    let event_name = event.name.0.clone();
+    let name = bindgen::lua::event_handler(&event.name);
+    lua.create_function(move |lua, handler: LuaValue<'_>| {
+        let LuaValue::Function(ref function) = handler else {
+            return Err(misuse(format!(
+                "{name}() expects a function to be called when the event occurs"
+            )));
+        };
+        let handlers: Table<'_> = lua.named_registry_value(HANDLERS)?;
+        let registered: Table<'_> =
+            if let LuaValue::Table(registered) = handlers.get(event_name.as_str())? {
+                registered
+            } else {
+                let created = lua.create_table()?;
+                handlers.set(event_name.as_str(), created.clone())?;
+                created
+            };
+        registered.push(function.clone())?;
+        // the function stays available to the script
+        Ok(handler)
+    })
+}
+
+/// Blocks the script and handles events as they arrive.
+///
+/// It waits for the given number of seconds or until the engine shuts down if there is none.
+fn wait_for_events(lua: &Lua, bindings: &Bindings, seconds: Option<f64>) -> mlua::Result<()> {
+    if bindings.dispatching.load(Ordering::Relaxed) {
+        return Err(misuse(
+            "wait_for_events() must not be called by an event handler",
+        ));
+    }
+    let deadline = seconds
+        .map(|seconds| {
+            Duration::try_from_secs_f64(seconds)
+                .map(|duration| Instant::now() + duration)
+                .map_err(|_invalid| {
+                    misuse(format!(
+                        "wait_for_events() expects a positive number of seconds, but got {seconds}"
+                    ))
+                })
+        })
+        .transpose()?;
+
+    loop {
+        // waiting might take a while, so the script must stay abortable meanwhile
+        bindings.supervisor.check()?;
+        let timeout = deadline.map_or(ABORT_POLL_INTERVAL, |deadline| {
+            deadline
+                .saturating_duration_since(Instant::now())
+                .min(ABORT_POLL_INTERVAL)
+        });
+        let received = bindings.events().recv_timeout(timeout);
+        match received {
+            Ok(event) => {
+                bindings.dispatching.store(true, Ordering::Relaxed);
+                let handled = dispatch_event(lua, event, bindings);
+                bindings.dispatching.store(false, Ordering::Relaxed);
+                handled?;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            // the engine has shut down, so there won't be any more events
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Ok(());
+        }
+    }
+}
+
+/// Calls the handlers of all events which have arrived so far.
+///
+/// Events raised while handlers are running will be handled once those have finished.
+fn dispatch_events(lua: &Lua, bindings: &Bindings) -> mlua::Result<()> {
+    if bindings.dispatching.swap(true, Ordering::Relaxed) {
+        return Ok(());
+    }
+    let mut handled = Ok(());
+    loop {
+        let received = bindings.events().try_recv();
+        let Ok(event) = received else {
+            break;
+        };
+        handled = dispatch_event(lua, event, bindings);
+        if handled.is_err() {
+            break;
+        }
+    }
+    bindings.dispatching.store(false, Ordering::Relaxed);
+    handled
+}
+
+/// Calls all handlers of the given event, passing its payload as arguments.
+///
+/// Events without handlers will be dropped.
+fn dispatch_event(lua: &Lua, event: Event, bindings: &Bindings) -> mlua::Result<()> {
+    let Some(descriptor) = bindings.api.event(&event.name.0) else {
+        return Ok(());
+    };
+    let handlers: Table<'_> = lua.named_registry_value(HANDLERS)?;
+    // handlers may register further handlers, so the list must be copied
+    let handlers: Vec<Function<'_>> = match handlers.get(event.name.0.as_str())? {
+        LuaValue::Table(registered) => registered.sequence_values().collect::<mlua::Result<_>>()?,
+        _ => return Ok(()),
+    };
+
+    let arguments = event
+        .payload
+        .into_iter()
+        .zip(&descriptor.payload)
+        .map(|(value, declaration)| to_lua(lua, value, &declaration.typ, &bindings.api))
+        .collect::<mlua::Result<MultiValue<'_>>>()?;
+    for handler in handlers {
+        handler.call::<_, ()>(arguments.clone())?;
+    }
+    Ok(())
+}
+
+/// Converts a Lua value into a [`Value`] of the given type.
+///
+/// This only performs the conversion; range checks are left to [`Command::new`].
+fn to_value(
+    lua: &Lua,
+    value: &LuaValue<'_>,
+    typ: &TypeDescriptor,
+    api: &Api,
+) -> Result<Value, String> {
+    let mismatch = || format!("must be of type {typ}, but got a {}", value.type_name());
+
+    let converted = match *typ {
+        TypeDescriptor::Integer(_) => match *value {
+            LuaValue::Integer(integer) => Value::Integer(integer),
+            // floats without a fractional part are fine, as Lua uses them for some integers
+            LuaValue::Number(_) => Value::Integer(
+                lua.unpack(value.clone())
+                    .map_err(|_fractional| mismatch())?,
+            ),
+            _ => return Err(mismatch()),
+        },
+        TypeDescriptor::Float => match *value {
+            LuaValue::Integer(_) | LuaValue::Number(_) => {
+                Value::Float(lua.unpack(value.clone()).map_err(|_invalid| mismatch())?)
+            }
+            _ => return Err(mismatch()),
+        },
+        TypeDescriptor::Boolean => {
+            let LuaValue::Boolean(boolean) = *value else {
+                return Err(mismatch());
+            };
+            Value::Boolean(boolean)
+        }
+        TypeDescriptor::String => {
+            let LuaValue::String(ref string) = *value else {
+                return Err(mismatch());
+            };
+            Value::String(string.to_str().map_err(|_invalid| mismatch())?.to_owned())
+        }
+        TypeDescriptor::List(ref element_type) => {
+            let LuaValue::Table(ref table) = *value else {
+                return Err(mismatch());
+            };
+            Value::List(
+                table
+                    .clone()
+                    .sequence_values::<LuaValue<'_>>()
+                    .map(|element| {
+                        let element = element.map_err(|error| error.to_string())?;
+                        to_value(lua, &element, element_type, api)
+                    })
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        TypeDescriptor::Named(ref name) => {
+            let definition = api
+                .type_definition(name)
+                .ok_or_else(|| format!("refers to the unknown type `{name}`"))?;
+            match definition.kind {
+                TypeKind::Record(ref fields) => {
+                    let LuaValue::Table(ref table) = *value else {
+                        return Err(mismatch());
+                    };
+                    Value::Record(
+                        fields
+                            .iter()
+                            .map(|field| {
+                                let field_value: LuaValue<'_> = table
+                                    .get(bindgen::lua::identifier(&field.name))
+                                    .map_err(|error| error.to_string())?;
+                                let converted = to_value(lua, &field_value, &field.typ, api)?;
+                                Ok((field.name.clone(), converted))
+                            })
+                            .collect::<Result<_, String>>()?,
+                    )
+                }
+                TypeKind::Enum(_) => {
+                    let LuaValue::String(ref variant) = *value else {
+                        return Err(mismatch());
+                    };
+                    Value::Enum(Identifier(
+                        variant.to_str().map_err(|_invalid| mismatch())?.to_owned(),
+                    ))
+                }
+            }
+        }
+    };
+    Ok(converted)
+}
+
+/// Converts a [`Value`] of the given type into the corresponding Lua value.
+fn to_lua<'lua>(
+    lua: &'lua Lua,
+    value: Value,
+    typ: &TypeDescriptor,
+    api: &Api,
+) -> mlua::Result<LuaValue<'lua>> {
+    let converted = match value {
+        Value::Integer(integer) => LuaValue::Integer(integer),
+        Value::Float(float) => LuaValue::Number(float),
+        Value::Boolean(boolean) => LuaValue::Boolean(boolean),
+        Value::String(string) | Value::Enum(Identifier(string)) => {
+            LuaValue::String(lua.create_string(string)?)
+        }
+        Value::List(elements) => {
+            let element_type = match *typ {
+                TypeDescriptor::List(ref element_type) => &**element_type,
+                _ => typ,
+            };
+            let table = lua.create_table()?;
+            for element in elements {
+                table.push(to_lua(lua, element, element_type, api)?)?;
+            }
+            LuaValue::Table(table)
+        }
+        Value::Record(fields) => {
+            let declared = match *typ {
+                TypeDescriptor::Named(ref name) => api.type_definition(name),
+                _ => None,
+            };
+            let table = lua.create_table()?;
+            for (name, field_value) in fields {
+                let field_type = declared
+                    .and_then(|definition| match definition.kind {
+                        TypeKind::Record(ref declared_fields) => {
+                            declared_fields.iter().find(|field| field.name == name)
+                        }
+                        TypeKind::Enum(_) => None,
+                    })
+                    .map_or(typ, |field| &field.typ);
+                table.set(
+                    bindgen::lua::identifier(&name),
+                    to_lua(lua, field_value, field_type, api)?,
+                )?;
+            }
+            LuaValue::Table(table)
+        }
+    };
+    Ok(converted)
+}
@@ -0,0 +1,124 @@
+//! Lets observers follow what the engine and the scripts are doing, e.g. a teacher watching the
+//! robots of a class via the event stream of the HTTP control server (see [`crate::http`]).
+//!
+//! Whoever takes part in running a script publishes its [`Activity`] to a shared [`Monitor`],
+//! which passes it on to all current subscribers. Nobody waits for the subscribers, so publishing
+//! is cheap even if nobody is watching.
+
+use std::sync::{
+    mpsc::{channel, Receiver, Sender},
+    Arc, Mutex, MutexGuard, PoisonError,
+};
+
+use serde::Serialize;
+use serde_json::Value as Json;
+
+use crate::{
+    api::{Identifier, Value},
+    command::{CommandError, CommandResult},
+    python::ScriptError,
+    Command,
+};
+
+/// Distributes the activity of the engine and the scripts to any number of subscribers.
+///
+/// Clones share their subscribers, so each participant may keep a clone of its own.
+#[derive(Clone, Debug, Default)]
+pub struct Monitor {
+    subscribers: Arc<Mutex<Vec<Sender<Activity>>>>,
+}
+
+impl Monitor {
+    /// Returns a receiver for all activity published from now on.
+    ///
+    /// Dropping the receiver cancels the subscription.
+    #[must_use]
+    pub fn subscribe(&self) -> Receiver<Activity> {
+        let (sender, receiver) = channel();
+        self.subscribers().push(sender);
+        receiver
+    }
+
+    /// Passes the activity on to all subscribers.
+    pub fn publish(&self, activity: &Activity) {
+        self.subscribers()
+            .retain(|subscriber| subscriber.send(activity.clone()).is_ok());
+    }
+
+    fn subscribers(&self) -> MutexGuard<'_, Vec<Sender<Activity>>> {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Something that happened within the engine or a script
+///
+/// Serialized as a json object whose `type` tells the kind of activity, e.g.
+/// `{"type": "animation_completed", "function": "move forward"}`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Activity {
+    /// The engine has processed a [`Command`].
+    Command {
+        /// technical name of the function
+        function: Identifier,
+        /// argument values in the order of the function's parameters
+        arguments: Vec<Json>,
+        /// the returned value; `null` if the function returns nothing or has been refused
+        result: Json,
+        /// the reason why the engine refused the command
+        error: Option<CommandError>,
+    },
+    /// The robot has finished animating a command.
+    AnimationCompleted {
+        /// technical name of the function which has been animated
+        function: Identifier,
+    },
+    /// A script failed or has been aborted.
+    ScriptError(ScriptError),
+    /// A script has written a line of text.
+    Output {
+        stream: OutputStream,
+        /// the text without the trailing line break
+        text: String,
+    },
+}
+
+impl Activity {
+    /// Describes the processing of the given command, which resulted in `result`.
+    ///
+    /// Values are represented as described by [`Value::to_json`].
+    #[must_use]
+    pub fn command(command: &Command, result: &CommandResult) -> Self {
+        let (result, error) = match *result {
+            Ok(ref value) => (value.as_ref().map_or(Json::Null, Value::to_json), None),
+            Err(ref error) => (Json::Null, Some(error.clone())),
+        };
+        Self::Command {
+            function: command.name.clone(),
+            arguments: command.arguments.iter().map(Value::to_json).collect(),
+            result,
+            error,
+        }
+    }
+
+    /// Returns the name of this kind of activity, which is also its `type` when serialized.
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            Self::Command { .. } => "command",
+            Self::AnimationCompleted { .. } => "animation_completed",
+            Self::ScriptError(_) => "script_error",
+            Self::Output { .. } => "output",
+        }
+    }
+}
+
+/// The stream a script has written its output to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
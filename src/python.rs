@@ -2,88 +2,198 @@
 // TODO re-enable this later and review all occurrences
 #![allow(clippy::cast_precision_loss)]
 
-use std::{fs::read_to_string, path::Path, sync::mpsc::Sender};
+use std::{
+    fs::read_to_string,
+    path::Path,
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc, Mutex,
+    },
+};
 
-use log::{error, info};
+use log::{error, info, warn};
 use rustpython_vm::{
     compiler::Mode, pyclass, pymodule, PyObject, PyPayload, PyResult, TryFromBorrowedObject,
     VirtualMachine,
 };
 
-use crate::{api::Api, scene::Command};
+use crate::{
+    api::Api,
+    bindgen,
+    command::Event,
+    monitor::{Activity, Monitor},
+    Command,
+};
+
+use self::limits::Supervisor;
+
+mod api_module;
+mod error;
+mod limits;
+mod output;
+mod sandbox;
+
+pub use error::{ScriptError, ScriptErrorKind};
+pub use limits::{Abort, CancelHandle, Limits};
+pub use sandbox::Sandbox;
 
-#[allow(clippy::missing_panics_doc)]
-pub fn runner(source_path: &(impl AsRef<Path> + ToString), sender: Sender<Command>, api: &Api) {
-    let source = read_to_string(source_path).unwrap();
+/// Runs a Python script which may control the engine by calling the functions of the given api.
+///
+/// Each api call will be sent as a [`Command`] to the engine and blocks the script until the
+/// engine replied.
+/// [`Event`]s raised by the engine will be passed to the handlers the script registered for them
+/// whenever the script calls into the api.
+/// The script will be aborted if it exceeds any of the given `limits` or if it gets cancelled
+/// via the `cancel` handle, which can be asked for the reason afterwards.
+/// The `sandbox` decides which modules and builtins the script may use.
+/// The output of the script and the error which ended it get published to the `monitor`; the
+/// output isn't printed.
+///
+/// # Errors
+///
+/// Returns a [`ScriptError`] describing the problem if the script couldn't be loaded, failed or
+/// has been aborted.
+#[expect(
+    clippy::missing_panics_doc,
+    reason = "only panics if the interpreter can't be set up at all"
+)]
+#[expect(
+    clippy::too_many_arguments,
+    reason = "the runners of all languages share this signature"
+)]
+pub fn runner(
+    source_path: impl AsRef<Path>,
+    sender: Sender<Command>,
+    events: Receiver<Event>,
+    api: &Api,
+    limits: Limits,
+    cancel: &CancelHandle,
+    sandbox: &Sandbox,
+    monitor: &Monitor,
+) -> Result<(), Box<ScriptError>> {
     let path_string = source_path.as_ref().display().to_string();
+    let source = read_to_string(source_path).map_err(|error| {
+        Box::new(ScriptError::new(
+            ScriptErrorKind::Io,
+            &path_string,
+            format!("failed to read {path_string}: {error}"),
+        ))
+    })?;
 
-    rust_py_module::COMMAND_QUEUE
-        .lock()
-        .unwrap()
-        .replace(sender);
+    let allowed_modules = allowed_modules(sandbox, api);
+    let supervisor = Supervisor::new(limits, cancel.clone());
+    let signal_receiver = supervisor.start_watchdog();
+    let module_supervisor = supervisor.clone();
+    let module_api = api.clone();
 
     let interpreter = rustpython::InterpreterConfig::new()
         .init_stdlib()
-        .init_hook(Box::new(|vm| {
+        .init_hook(Box::new(move |vm| {
+            vm.set_user_signal_channel(signal_receiver);
+
+            // the module might be created more than once, but there's only one script listening
+            let events = Arc::new(Mutex::new(events));
+
             vm.add_native_module(
-                "robot_api".to_owned(),
+                "rust_py_module".to_owned(),
                 Box::new(rust_py_module::make_module),
             );
 
-            // vm.add_native_module(
-            //     "robot_api2".to_owned(),
-            //     Box::new(|vm: &VirtualMachine| {
-            //         let module = PyModule::new();
-            //         // ???
-            //         module.into_ref(&vm.ctx)
-            //     }),
-            // );
+            let shim_api = module_api.clone();
+            vm.add_native_module(
+                bindgen::python::module_name(&module_api),
+                Box::new(move |module_vm: &VirtualMachine| {
+                    api_module::make_shim(module_vm, &shim_api)
+                }),
+            );
+            vm.add_native_module(
+                bindgen::python::native_module_name(&module_api),
+                Box::new(move |module_vm: &VirtualMachine| {
+                    api_module::make_module(
+                        module_vm,
+                        &module_api,
+                        &sender,
+                        &events,
+                        &module_supervisor,
+                    )
+                }),
+            );
         }))
         .interpreter();
 
-    interpreter.enter(|vm| {
+    let result = interpreter.enter(|vm| {
         vm.insert_sys_path(vm.new_pyobj("python"))
             .expect("add path");
+        let script_error = |exception| {
+            Box::new(ScriptError::from_exception(
+                vm,
+                &exception,
+                &path_string,
+                &source,
+                api,
+                cancel.reason(),
+            ))
+        };
+        // the script may already be aborted while the interpreter is still starting up
+        output::install(vm, monitor).map_err(script_error)?;
 
-        match vm.import("robot", 0) {
-            Ok(module) => {
-                let init_fn = module.get_attr("python_callback", vm).unwrap();
-                init_fn.call((), vm).unwrap();
+        let scope = vm.new_scope_with_builtins();
+        let py_code = vm
+            .compile(&source, Mode::Exec, path_string.clone())
+            .map_err(|error| Box::new(ScriptError::from_compile_error(vm, &error, &source)))?;
 
-                let take_string_fn = module.get_attr("take_string", vm).unwrap();
-                take_string_fn
-                    .call((String::from("Rust string sent to python"),), vm)
-                    .unwrap();
-            }
-            Err(exc) => {
-                let mut msg = String::new();
-                vm.write_exception(&mut msg, &exc).unwrap();
-                panic!("{msg}");
+        let run = match allowed_modules.as_deref() {
+            Some(allowed_modules) => {
+                sandbox::check_code(vm, &py_code, allowed_modules).map_err(|violation| {
+                    Box::new(ScriptError::from_violation(
+                        vm,
+                        &violation,
+                        &path_string,
+                        &source,
+                        api,
+                    ))
+                })?;
+                sandbox::install(vm, &scope.globals, allowed_modules)
+                    .and_then(|()| vm.run_code_obj(py_code, scope))
             }
+            None => vm.run_code_obj(py_code, scope),
+        };
+        // stop interrupting, as the interpreter is still needed to format the exception
+        supervisor.finish();
+        if output::flush(vm).is_err() {
+            warn!("failed to publish the last line of output");
         }
 
-        let scope = vm.new_scope_with_builtins();
-        let compile = vm.compile(&source, Mode::Exec, path_string);
-
-        match compile {
-            Ok(py_code) => match vm.run_code_obj(py_code, scope) {
-                Ok(code_result) => {
-                    info!("Success: {code_result:?}");
-                }
-                Err(exception) => {
-                    let mut output = String::new();
-                    vm.write_exception(&mut output, &exception).unwrap();
-                    error!("Syntax error: {output}");
-                }
-            },
-            Err(err) => {
-                let exception = vm.new_syntax_error(&err, Some(&source));
-                let mut output = String::new();
-                vm.write_exception(&mut output, &exception).unwrap();
-                error!("Runtime error: {output}");
+        match run {
+            Ok(code_result) => {
+                info!("Success: {code_result:?}");
+                Ok(())
             }
+            Err(exception) => Err(script_error(exception)),
         }
     });
+
+    if let Err(ref error) = result {
+        error!("{error}");
+        monitor.publish(&Activity::ScriptError((**error).clone()));
+    }
+
+    supervisor.finish();
+    result
+}
+
+/// Returns the modules a script may import or `None` if it may import anything.
+fn allowed_modules(sandbox: &Sandbox, api: &Api) -> Option<Vec<String>> {
+    match *sandbox {
+        Sandbox::Unrestricted => None,
+        Sandbox::Restricted {
+            ref allowed_modules,
+        } => {
+            let mut allowed_modules = allowed_modules.clone();
+            allowed_modules.push(bindgen::python::module_name(api));
+            Some(allowed_modules)
+        }
+    }
 }
 
 #[pymodule]
@@ -94,19 +204,13 @@ pub fn runner(source_path: &(impl AsRef<Path> + ToString), sender: Sender<Comman
     clippy::unused_self
 )]
 mod rust_py_module {
-    use std::{
-        sync::{atomic::Ordering, mpsc::Sender, Mutex},
-        thread,
-        time::Duration,
-    };
+    use std::sync::atomic::Ordering;
 
-    use crate::{api::Identifier, scene::Command, ROTATION};
+    use crate::ROTATION;
 
     use super::{pyclass, PyObject, PyPayload, PyResult, TryFromBorrowedObject, VirtualMachine};
     use rustpython::vm::{builtins::PyList, convert::ToPyObject, PyObjectRef};
 
-    pub(super) static COMMAND_QUEUE: Mutex<Option<Sender<Command>>> = Mutex::new(None);
-
     #[pyfunction]
     fn rust_function(
         num: i32,
@@ -126,48 +230,6 @@ python_person.name: {}",
         })
     }
 
-    #[pyfunction]
-    fn move_forward() {
-        COMMAND_QUEUE
-            .lock()
-            .unwrap()
-            .as_mut()
-            .unwrap()
-            .send(Command {
-                name: Identifier("MoveForward".to_owned()),
-            })
-            .unwrap();
-        thread::sleep(Duration::from_millis(1000));
-    }
-
-    #[pyfunction]
-    fn turn_left() {
-        COMMAND_QUEUE
-            .lock()
-            .unwrap()
-            .as_mut()
-            .unwrap()
-            .send(Command {
-                name: Identifier("TurnLeft".to_owned()),
-            })
-            .unwrap();
-        thread::sleep(Duration::from_millis(1000));
-    }
-
-    #[pyfunction]
-    fn turn_right() {
-        COMMAND_QUEUE
-            .lock()
-            .unwrap()
-            .as_mut()
-            .unwrap()
-            .send(Command {
-                name: Identifier("TurnRight".to_owned()),
-            })
-            .unwrap();
-        thread::sleep(Duration::from_millis(1000));
-    }
-
     #[pyfunction]
     fn rotate_cube(angle: u16) {
         println!("angle {angle}");
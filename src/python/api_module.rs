@@ -0,0 +1,584 @@
+//! Builds a native Python module at runtime from an [`Api`] description.
+//!
+//! Every [`FunctionDescriptor`] of the api becomes a Python callable within this module.
+//! Calling it will convert its arguments and send a corresponding [`Command`] to the engine.
+//! The call blocks until the engine has finished executing the command and returns the value
+//! the engine replied with.
+//!
+//! Every [`EventDescriptor`] becomes a decorator (e.g. `on_bumped_into_wall`), which registers
+//! the decorated function as handler for that event. Events are being dispatched after each
+//! function call and while the script is waiting for them in `wait_for_events`.
+//!
+//! Scripts don't import the native module directly, but the shim generated by
+//! [`bindgen::python::generate`], which re-exports it. This way the very same script also runs
+//! outside of the engine.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+use rustpython_vm::{
+    builtins::{PyBaseExceptionRef, PyDict, PyDictRef, PyModule, PyType},
+    function::{ArgIntoFloat, FuncArgs, OptionalOption},
+    scope::Scope,
+    AsObject, PyObjectRef, PyRef, PyResult, VirtualMachine,
+};
+
+use crate::{
+    api::{
+        Api, EventDescriptor, FunctionDescriptor, Identifier, TypeDefinition, TypeDescriptor,
+        TypeKind, Value,
+    },
+    bindgen,
+    command::{CommandError, Event},
+    Command,
+};
+
+use super::limits::Supervisor;
+
+/// how often a script waiting for the engine checks whether it has been aborted
+const ABORT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Creates a new Python module containing one function for each function of the given api
+/// and one decorator for each of its events.
+pub(super) fn make_module(
+    vm: &VirtualMachine,
+    api: &Api,
+    sender: &Sender<Command>,
+    events: &Arc<Mutex<Receiver<Event>>>,
+    supervisor: &Supervisor,
+) -> PyRef<PyModule> {
+    let module_name = bindgen::python::native_module_name(api);
+    let dict = vm.ctx.new_dict();
+    dict.set_item("__name__", vm.new_pyobj(module_name.as_str()), vm)
+        .unwrap();
+
+    // the classes of named types and errors are easier to express in Python than by using the
+    // vm directly
+    let mut types_source = Vec::new();
+    bindgen::python::generate_prelude(&mut types_source, api).unwrap();
+    bindgen::python::generate_types(&mut types_source, api).unwrap();
+    bindgen::python::generate_errors(&mut types_source, api).unwrap();
+    let types_source = String::from_utf8(types_source).unwrap();
+    vm.run_code_string(
+        Scope::with_builtins(None, dict.clone(), vm),
+        &types_source,
+        format!("<{module_name} types>"),
+    )
+    .expect("the generated type definitions are valid Python");
+
+    let bindings = Bindings {
+        api: api.clone(),
+        namespace: dict.clone(),
+        sender: sender.clone(),
+        supervisor: supervisor.clone(),
+        events: Arc::new(Events {
+            receiver: Arc::clone(events),
+            handlers: Mutex::default(),
+            dispatching: AtomicBool::default(),
+        }),
+    };
+
+    for function in &api.functions {
+        // the vm requires function names to live forever; interned strings serve this purpose
+        let name = vm
+            .ctx
+            .intern_str(bindgen::python::identifier(&function.name))
+            .as_str();
+        let py_function = make_function(vm, name, function.clone(), bindings.clone());
+        dict.set_item(name, py_function, vm).unwrap();
+    }
+
+    for event in &api.events {
+        let name = vm
+            .ctx
+            .intern_str(bindgen::python::event_decorator(&event.name))
+            .as_str();
+        let decorator = make_decorator(vm, name, event, bindings.clone());
+        dict.set_item(name, decorator, vm).unwrap();
+    }
+    if !api.events.is_empty() {
+        let wait = make_wait_for_events(vm, bindings);
+        dict.set_item("wait_for_events", wait, vm).unwrap();
+    }
+
+    vm.new_module(&module_name, dict, None)
+}
+
+/// Creates the module scripts import to use the given api.
+///
+/// It runs the same shim `python_bindgen` writes to disk, which re-exports the native module
+/// created by [`make_module`].
+pub(super) fn make_shim(vm: &VirtualMachine, api: &Api) -> PyRef<PyModule> {
+    let module_name = bindgen::python::module_name(api);
+    let dict = vm.ctx.new_dict();
+    dict.set_item("__name__", vm.new_pyobj(module_name.as_str()), vm)
+        .unwrap();
+
+    let mut source = Vec::new();
+    bindgen::python::generate(&mut source, api).unwrap();
+    let source = String::from_utf8(source).unwrap();
+    vm.run_code_string(
+        Scope::with_builtins(None, dict.clone(), vm),
+        &source,
+        format!("<{module_name}>"),
+    )
+    .expect("the generated shim is valid Python");
+
+    vm.new_module(&module_name, dict, None)
+}
+
+/// State shared by all functions of the module
+#[derive(Clone)]
+struct Bindings {
+    api: Api,
+    /// namespace of the module containing the classes of all named types and errors
+    namespace: PyDictRef,
+    sender: Sender<Command>,
+    supervisor: Supervisor,
+    events: Arc<Events>,
+}
+
+/// Events sent by the engine and the handlers the script registered for them
+struct Events {
+    receiver: Arc<Mutex<Receiver<Event>>>,
+    handlers: Mutex<HashMap<Identifier, Vec<PyObjectRef>>>,
+    /// set while handlers are running, as those must not be interrupted by other handlers
+    dispatching: AtomicBool,
+}
+
+impl Events {
+    fn receiver(&self) -> MutexGuard<'_, Receiver<Event>> {
+        self.receiver.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn handlers(&self) -> MutexGuard<'_, HashMap<Identifier, Vec<PyObjectRef>>> {
+        self.handlers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Bindings {
+    /// Returns the definition of a named type along with its Python class.
+    fn named_type(
+        &self,
+        vm: &VirtualMachine,
+        name: &Identifier,
+    ) -> PyResult<(&TypeDefinition, PyObjectRef)> {
+        let definition = self
+            .api
+            .type_definition(name)
+            .ok_or_else(|| vm.new_type_error(format!("unknown type `{name}`")))?;
+        let class = self
+            .namespace
+            .get_item(bindgen::python::class_name(name).as_str(), vm)?;
+        Ok((definition, class))
+    }
+
+    /// Creates the exception for a command the engine refused to execute.
+    ///
+    /// Errors the function declared get their own exception class, all others are raised using
+    /// the common base class.
+    fn command_error(
+        &self,
+        vm: &VirtualMachine,
+        function: &FunctionDescriptor,
+        error: CommandError,
+    ) -> PyBaseExceptionRef {
+        let class_name = if function
+            .errors
+            .iter()
+            .any(|declared| declared.name == error.kind)
+        {
+            bindgen::python::error_class(&self.api.name, &error.kind)
+        } else {
+            bindgen::python::api_error_class(&self.api.name)
+        };
+
+        match self
+            .namespace
+            .get_item(class_name.as_str(), vm)
+            .and_then(|class| {
+                class.downcast::<PyType>().map_err(|_not_a_class| {
+                    vm.new_type_error(format!("`{class_name}` is not a class"))
+                })
+            }) {
+            Ok(class) => vm.new_exception_msg(class, error.message),
+            Err(exception) => exception,
+        }
+    }
+}
+
+fn make_function(
+    vm: &VirtualMachine,
+    name: &'static str,
+    function: FunctionDescriptor,
+    bindings: Bindings,
+) -> PyObjectRef {
+    vm.new_function(
+        name,
+        move |args: FuncArgs, vm: &VirtualMachine| -> PyResult {
+            let arguments = collect_arguments(vm, name, &function, args, &bindings)?;
+            let (command, reply) = Command::new(&bindings.api, &function, arguments)
+                .map_err(|error| vm.new_value_error(format!("{name}(): {error}")))?;
+
+            bindings.supervisor.count_command(vm)?;
+            bindings.sender.send(command).map_err(|_closed| {
+                vm.new_runtime_error("the engine has been shut down".to_owned())
+            })?;
+
+            // waiting might take a while, so the script must stay abortable meanwhile
+            let result = loop {
+                match reply.recv_timeout(ABORT_POLL_INTERVAL) {
+                    Ok(result) => break result,
+                    Err(RecvTimeoutError::Timeout) => bindings.supervisor.check(vm)?,
+                    Err(RecvTimeoutError::Disconnected) => {
+                        return Err(vm.new_runtime_error(
+                            "the engine did not reply to the command".to_owned(),
+                        ))
+                    }
+                }
+            };
+
+            let returned = match (result, function.returns.as_ref()) {
+                (Ok(Some(value)), Some(returns)) => {
+                    to_py_object(vm, value, &returns.typ, &bindings)
+                }
+                // the reply has already complained about an unexpected return value
+                (Ok(_), _) => Ok(vm.ctx.none()),
+                (Err(error), _) => Err(bindings.command_error(vm, &function, error)),
+            };
+
+            // the command might have raised some events, which should be handled right away
+            dispatch_events(vm, &bindings)?;
+            returned
+        },
+    )
+    .into()
+}
+
+/// Creates a decorator which registers the decorated function as handler for the given event.
+fn make_decorator(
+    vm: &VirtualMachine,
+    name: &'static str,
+    event: &EventDescriptor,
+    bindings: Bindings,
+) -> PyObjectRef {
+    let event_name = event.name.clone();
+    vm.new_function(
+        name,
+        move |handler: PyObjectRef, vm: &VirtualMachine| -> PyResult {
+            if !handler.is_callable() {
+                return Err(vm.new_type_error(format!(
+                    "{name}() expects a function to be called when the event occurs"
+                )));
+            }
+            bindings
+                .events
+                .handlers()
+                .entry(event_name.clone())
+                .or_default()
+                .push(handler.clone());
+            // the decorated function stays available to the script
+            Ok(handler)
+        },
+    )
+    .into()
+}
+
+/// Creates a function which blocks the script and handles events as they arrive.
+///
+/// It waits for the given number of seconds or until the engine shuts down if there is none.
+fn make_wait_for_events(vm: &VirtualMachine, bindings: Bindings) -> PyObjectRef {
+    vm.new_function(
+        "wait_for_events",
+        move |seconds: OptionalOption<ArgIntoFloat>, vm: &VirtualMachine| -> PyResult<()> {
+            if bindings.events.dispatching.load(Ordering::Relaxed) {
+                return Err(vm.new_runtime_error(
+                    "wait_for_events() must not be called by an event handler".to_owned(),
+                ));
+            }
+            let deadline = seconds
+                .flatten()
+                .map(f64::from)
+                .map(|seconds| {
+                    Duration::try_from_secs_f64(seconds)
+                        .map(|duration| Instant::now() + duration)
+                        .map_err(|_invalid| {
+                            vm.new_value_error(format!(
+                                "wait_for_events() expects a positive number of seconds, but got {seconds}"
+                            ))
+                        })
+                })
+                .transpose()?;
+
+            loop {
+                // waiting might take a while, so the script must stay abortable meanwhile
+                bindings.supervisor.check(vm)?;
+                let timeout = deadline.map_or(ABORT_POLL_INTERVAL, |deadline| {
+                    deadline
+                        .saturating_duration_since(Instant::now())
+                        .min(ABORT_POLL_INTERVAL)
+                });
+                let received = bindings.events.receiver().recv_timeout(timeout);
+                match received {
+                    Ok(event) => {
+                        bindings.events.dispatching.store(true, Ordering::Relaxed);
+                        let handled = dispatch_event(vm, event, &bindings);
+                        bindings.events.dispatching.store(false, Ordering::Relaxed);
+                        handled?;
+                        dispatch_events(vm, &bindings)?;
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    // no more events to come
+                    Err(RecvTimeoutError::Disconnected) => return Ok(()),
+                }
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return Ok(());
+                }
+            }
+        },
+    )
+    .into()
+}
+
+/// Passes all pending events to their handlers unless a handler is already running.
+fn dispatch_events(vm: &VirtualMachine, bindings: &Bindings) -> PyResult<()> {
+    if bindings.events.dispatching.swap(true, Ordering::Relaxed) {
+        return Ok(());
+    }
+    let mut handled = Ok(());
+    loop {
+        let received = bindings.events.receiver().try_recv();
+        let Ok(event) = received else {
+            break;
+        };
+        handled = dispatch_event(vm, event, bindings);
+        if handled.is_err() {
+            break;
+        }
+    }
+    bindings.events.dispatching.store(false, Ordering::Relaxed);
+    handled
+}
+
+/// Calls all handlers of the given event, passing its payload as arguments.
+///
+/// Events without handlers will be dropped.
+fn dispatch_event(vm: &VirtualMachine, event: Event, bindings: &Bindings) -> PyResult<()> {
+    // handlers may register further handlers, so the registry must not stay borrowed
+    let handlers = bindings
+        .events
+        .handlers()
+        .get(&event.name)
+        .cloned()
+        .unwrap_or_default();
+    let Some(descriptor) = bindings.api.event(&event.name.0) else {
+        return Ok(());
+    };
+    if handlers.is_empty() {
+        return Ok(());
+    }
+
+    let arguments = event
+        .payload
+        .into_iter()
+        .zip(&descriptor.payload)
+        .map(|(value, declaration)| to_py_object(vm, value, &declaration.typ, bindings))
+        .collect::<PyResult<Vec<_>>>()?;
+    for handler in handlers {
+        handler.call(arguments.clone(), vm)?;
+    }
+    Ok(())
+}
+
+/// Matches the positional and keyword arguments of a Python call to the function's parameters
+/// and converts them into [`Value`]s.
+fn collect_arguments(
+    vm: &VirtualMachine,
+    name: &str,
+    function: &FunctionDescriptor,
+    args: FuncArgs,
+    bindings: &Bindings,
+) -> PyResult<Vec<Value>> {
+    let FuncArgs {
+        args: positional,
+        mut kwargs,
+    } = args;
+
+    if positional.len() > function.parameters.len() {
+        return Err(vm.new_type_error(format!(
+            "{name}() takes {expected} positional argument(s) but {actual} were given",
+            expected = function.parameters.len(),
+            actual = positional.len()
+        )));
+    }
+
+    let mut positional = positional.into_iter();
+    let arguments = function
+        .parameters
+        .iter()
+        .map(|parameter| {
+            let parameter_name = bindgen::python::identifier(&parameter.name);
+            let object = match positional.next() {
+                Some(object) => {
+                    if kwargs.contains_key(&parameter_name) {
+                        return Err(vm.new_type_error(format!(
+                            "{name}() got multiple values for argument '{parameter_name}'"
+                        )));
+                    }
+                    object
+                }
+                None => match kwargs.swap_remove(&parameter_name) {
+                    Some(object) => object,
+                    None => {
+                        return parameter.default.clone().ok_or_else(|| {
+                            vm.new_type_error(format!(
+                                "{name}() missing required argument: '{parameter_name}'"
+                            ))
+                        })
+                    }
+                },
+            };
+            to_value(vm, &object, &parameter.typ, bindings)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+
+    if let Some(unexpected) = kwargs.keys().next() {
+        return Err(vm.new_type_error(format!(
+            "{name}() got an unexpected keyword argument '{unexpected}'"
+        )));
+    }
+
+    Ok(arguments)
+}
+
+/// Converts a Python object into a [`Value`] of the given type.
+///
+/// This only performs the conversion; range checks are left to [`Command::new`].
+fn to_value(
+    vm: &VirtualMachine,
+    object: &PyObjectRef,
+    typ: &TypeDescriptor,
+    bindings: &Bindings,
+) -> PyResult<Value> {
+    let value = match *typ {
+        TypeDescriptor::Integer(_) => {
+            if object.fast_isinstance(vm.ctx.types.bool_type) {
+                return Err(vm.new_type_error("expected an integer, but got a bool".to_owned()));
+            }
+            Value::Integer(object.clone().try_into_value(vm)?)
+        }
+        TypeDescriptor::Float => Value::Float(object.clone().try_into_value(vm)?),
+        TypeDescriptor::Boolean => Value::Boolean(object.clone().try_into_value(vm)?),
+        TypeDescriptor::String => Value::String(object.clone().try_into_value(vm)?),
+        TypeDescriptor::List(ref element_type) => {
+            let elements: Vec<PyObjectRef> = object.clone().try_into_value(vm)?;
+            Value::List(
+                elements
+                    .iter()
+                    .map(|element| to_value(vm, element, element_type, bindings))
+                    .collect::<PyResult<_>>()?,
+            )
+        }
+        TypeDescriptor::Named(ref name) => {
+            let (definition, class) = bindings.named_type(vm, name)?;
+            match definition.kind {
+                TypeKind::Record(ref fields) => Value::Record(
+                    fields
+                        .iter()
+                        .map(|field| {
+                            let field_name =
+                                vm.ctx.new_str(bindgen::python::identifier(&field.name));
+                            // records may also be passed as plain dictionaries
+                            let field_object = if object.payload_is::<PyDict>() {
+                                object.get_item(&*field_name, vm)?
+                            } else {
+                                object.get_attr(&field_name, vm)?
+                            };
+                            let value = to_value(vm, &field_object, &field.typ, bindings)?;
+                            Ok((field.name.clone(), value))
+                        })
+                        .collect::<PyResult<_>>()?,
+                ),
+                TypeKind::Enum(_) => {
+                    // enum members carry the technical name of their variant as value;
+                    // that name may also be passed as a plain string
+                    let variant = if object.is_instance(&class, vm)? {
+                        object.get_attr("value", vm)?
+                    } else {
+                        object.clone()
+                    };
+                    Value::Enum(Identifier(variant.try_into_value(vm)?))
+                }
+            }
+        }
+    };
+    Ok(value)
+}
+
+/// Converts a [`Value`] of the given type into the corresponding Python object.
+fn to_py_object(
+    vm: &VirtualMachine,
+    value: Value,
+    typ: &TypeDescriptor,
+    bindings: &Bindings,
+) -> PyResult {
+    let mismatch = |returned: &Value| {
+        vm.new_type_error(format!(
+            "the engine returned {returned}, which is not of type {typ}"
+        ))
+    };
+
+    let object = match value {
+        Value::Integer(integer) => vm.new_pyobj(integer),
+        Value::Float(float) => vm.new_pyobj(float),
+        Value::Boolean(boolean) => vm.new_pyobj(boolean),
+        Value::String(string) => vm.new_pyobj(string),
+        Value::List(elements) => {
+            let TypeDescriptor::List(ref element_type) = *typ else {
+                return Err(mismatch(&Value::List(elements)));
+            };
+            vm.ctx
+                .new_list(
+                    elements
+                        .into_iter()
+                        .map(|element| to_py_object(vm, element, element_type, bindings))
+                        .collect::<PyResult<_>>()?,
+                )
+                .into()
+        }
+        Value::Record(mut values) => {
+            let TypeDescriptor::Named(ref name) = *typ else {
+                return Err(mismatch(&Value::Record(values)));
+            };
+            let (definition, class) = bindings.named_type(vm, name)?;
+            let TypeKind::Record(ref fields) = definition.kind else {
+                return Err(vm.new_type_error(format!("`{name}` is not a record")));
+            };
+            let arguments = fields
+                .iter()
+                .map(|field| {
+                    let field_value = values.remove(&field.name).ok_or_else(|| {
+                        vm.new_value_error(format!("field `{}` is missing", field.name))
+                    })?;
+                    to_py_object(vm, field_value, &field.typ, bindings)
+                })
+                .collect::<PyResult<Vec<_>>>()?;
+            class.call(arguments, vm)?
+        }
+        Value::Enum(variant) => {
+            let TypeDescriptor::Named(ref name) = *typ else {
+                return Err(mismatch(&Value::Enum(variant)));
+            };
+            let (_definition, class) = bindings.named_type(vm, name)?;
+            class.call((variant.0,), vm)?
+        }
+    };
+    Ok(object)
+}
@@ -0,0 +1,583 @@
+//! Turns Python exceptions into reports a beginner can make sense of.
+//!
+//! A [`ScriptError`] tells where the script failed and what kind of mistake it was, explains
+//! the problem in plain words and suggests names of api functions the author might have meant.
+
+use std::fmt::{self, Display};
+
+use rustpython_vm::{
+    builtins::PyBaseExceptionRef, compiler::CompileError, AsObject, VirtualMachine,
+};
+use serde::Serialize;
+
+use crate::{
+    api::{Api, ErrorDescriptor},
+    bindgen,
+};
+
+use super::{limits::Abort, sandbox::Violation};
+
+/// What kind of mistake ended the script
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptErrorKind {
+    /// the script file couldn't be read
+    Io,
+    /// the script isn't valid Python
+    Syntax,
+    /// the script failed while running
+    Runtime,
+    /// the script called the api in a wrong way or the engine refused a command
+    ApiMisuse,
+    /// the script tried to use something the sandbox doesn't allow, e.g. a forbidden module
+    Sandbox,
+    /// the script didn't finish within its time limit
+    Timeout,
+    /// the script has been cancelled or exceeded its command budget
+    Aborted,
+}
+
+impl Display for ScriptErrorKind {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match *self {
+            ScriptErrorKind::Io => "io error",
+            ScriptErrorKind::Syntax => "syntax error",
+            ScriptErrorKind::Runtime => "runtime error",
+            ScriptErrorKind::ApiMisuse => "api misuse",
+            ScriptErrorKind::Sandbox => "sandbox violation",
+            ScriptErrorKind::Timeout => "timeout",
+            ScriptErrorKind::Aborted => "aborted",
+        })
+    }
+}
+
+/// Describes why a script failed
+#[derive(Clone, Debug, Serialize)]
+pub struct ScriptError {
+    /// what kind of mistake ended the script
+    pub kind: ScriptErrorKind,
+    /// path of the script
+    pub file: String,
+    /// line of the script where the error occurred, starting at 1
+    pub line: Option<usize>,
+    /// column within [`Self::line`] where the error occurred, starting at 1
+    pub column: Option<usize>,
+    /// the offending line of the script
+    pub source_line: Option<String>,
+    /// the original message, e.g. `NameError: name 'x' is not defined`
+    pub message: String,
+    /// what went wrong in plain words
+    pub explanation: String,
+    /// names the author might have meant instead of a misspelled one
+    pub suggestions: Vec<String>,
+    /// the full Python traceback, if any
+    pub traceback: Option<String>,
+}
+
+impl ScriptError {
+    /// Creates an error without any location within the script.
+    #[must_use]
+    pub fn new(kind: ScriptErrorKind, file: impl Into<String>, message: impl Into<String>) -> Self {
+        let message = message.into();
+        Self {
+            kind,
+            file: file.into(),
+            line: None,
+            column: None,
+            source_line: None,
+            explanation: default_explanation(kind, &message),
+            message,
+            suggestions: Vec::new(),
+            traceback: None,
+        }
+    }
+
+    /// Describes a script which couldn't be compiled.
+    pub(super) fn from_compile_error(
+        vm: &VirtualMachine,
+        error: &CompileError,
+        source: &str,
+    ) -> Self {
+        let exception = vm.new_syntax_error(error, Some(source));
+        let type_name = exception.class().name().to_string();
+        let line = error.location.map(|location| location.row.to_usize());
+
+        let mut script_error = Self::new(
+            ScriptErrorKind::Syntax,
+            &error.source_path,
+            format!("{type_name}: {}", error.error),
+        );
+        script_error.line = line;
+        script_error.column = error.location.map(|location| location.column.to_usize());
+        script_error.source_line = line.and_then(|line| source_line(source, line));
+        script_error.traceback = Some(format_exception(vm, &exception));
+        if let Some(explanation) = explain_exception(&type_name, ScriptErrorKind::Syntax) {
+            explanation.clone_into(&mut script_error.explanation);
+        }
+        script_error
+    }
+
+    /// Describes a part of the script the sandbox refused to run.
+    pub(super) fn from_violation(
+        vm: &VirtualMachine,
+        violation: &Violation,
+        file: &str,
+        source: &str,
+        api: &Api,
+    ) -> Self {
+        let mut script_error =
+            Self::from_exception(vm, &violation.exception, file, source, api, None);
+        if let Some(location) = violation.location {
+            let line = location.row.to_usize();
+            script_error.line = Some(line);
+            script_error.column = Some(location.column.to_usize());
+            script_error.source_line = source_line(source, line);
+        }
+        script_error
+    }
+
+    /// Describes an exception which ended the script.
+    ///
+    /// `abort` is the reason why the script has been aborted, if it has been.
+    pub(super) fn from_exception(
+        vm: &VirtualMachine,
+        exception: &PyBaseExceptionRef,
+        file: &str,
+        source: &str,
+        api: &Api,
+        abort: Option<Abort>,
+    ) -> Self {
+        let type_name = exception.class().name().to_string();
+        let text = exception
+            .as_object()
+            .str(vm)
+            .map(|text| text.as_str().to_owned())
+            .unwrap_or_default();
+        let message = if text.is_empty() {
+            type_name.clone()
+        } else {
+            format!("{type_name}: {text}")
+        };
+
+        let kind = classify(&type_name, &text, api, abort);
+        let mut script_error = Self::new(kind, file, message);
+
+        // the innermost frame of the script itself is the most helpful one for the author
+        if let Some(traceback) = exception.traceback().and_then(|traceback| {
+            traceback
+                .iter()
+                .filter(|entry| entry.frame.code.source_path.as_str() == file)
+                .last()
+        }) {
+            let line = traceback.lineno.to_usize();
+            script_error.line = Some(line);
+            script_error.column = traceback
+                .lasti
+                .checked_sub(1)
+                .and_then(|index| traceback.frame.code.locations.get(index as usize))
+                .map(|location| location.column.to_usize());
+            script_error.source_line = source_line(source, line);
+        }
+
+        if let Some(error) = declared_error(&type_name, api) {
+            script_error.explanation = error.description.to_string();
+        } else if let Some(unknown) = unknown_name(&type_name, &text, api) {
+            script_error.suggestions = suggestions(unknown, api);
+            script_error.explanation = explain_unknown_name(unknown, &script_error.suggestions);
+        } else if let Some(explanation) = explain_exception(&type_name, kind) {
+            explanation.clone_into(&mut script_error.explanation);
+        }
+        script_error.traceback = Some(format_exception(vm, exception));
+        script_error
+    }
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}", self.file)?;
+        if let Some(line) = self.line {
+            write!(formatter, ":{line}")?;
+            if let Some(column) = self.column {
+                write!(formatter, ":{column}")?;
+            }
+        }
+        writeln!(formatter, ": {}: {}", self.kind, self.message)?;
+        if let Some(ref source_line) = self.source_line {
+            writeln!(formatter, "    {source_line}")?;
+            if let Some(column) = self.column {
+                writeln!(formatter, "    {:>column$}", "^")?;
+            }
+        }
+        write!(formatter, "{}", self.explanation)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// Decides which kind of mistake an exception represents.
+fn classify(type_name: &str, text: &str, api: &Api, abort: Option<Abort>) -> ScriptErrorKind {
+    if type_name == "ScriptAborted" {
+        return match abort {
+            Some(Abort::Timeout(_)) => ScriptErrorKind::Timeout,
+            _ => ScriptErrorKind::Aborted,
+        };
+    }
+
+    if super::sandbox::is_violation(text) {
+        return ScriptErrorKind::Sandbox;
+    }
+
+    // the exception types of commands refused by the engine
+    if type_name == bindgen::python::api_error_class(&api.name)
+        || declared_error(type_name, api).is_some()
+    {
+        return ScriptErrorKind::ApiMisuse;
+    }
+
+    // api functions prefix their argument errors with the name of the function
+    let raised_by_api = api.functions.iter().any(|function| {
+        text.starts_with(&format!(
+            "{}()",
+            bindgen::python::identifier(&function.name)
+        ))
+    });
+    if raised_by_api && matches!(type_name, "TypeError" | "ValueError") {
+        return ScriptErrorKind::ApiMisuse;
+    }
+
+    // a misspelled api function is the most common mistake of all
+    if unknown_name(type_name, text, api)
+        .is_some_and(|unknown| !suggestions(unknown, api).is_empty())
+    {
+        return ScriptErrorKind::ApiMisuse;
+    }
+
+    ScriptErrorKind::Runtime
+}
+
+/// Looks up the declared error an exception class has been generated for.
+fn declared_error<'api>(type_name: &str, api: &'api Api) -> Option<&'api ErrorDescriptor> {
+    api.errors()
+        .into_iter()
+        .find(|error| bindgen::python::error_class(&api.name, &error.name) == type_name)
+}
+
+/// Extracts the name Python didn't know about from the text of the exception.
+///
+/// Attributes only count as names if they are missing from the module of the api, as other
+/// values (e.g. `None`) lacking an attribute are a different kind of mistake.
+fn unknown_name<'text>(type_name: &str, text: &'text str, api: &Api) -> Option<&'text str> {
+    let mut quoted = text.split('\'').skip(1).step_by(2);
+    match type_name {
+        // name 'x' is not defined
+        "NameError" | "UnboundLocalError" => quoted.next(),
+        // module 'robot_api' has no attribute 'x'
+        "AttributeError" => {
+            let module = quoted.next()?;
+            let is_api_module = module == bindgen::python::module_name(api)
+                || module == bindgen::python::native_module_name(api);
+            let attribute = quoted.next()?;
+            (text.starts_with("module '") && is_api_module).then_some(attribute)
+        }
+        // cannot import name 'x' from 'robot_api'
+        "ImportError" if text.starts_with("cannot import name") => quoted.next(),
+        _ => None,
+    }
+}
+
+/// Returns the names of all api functions which are similar to the given name,
+/// best matches first.
+fn suggestions(unknown: &str, api: &Api) -> Vec<String> {
+    // allow for about one typo every three characters
+    let max_distance = (unknown.chars().count() / 3).max(1);
+
+    let mut candidates: Vec<(usize, String)> = api
+        .functions
+        .iter()
+        .map(|function| bindgen::python::identifier(&function.name))
+        .filter(|candidate| candidate != unknown)
+        .map(|candidate| (edit_distance(unknown, &candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .collect();
+    candidates.sort();
+    candidates
+        .into_iter()
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// Counts the characters which need to be inserted, removed or replaced to turn one string into
+/// the other (Levenshtein distance). Letter case is ignored.
+fn edit_distance(first: &str, second: &str) -> usize {
+    let first: Vec<char> = first.to_lowercase().chars().collect();
+    let second: Vec<char> = second.to_lowercase().chars().collect();
+
+    let mut previous: Vec<usize> = (0..=second.len()).collect();
+    for (index, &first_char) in first.iter().enumerate() {
+        let mut current = vec![index + 1];
+        for (second_index, &second_char) in second.iter().enumerate() {
+            let replace = previous[second_index] + usize::from(first_char != second_char);
+            let insert = current[second_index] + 1;
+            let remove = previous[second_index + 1] + 1;
+            current.push(replace.min(insert).min(remove));
+        }
+        previous = current;
+    }
+    previous[second.len()]
+}
+
+fn explain_unknown_name(unknown: &str, suggestions: &[String]) -> String {
+    match *suggestions {
+        [] => format!(
+            "Python doesn't know anything called `{unknown}`. Check the spelling and make sure \
+            it has been defined or imported before this line."
+        ),
+        [ref suggestion] => {
+            format!("Python doesn't know anything called `{unknown}`. Did you mean `{suggestion}`?")
+        }
+        _ => format!(
+            "Python doesn't know anything called `{unknown}`. Did you mean one of {}?",
+            suggestions
+                .iter()
+                .map(|suggestion| format!("`{suggestion}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Explains common exceptions; returns `None` if the default explanation of the kind fits best.
+fn explain_exception(type_name: &str, kind: ScriptErrorKind) -> Option<&'static str> {
+    let explanation = match (kind, type_name) {
+        (ScriptErrorKind::ApiMisuse, "TypeError") => {
+            "An api function has been called with the wrong number or types of arguments."
+        }
+        (ScriptErrorKind::ApiMisuse, "ValueError") => {
+            "An api function has been called with a value it cannot handle."
+        }
+        (_, "IndentationError") => {
+            "The indentation of this line doesn't fit the lines around it. Blocks following a `:` \
+            need to be indented by the same amount."
+        }
+        (_, "AttributeError") => {
+            "The value before the `.` doesn't have what is being asked for after it. Check the \
+            spelling and whether the value is what you expected; it might be `None` or of another \
+            type."
+        }
+        (_, "ZeroDivisionError") => "A number has been divided by zero.",
+        (_, "IndexError") => "A list has been accessed at a position it doesn't have.",
+        (_, "KeyError") => "A dictionary has been asked for a key it doesn't contain.",
+        (_, "RecursionError") => {
+            "A function kept calling itself without ever stopping. Make sure there is a case \
+            in which it returns without calling itself again."
+        }
+        (_, "ImportError" | "ModuleNotFoundError") => {
+            "This module cannot be imported. Scripts may only use the robot api and a few \
+            modules of the standard library like `math`, `random` and `time`."
+        }
+        (_, "PermissionError") => "Scripts are not allowed to do this.",
+        _ => return None,
+    };
+    Some(explanation)
+}
+
+fn default_explanation(kind: ScriptErrorKind, message: &str) -> String {
+    match kind {
+        ScriptErrorKind::Io => "The script couldn't be read.".to_owned(),
+        ScriptErrorKind::Syntax => "Python couldn't understand this line. Look for missing \
+            colons, brackets or quotes, here or in the line above."
+            .to_owned(),
+        ScriptErrorKind::Runtime => "The script stopped because of an error.".to_owned(),
+        ScriptErrorKind::ApiMisuse => format!(
+            "The robot couldn't do what it has been told: {}",
+            message.split_once(": ").map_or(message, |(_, text)| text)
+        ),
+        ScriptErrorKind::Sandbox => "Scripts are not allowed to do this.".to_owned(),
+        ScriptErrorKind::Timeout => "The script took too long and has been stopped. Maybe a \
+            loop never ends?"
+            .to_owned(),
+        ScriptErrorKind::Aborted => {
+            "The script has been stopped before it could finish.".to_owned()
+        }
+    }
+}
+
+/// Returns the given line of the source without trailing whitespace.
+fn source_line(source: &str, line: usize) -> Option<String> {
+    source
+        .lines()
+        .nth(line.checked_sub(1)?)
+        .map(|text| text.trim_end().to_owned())
+        .filter(|text| !text.is_empty())
+}
+
+fn format_exception(vm: &VirtualMachine, exception: &PyBaseExceptionRef) -> String {
+    let mut output = String::new();
+    match vm.write_exception(&mut output, exception) {
+        Ok(()) => output,
+        Err(_) => "the exception couldn't be formatted".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn robot_api() -> Api {
+        Api::load("apis/robot.api.json").unwrap()
+    }
+
+    #[test]
+    fn measures_edit_distance() {
+        assert_eq!(edit_distance("turn_left", "turn_left"), 0);
+        assert_eq!(edit_distance("turn_lef", "turn_left"), 1);
+        assert_eq!(edit_distance("Turn_Left", "turn_left"), 0);
+        assert_eq!(edit_distance("trun_left", "turn_left"), 2);
+        assert_eq!(edit_distance("", "move"), 4);
+        assert_eq!(edit_distance("turn_left", "turn_right"), 4);
+    }
+
+    #[test]
+    fn suggests_similar_api_functions() {
+        let api = robot_api();
+        assert_eq!(suggestions("turn_lef", &api), ["turn_left"]);
+        assert_eq!(suggestions("turnleft", &api), ["turn_left"]);
+        assert_eq!(suggestions("move_foward", &api), ["move_forward"]);
+        assert!(suggestions("jump", &api).is_empty());
+        // the function itself is not a suggestion
+        assert!(suggestions("turn_left", &api).is_empty());
+    }
+
+    #[test]
+    fn explains_misspelled_api_functions() {
+        let api = robot_api();
+        let suggestions = suggestions("turn_lef", &api);
+        assert_eq!(
+            explain_unknown_name("turn_lef", &suggestions),
+            "Python doesn't know anything called `turn_lef`. Did you mean `turn_left`?"
+        );
+        assert_eq!(
+            explain_unknown_name("jump", &[]),
+            "Python doesn't know anything called `jump`. Check the spelling and make sure it has \
+            been defined or imported before this line."
+        );
+    }
+
+    #[test]
+    fn finds_unknown_names() {
+        let api = robot_api();
+        assert_eq!(
+            unknown_name("NameError", "name 'turn_lef' is not defined", &api),
+            Some("turn_lef")
+        );
+        assert_eq!(
+            unknown_name(
+                "AttributeError",
+                "module 'robot_api' has no attribute 'turn_lef'",
+                &api
+            ),
+            Some("turn_lef")
+        );
+        assert_eq!(
+            unknown_name(
+                "ImportError",
+                "cannot import name 'turn_lef' from 'robot_api'",
+                &api
+            ),
+            Some("turn_lef")
+        );
+    }
+
+    #[test]
+    fn ignores_attributes_of_other_values() {
+        let api = robot_api();
+        assert_eq!(
+            unknown_name(
+                "AttributeError",
+                "'NoneType' object has no attribute 'system'",
+                &api
+            ),
+            None
+        );
+        assert_eq!(
+            unknown_name(
+                "AttributeError",
+                "'list' object has no attribute 'add'",
+                &api
+            ),
+            None
+        );
+        assert_eq!(
+            unknown_name(
+                "AttributeError",
+                "module 'math' has no attribute 'flor'",
+                &api
+            ),
+            None
+        );
+        assert!(explain_exception("AttributeError", ScriptErrorKind::Runtime).is_some());
+    }
+
+    #[test]
+    fn classifies_exceptions() {
+        let api = robot_api();
+        let classify = |type_name, text| classify(type_name, text, &api, None);
+        assert_eq!(
+            classify("ScriptAborted", "the script has been cancelled"),
+            ScriptErrorKind::Aborted
+        );
+        assert_eq!(
+            classify("ImportError", "module 'os' is not available in the sandbox"),
+            ScriptErrorKind::Sandbox
+        );
+        assert_eq!(
+            classify("RobotError", "something went wrong"),
+            ScriptErrorKind::ApiMisuse
+        );
+        assert_eq!(
+            classify(
+                "TypeError",
+                "move_forward() expected at most 1 argument(s), got 2"
+            ),
+            ScriptErrorKind::ApiMisuse
+        );
+        assert_eq!(
+            classify("NameError", "name 'turn_lef' is not defined"),
+            ScriptErrorKind::ApiMisuse
+        );
+        assert_eq!(
+            classify("NameError", "name 'jump' is not defined"),
+            ScriptErrorKind::Runtime
+        );
+        assert_eq!(
+            classify(
+                "AttributeError",
+                "'list' object has no attribute 'turn_lef'"
+            ),
+            ScriptErrorKind::Runtime
+        );
+        assert_eq!(
+            classify(
+                "TypeError",
+                "unsupported operand type(s) for +: 'int' and 'str'"
+            ),
+            ScriptErrorKind::Runtime
+        );
+    }
+
+    #[test]
+    fn classifies_aborts_by_their_reason() {
+        let api = robot_api();
+        let timeout = Some(Abort::Timeout(Duration::from_secs(1)));
+        assert_eq!(
+            classify("ScriptAborted", "", &api, timeout),
+            ScriptErrorKind::Timeout
+        );
+        assert_eq!(
+            classify("ScriptAborted", "", &api, Some(Abort::CommandBudget(3))),
+            ScriptErrorKind::Aborted
+        );
+    }
+}
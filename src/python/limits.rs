@@ -0,0 +1,198 @@
+//! Restrictions which keep a misbehaving script from hanging the engine.
+//!
+//! A run can be aborted for three reasons: it exceeded its wall-clock time, it sent more commands
+//! than allowed, or someone cancelled it via a [`CancelHandle`].
+//! The abort is delivered as an exception which doesn't derive from `Exception`, so a plain
+//! `except Exception:` within the script won't swallow it.
+
+use std::{
+    fmt::{self, Display},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::debug;
+use rustpython_vm::{
+    builtins::PyBaseExceptionRef,
+    signal::{user_signal_channel, UserSignalReceiver, UserSignalSender},
+    VirtualMachine,
+};
+
+/// how often the watchdog checks whether the script needs to be aborted
+const WATCHDOG_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Limits applied to a single run of a script
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    /// wall-clock time the script may run; unlimited if `None`
+    pub timeout: Option<Duration>,
+    /// number of api calls the script may make; unlimited if `None`
+    pub max_commands: Option<usize>,
+}
+
+/// The reason why a script has been aborted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Abort {
+    /// the script has been cancelled from the outside
+    Cancelled,
+    /// the script didn't finish within the given time
+    Timeout(Duration),
+    /// the script tried to send more than the given number of commands
+    CommandBudget(usize),
+}
+
+impl Display for Abort {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Abort::Cancelled => write!(formatter, "the script has been cancelled"),
+            Abort::Timeout(timeout) => write!(
+                formatter,
+                "the script didn't finish within its time limit of {timeout:?}"
+            ),
+            Abort::CommandBudget(max_commands) => write!(
+                formatter,
+                "the script exceeded its budget of {max_commands} commands"
+            ),
+        }
+    }
+}
+
+/// Aborts a running script from another thread and tells why a script has been aborted.
+#[derive(Clone, Debug, Default)]
+pub struct CancelHandle {
+    reason: Arc<Mutex<Option<Abort>>>,
+}
+
+impl CancelHandle {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests the script to be aborted as soon as possible.
+    pub fn cancel(&self) {
+        self.abort(Abort::Cancelled);
+    }
+
+    /// Returns the reason why the script has been aborted or `None` if it is allowed to continue.
+    #[must_use]
+    pub fn reason(&self) -> Option<Abort> {
+        *self.reason.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Records the reason for aborting the script unless there already is one.
+    pub(crate) fn abort(&self, reason: Abort) {
+        self.reason
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_or_insert(reason);
+    }
+}
+
+/// Enforces the limits of a single run; shared by the runner and all api functions.
+#[derive(Clone, Debug)]
+pub(super) struct Supervisor {
+    limits: Limits,
+    cancel: CancelHandle,
+    command_count: Arc<AtomicUsize>,
+    finished: Arc<AtomicBool>,
+}
+
+impl Supervisor {
+    pub(super) fn new(limits: Limits, cancel: CancelHandle) -> Self {
+        Self {
+            limits,
+            cancel,
+            command_count: Arc::default(),
+            finished: Arc::default(),
+        }
+    }
+
+    /// Raises the abort exception if the script shall not continue.
+    pub(super) fn check(&self, vm: &VirtualMachine) -> Result<(), PyBaseExceptionRef> {
+        match self.cancel.reason() {
+            Some(reason) => Err(abort_exception(vm, reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Accounts for another command to be sent and fails if this exceeds the budget.
+    pub(super) fn count_command(&self, vm: &VirtualMachine) -> Result<(), PyBaseExceptionRef> {
+        self.check(vm)?;
+        let count = self.command_count.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(max_commands) = self.limits.max_commands {
+            if count > max_commands {
+                self.cancel.abort(Abort::CommandBudget(max_commands));
+                self.check(vm)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts a thread which interrupts the interpreter once the script shall be aborted.
+    ///
+    /// The returned receiver needs to be installed into the interpreter.
+    /// The thread keeps running until [`Self::finish`] is being called.
+    pub(super) fn start_watchdog(&self) -> UserSignalReceiver {
+        let (signal_sender, signal_receiver) = user_signal_channel();
+        let deadline = self
+            .limits
+            .timeout
+            .map(|timeout| (Instant::now() + timeout, timeout));
+        let supervisor = self.clone();
+        thread::spawn(move || supervisor.watch(deadline, &signal_sender));
+        signal_receiver
+    }
+
+    /// Tells the watchdog that the script has ended and must not be interrupted anymore.
+    pub(super) fn finish(&self) {
+        self.finished.store(true, Ordering::Relaxed);
+    }
+
+    fn watch(&self, deadline: Option<(Instant, Duration)>, signal_sender: &UserSignalSender) {
+        while !self.finished.load(Ordering::Relaxed) {
+            if let Some((deadline, timeout)) = deadline {
+                if Instant::now() >= deadline {
+                    self.cancel.abort(Abort::Timeout(timeout));
+                }
+            }
+
+            if let Some(reason) = self.cancel.reason() {
+                self.interrupt(signal_sender, reason);
+                break;
+            }
+
+            thread::sleep(WATCHDOG_INTERVAL);
+        }
+    }
+
+    /// Makes the interpreter raise the abort exception before executing the next instruction.
+    fn interrupt(&self, signal_sender: &UserSignalSender, reason: Abort) {
+        let supervisor = self.clone();
+        let sender = signal_sender.clone();
+        let signal = Box::new(move |vm: &VirtualMachine| {
+            // keep interrupting, as the script might catch the exception and carry on
+            if !supervisor.finished.load(Ordering::Relaxed) {
+                supervisor.interrupt(&sender, reason);
+            }
+            Err(abort_exception(vm, reason))
+        });
+        if signal_sender.send(signal).is_err() {
+            debug!("the interpreter has already been shut down");
+        }
+    }
+}
+
+/// Creates the exception which aborts the script.
+fn abort_exception(vm: &VirtualMachine, reason: Abort) -> PyBaseExceptionRef {
+    // deriving from `BaseException` bypasses `except Exception:` clauses
+    let base = vm.ctx.exceptions.base_exception_type.to_owned();
+    let typ = vm
+        .ctx
+        .new_exception_type("builtins", "ScriptAborted", Some(vec![base]));
+    vm.new_exception_msg(typ, reason.to_string())
+}
@@ -0,0 +1,68 @@
+//! Publishes the output of a script to a [`Monitor`] instead of printing it.
+//!
+//! Whoever runs the script decides what to do with the output, e.g. print it to the console or
+//! add it to a report.
+
+use rustpython_vm::{PyResult, VirtualMachine};
+
+use crate::monitor::{Activity, Monitor, OutputStream};
+
+/// Replaces the streams, so that each complete line gets published via `publish(stream, line)`.
+///
+/// Everything but writing is left to the original streams.
+const WRAPPER_SOURCE: &str = r#"
+import sys
+
+
+class _Output:
+    def __init__(self, stream, target):
+        self._stream = stream
+        self._target = target
+        self._line = ""
+
+    def write(self, text):
+        *lines, self._line = (self._line + text).split("\n")
+        for line in lines:
+            publish(self._stream, line)
+        return len(text)
+
+    def flush(self):
+        if self._line:
+            publish(self._stream, self._line)
+            self._line = ""
+
+    def __getattr__(self, name):
+        return getattr(self._target, name)
+
+
+sys.stdout = _Output("stdout", sys.stdout)
+sys.stderr = _Output("stderr", sys.stderr)
+"#;
+
+/// Replaces `sys.stdout` and `sys.stderr` by streams which publish each line written to them.
+pub(super) fn install(vm: &VirtualMachine, monitor: &Monitor) -> PyResult<()> {
+    let monitor = monitor.clone();
+    let publish = vm.new_function("publish", move |stream: String, text: String| {
+        let stream = if stream == "stderr" {
+            OutputStream::Stderr
+        } else {
+            OutputStream::Stdout
+        };
+        monitor.publish(&Activity::Output { stream, text });
+    });
+
+    // the wrappers live in a scope of their own, out of reach of the script
+    let scope = vm.new_scope_with_builtins();
+    scope.globals.set_item("publish", publish.into(), vm)?;
+    vm.run_code_string(scope, WRAPPER_SOURCE, "<output>".to_owned())?;
+    Ok(())
+}
+
+/// Publishes the last line of each stream, even if it hasn't been terminated.
+pub(super) fn flush(vm: &VirtualMachine) -> PyResult<()> {
+    for name in ["stdout", "stderr"] {
+        let stream = vm.sys_module.get_attr(name, vm)?;
+        vm.call_method(&stream, "flush", ())?;
+    }
+    Ok(())
+}
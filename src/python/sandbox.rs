@@ -0,0 +1,360 @@
+//! Keeps student scripts away from the host.
+//!
+//! A restricted script may only import a whitelist of modules and has no access to files,
+//! the network or other processes. This is enforced in several layers:
+//! - before running, the compiled script is checked for forbidden imports and for names which
+//!   are commonly used to escape from a Python sandbox (e.g. `__globals__` or `__subclasses__`)
+//! - `__import__` only grants whitelisted modules to the script; modules imported on behalf of
+//!   a whitelisted module may not load anything which touches the host (e.g. `os`)
+//! - builtins like `open` or `eval` will be refused when being called by the script
+//!
+//! This is meant to prevent accidents and keep curious students in check. It is no replacement
+//! for running untrusted code within an isolated environment.
+
+use rustpython_vm::{
+    builtins::{PyBaseExceptionRef, PyCode, PyDictRef, PyStr},
+    bytecode::{BorrowedConstant, CodeObject, Constant, Instruction, OpArgState},
+    function::FuncArgs,
+    source_code::SourceLocation,
+    AsObject, PyObjectRef, PyResult, VirtualMachine,
+};
+
+/// modules a restricted script may import besides the engine's api
+const STUDENT_MODULES: &[&str] = &["math", "random", "time"];
+
+/// modules which must not be loaded, not even on behalf of a whitelisted module
+const BLOCKED_MODULES: &[&str] = &[
+    "_ctypes",
+    "_imp",
+    "_io",
+    "_posixsubprocess",
+    "_signal",
+    "_socket",
+    "ctypes",
+    "fcntl",
+    "gc",
+    "glob",
+    "importlib",
+    "inspect",
+    "io",
+    "marshal",
+    "mmap",
+    "nt",
+    "ntpath",
+    "os",
+    "pathlib",
+    "posix",
+    "posixpath",
+    "select",
+    "selectors",
+    "shutil",
+    "signal",
+    "socket",
+    "subprocess",
+    "tempfile",
+    "zipimport",
+];
+
+/// builtins a restricted script must not call
+const FORBIDDEN_BUILTINS: &[&str] = &[
+    "breakpoint",
+    "compile",
+    "eval",
+    "exec",
+    "exit",
+    "globals",
+    "help",
+    "input",
+    "locals",
+    "open",
+    "quit",
+    "vars",
+];
+
+/// builtins which access attributes by name and need to be checked for [`FORBIDDEN_NAMES`]
+const ATTRIBUTE_BUILTINS: &[&str] = &["delattr", "getattr", "hasattr", "setattr"];
+
+/// attributes which give access to the internals of the interpreter
+const FORBIDDEN_NAMES: &[&str] = &[
+    "__base__",
+    "__bases__",
+    "__builtins__",
+    "__class__",
+    "__closure__",
+    "__code__",
+    "__dict__",
+    "__func__",
+    "__getattribute__",
+    "__globals__",
+    "__import__",
+    "__loader__",
+    "__mro__",
+    "__reduce__",
+    "__reduce_ex__",
+    "__self__",
+    "__spec__",
+    "__subclasses__",
+    "__traceback__",
+    "ag_frame",
+    "cr_frame",
+    "f_back",
+    "f_builtins",
+    "f_code",
+    "f_globals",
+    "f_locals",
+    "gi_code",
+    "gi_frame",
+    "tb_frame",
+    "tb_next",
+];
+
+/// Restricts what a script may access
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Sandbox {
+    /// the script may use the whole standard library; only use this for trusted scripts
+    Unrestricted,
+    /// the script may only import the given modules and the engine's api
+    Restricted { allowed_modules: Vec<String> },
+}
+
+impl Sandbox {
+    /// The profile for student scripts: `math`, `random`, `time` and the engine's api
+    #[must_use]
+    pub fn student() -> Self {
+        Self::Restricted {
+            allowed_modules: STUDENT_MODULES
+                .iter()
+                .map(|&module| module.to_owned())
+                .collect(),
+        }
+    }
+}
+
+/// A part of a script the sandbox doesn't allow, found before running the script
+pub(super) struct Violation {
+    /// the exception the script would have raised
+    pub(super) exception: PyBaseExceptionRef,
+    /// location of the offending instruction within the script, if known
+    pub(super) location: Option<SourceLocation>,
+}
+
+/// Rejects a script which imports modules not being allowed or uses forbidden names.
+pub(super) fn check_code(
+    vm: &VirtualMachine,
+    code: &PyCode,
+    allowed_modules: &[String],
+) -> Result<(), Violation> {
+    check_code_object(vm, &code.code, allowed_modules)
+}
+
+/// Returns `true` if the given text of an exception tells that the sandbox refused something.
+pub(super) fn is_violation(text: &str) -> bool {
+    // all exceptions raised by the sandbox end like this
+    text.ends_with(" available in the sandbox")
+}
+
+fn check_code_object<C: Constant>(
+    vm: &VirtualMachine,
+    code: &CodeObject<C>,
+    allowed_modules: &[String],
+) -> Result<(), Violation> {
+    let mut arg_state = OpArgState::default();
+    for (index, &unit) in code.instructions.iter().enumerate() {
+        let violation = |exception| Violation {
+            exception,
+            location: code.locations.get(index).copied(),
+        };
+        match arg_state.get(unit) {
+            (Instruction::ImportName { idx }, arg) => {
+                let module = code.names[idx.get(arg) as usize].as_ref();
+                if !is_allowed(module, allowed_modules) {
+                    return Err(violation(import_error(vm, module)));
+                }
+            }
+            (Instruction::ImportNameless, _) => {
+                return Err(violation(vm.new_import_error(
+                    "relative imports are not available in the sandbox".to_owned(),
+                    vm.ctx.new_str(""),
+                )));
+            }
+            (
+                Instruction::LoadAttr { idx }
+                | Instruction::LoadMethod { idx }
+                | Instruction::StoreAttr { idx }
+                | Instruction::DeleteAttr { idx }
+                | Instruction::LoadNameAny(idx)
+                | Instruction::LoadGlobal(idx),
+                arg,
+            ) => {
+                let name = code.names[idx.get(arg) as usize].as_ref();
+                if FORBIDDEN_NAMES.contains(&name) {
+                    return Err(violation(forbidden_name_error(vm, name)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // names used in other ways, e.g. imported or assigned, can't be located as easily
+    if let Some(name) = code
+        .names
+        .iter()
+        .map(AsRef::as_ref)
+        .find(|name| FORBIDDEN_NAMES.contains(name))
+    {
+        return Err(Violation {
+            exception: forbidden_name_error(vm, name),
+            location: None,
+        });
+    }
+
+    // functions and classes are compiled into nested code objects
+    for constant in &*code.constants {
+        if let BorrowedConstant::Code { code: nested } = constant.borrow_constant() {
+            check_code_object(vm, nested, allowed_modules)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Restricts the builtins of the interpreter for the script running with the given globals.
+pub(super) fn install(
+    vm: &VirtualMachine,
+    script_globals: &PyDictRef,
+    allowed_modules: &[String],
+) -> PyResult<()> {
+    // cached modules will be handed out without asking `__import__`
+    let modules = vm.sys_module.get_attr("modules", vm)?;
+    for &module in BLOCKED_MODULES {
+        // most of them haven't been loaded anyway
+        let _not_loaded = modules.del_item(module, vm);
+    }
+
+    let builtins = vm.builtins.as_object();
+
+    let original_import = builtins.get_attr("__import__", vm)?;
+    let import = guard_import(
+        vm,
+        original_import,
+        script_globals.clone(),
+        allowed_modules.to_vec(),
+    );
+    builtins.set_attr("__import__", import, vm)?;
+
+    for &name in FORBIDDEN_BUILTINS {
+        if let Ok(original) = builtins.get_attr(name, vm) {
+            let guarded = guard_builtin(vm, name, original, script_globals.clone(), None);
+            builtins.set_attr(name, guarded, vm)?;
+        }
+    }
+
+    for &name in ATTRIBUTE_BUILTINS {
+        let original = builtins.get_attr(name, vm)?;
+        let guarded = guard_builtin(vm, name, original, script_globals.clone(), Some(1));
+        builtins.set_attr(name, guarded, vm)?;
+    }
+
+    Ok(())
+}
+
+/// Wraps `__import__`, so that the script only gets the allowed modules.
+fn guard_import(
+    vm: &VirtualMachine,
+    original: PyObjectRef,
+    script_globals: PyDictRef,
+    allowed_modules: Vec<String>,
+) -> PyObjectRef {
+    vm.new_function("__import__", move |args: FuncArgs, vm: &VirtualMachine| {
+        let module = args
+            .args
+            .first()
+            .and_then(|name| name.payload::<PyStr>())
+            .map(|name| name.as_str().to_owned())
+            .unwrap_or_default();
+        let globals = args
+            .args
+            .get(1)
+            .or_else(|| args.kwargs.get("globals"))
+            .filter(|globals| !vm.is_none(globals));
+        let level = args.args.get(4).or_else(|| args.kwargs.get("level"));
+        let relative = level.is_some_and(|level| !level.is(&vm.ctx.new_int(0)));
+
+        // a direct call of `__import__` without globals can only come from the script
+        let by_script = globals.is_none_or(|globals| globals.is(&script_globals));
+        let allowed = if by_script {
+            !relative && is_allowed(&module, &allowed_modules)
+        } else {
+            !is_blocked(&module)
+        };
+        if !allowed {
+            return Err(import_error(vm, &module));
+        }
+
+        original.call(args, vm)
+    })
+    .into()
+}
+
+/// Wraps a builtin function, so that the script may not call it at all or, if `name_index` is
+/// given, may not pass a forbidden name at this position.
+fn guard_builtin(
+    vm: &VirtualMachine,
+    name: &'static str,
+    original: PyObjectRef,
+    script_globals: PyDictRef,
+    name_index: Option<usize>,
+) -> PyObjectRef {
+    vm.new_function(name, move |args: FuncArgs, vm: &VirtualMachine| {
+        let by_script = vm
+            .current_frame()
+            .is_some_and(|frame| frame.globals.is(&script_globals));
+        if by_script {
+            match name_index {
+                None => {
+                    return Err(vm.new_exception_msg(
+                        vm.ctx.exceptions.permission_error.to_owned(),
+                        format!("`{name}` is not available in the sandbox"),
+                    ));
+                }
+                Some(index) => {
+                    if let Some(forbidden) = args
+                        .args
+                        .get(index)
+                        .and_then(|attribute| attribute.payload::<PyStr>())
+                        .map(PyStr::as_str)
+                        .filter(|attribute| FORBIDDEN_NAMES.contains(attribute))
+                    {
+                        return Err(forbidden_name_error(vm, forbidden));
+                    }
+                }
+            }
+        }
+        original.call(args, vm)
+    })
+    .into()
+}
+
+fn is_allowed(module: &str, allowed_modules: &[String]) -> bool {
+    let top_level = module.split('.').next().unwrap_or_default();
+    allowed_modules.iter().any(|allowed| allowed == top_level)
+}
+
+fn is_blocked(module: &str) -> bool {
+    let top_level = module.split('.').next().unwrap_or_default();
+    BLOCKED_MODULES.contains(&top_level)
+}
+
+fn import_error(vm: &VirtualMachine, module: &str) -> PyBaseExceptionRef {
+    vm.new_import_error(
+        format!("module '{module}' is not available in the sandbox"),
+        vm.ctx.new_str(module),
+    )
+}
+
+fn forbidden_name_error(vm: &VirtualMachine, name: &str) -> PyBaseExceptionRef {
+    vm.new_exception_msg(
+        vm.ctx.exceptions.permission_error.to_owned(),
+        format!("access to `{name}` is not available in the sandbox"),
+    )
+}
@@ -5,12 +5,21 @@ use projection::Projection;
 use robot::Robot;
 use std::time::Instant;
 
+use crate::{
+    api::Identifier,
+    command::EventSender,
+    level::Level,
+    monitor::{Activity, Monitor},
+    world::{Action, World},
+    Command,
+};
+
 mod camera;
 mod floor;
 mod projection;
 mod robot;
-pub use robot::Command;
 
+/// position of the camera relative to the center of the world
 const CAMERA_POS: Vec3 = Vec3::new(-2.0, -3.0, 2.0);
 
 pub(crate) struct Scene {
@@ -18,8 +27,14 @@ pub(crate) struct Scene {
     start_time: Instant,
     projection: Projection,
     camera: Camera,
+    /// the point the camera is looking at
+    center: Vec3,
+    /// the logical state being rendered
+    world: World,
     robot: Robot,
     floor: Floor,
+    /// the function whose command is being animated
+    animated: Option<Identifier>,
 }
 
 fn elapsed_as_vec(start_time: Instant) -> [u32; 2] {
@@ -38,9 +53,11 @@ impl Scene {
         _adapter: &wgpu::Adapter,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
+        level: &Level,
     ) -> Self {
-        let cube = Robot::new(device, queue, surface.view_formats[0]);
-        let floor = Floor::new(device, queue, surface.view_formats[0]);
+        let world = World::new(level);
+        let cube = Robot::new(device, queue, surface.view_formats[0], &world);
+        let floor = Floor::new(device, queue, surface.view_formats[0], &world);
 
         let projection = Projection::new_perspective(
             (surface.width, surface.height),
@@ -48,7 +65,8 @@ impl Scene {
             1.0..15.0,
         );
 
-        let camera = Camera::new(CAMERA_POS, Vec3::ZERO);
+        let center = (world.size().as_vec2() / 2.0).extend(0.0);
+        let camera = Camera::new(center + CAMERA_POS, center);
 
         let start_time = Instant::now();
 
@@ -59,8 +77,11 @@ impl Scene {
             start_time,
             projection,
             camera,
+            center,
+            world,
             robot: cube,
             floor,
+            animated: None,
         }
     }
 
@@ -85,7 +106,7 @@ impl Scene {
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
         let (dy, dx) = (self.start_time.elapsed().as_secs_f32() * 0.1).sin_cos();
-        self.camera.position = CAMERA_POS + Vec3::new(dx * 0.3, -dy * 0.3, 0.0);
+        self.camera.position = self.center + CAMERA_POS + Vec3::new(dx * 0.3, -dy * 0.3, 0.0);
 
         self.render_cube(texture_view, &mut encoder, queue);
 
@@ -176,6 +197,8 @@ impl Scene {
             occlusion_query_set: None,
         };
         {
+            self.floor.update_tiles(queue, &self.world);
+
             let mut render_pass = encoder.begin_render_pass(&render_pass_descriptor);
 
             self.floor.render(
@@ -192,8 +215,60 @@ impl Scene {
         self.robot.is_idle()
     }
 
-    pub(crate) fn process_command(&mut self, command: &Command) {
-        self.robot.process_command(command, &mut self.floor);
+    /// Tells the monitor once the animation of the last command has been completed.
+    pub(crate) fn report_animation(&mut self, monitor: &Monitor) {
+        if self.robot.is_idle() {
+            if let Some(function) = self.animated.take() {
+                monitor.publish(&Activity::AnimationCompleted { function });
+            }
+        }
+    }
+
+    pub(crate) fn process_command(
+        &mut self,
+        command: Command,
+        events: &EventSender,
+        monitor: &Monitor,
+    ) {
+        // queries neither change the world nor need to be animated
+        if let Some(answer) = self.world.query(&command) {
+            let answer = answer.map(Some);
+            monitor.publish(&Activity::command(&command, &answer));
+            command.reply.send(answer);
+            return;
+        }
+
+        // the framework only passes on commands once the previous animation has been completed
+        // and reported, so that it doesn't interfere with the next one
+        debug_assert!(
+            self.robot.is_idle(),
+            "the robot is still animating the previous command"
+        );
+
+        let outcome = self.world.process_command(&command);
+        // the events will be handled by the script as soon as the reply arrives
+        for event in self.world.events(&outcome) {
+            events.emit(event);
+        }
+
+        match outcome {
+            Ok(actions) => {
+                monitor.publish(&Activity::command(&command, &Ok(None)));
+                if actions
+                    .iter()
+                    .any(|action| matches!(*action, Action::Moved { .. }))
+                {
+                    self.floor.tainted = true;
+                }
+                self.animated = Some(command.name.clone());
+                self.robot.animate(actions, command.reply);
+            }
+            Err(error) => {
+                let result = Err(error);
+                monitor.publish(&Activity::command(&command, &result));
+                command.reply.send(result);
+            }
+        }
     }
 }
 
@@ -1,22 +1,21 @@
-use std::{
-    mem::size_of,
-    ops::{self, Neg},
-};
+use std::mem::size_of;
 
 use bytemuck::{offset_of, Pod, Zeroable};
 use std::{borrow::Cow, time::Instant};
 use wgpu::{util::DeviceExt, PipelineCompilationOptions, Queue, RenderPass, TextureFormat};
 
-use super::{
-    camera::Camera, elapsed_as_vec, projection::Projection, robot::Orientation, DepthTexture,
-};
+use crate::world::{LinePattern, TileFlags, World};
+
+use super::{camera::Camera, elapsed_as_vec, projection::Projection, DepthTexture};
 
 pub(super) struct Floor {
     pipeline: wgpu::RenderPipeline,
     time_buf: wgpu::Buffer,
     bind_group: wgpu::BindGroup,
     matrix_buf: wgpu::Buffer,
-    pub(super) tiles: Vec<Tile>,
+    /// one instance per tile of the world
+    tiles: Vec<Tile>,
+    /// the tiles of the world have changed and need to be uploaded again
     pub(super) tainted: bool,
     tile_buf: wgpu::Buffer,
 }
@@ -25,8 +24,13 @@ impl Floor {
     // `time` will be moved to global scope anyway
     #[allow(clippy::similar_names)]
     #[must_use]
-    pub(super) fn new(device: &wgpu::Device, _queue: &Queue, view_format: TextureFormat) -> Self {
-        let tiles = Self::create_vertices();
+    pub(super) fn new(
+        device: &wgpu::Device,
+        _queue: &Queue,
+        view_format: TextureFormat,
+        world: &World,
+    ) -> Self {
+        let tiles = Self::create_vertices(world);
 
         let tile_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Tile Buffer"),
@@ -91,6 +95,11 @@ impl Floor {
                     offset: offset_of!(Tile, line_pattern) as u64,
                     shader_location: 2,
                 },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Uint32,
+                    offset: offset_of!(Tile, flags) as u64,
+                    shader_location: 3,
+                },
             ],
         }];
 
@@ -117,6 +126,19 @@ impl Floor {
         u32::try_from(self.tiles.len()).unwrap()
     }
 
+    /// Copies the current state of the world's tiles if they have been changed.
+    pub(super) fn update_tiles(&mut self, queue: &Queue, world: &World) {
+        if !self.tainted {
+            return;
+        }
+        for (tile, state) in self.tiles.iter_mut().zip(world.tiles()) {
+            tile.line_pattern = state.line_pattern;
+            tile.flags = state.flags;
+        }
+        queue.write_buffer(&self.tile_buf, 0, bytemuck::cast_slice(&self.tiles));
+        self.tainted = false;
+    }
+
     pub(super) fn render<'pipeline>(
         &'pipeline mut self,
         queue: &Queue,
@@ -125,11 +147,6 @@ impl Floor {
         projection: &Projection,
         start_time: Instant,
     ) {
-        if self.tainted {
-            queue.write_buffer(&self.tile_buf, 0, bytemuck::cast_slice(&self.tiles));
-            self.tainted = false;
-        }
-
         self.update_time(start_time, queue);
         self.update_matrix(projection, camera, queue);
 
@@ -193,17 +210,17 @@ impl Floor {
         })
     }
 
-    fn create_vertices() -> Vec<Tile> {
+    fn create_vertices(world: &World) -> Vec<Tile> {
+        let size = world.size();
         let mut vertex_data = Vec::new();
-        for y in -5_i16..5 {
-            let bottom = f32::from(y);
-            for x in -5_i16..5 {
-                let left = f32::from(x);
-                let line_pattern = 0; //thread_rng.gen();
-                vertex_data.push(tile([left, bottom, 0.0], LinePattern(line_pattern)));
+        for y in 0..size.y {
+            let bottom = y as f32;
+            for x in 0..size.x {
+                let left = x as f32;
+                let state = world.tiles()[vertex_data.len()];
+                vertex_data.push(tile([left, bottom, 0.0], state.line_pattern, state.flags));
             }
         }
-
         vertex_data
     }
 
@@ -238,102 +255,16 @@ impl Floor {
 
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable, Default)]
-pub(super) struct Tile {
+struct Tile {
     pos: [f32; 4],
-    pub(super) line_pattern: LinePattern,
+    line_pattern: LinePattern,
+    flags: TileFlags,
 }
 
-fn tile(pos: [f32; 3], line_pattern: LinePattern) -> Tile {
+fn tile(pos: [f32; 3], line_pattern: LinePattern, flags: TileFlags) -> Tile {
     Tile {
         pos: [pos[0], pos[1], pos[2], 1.0],
         line_pattern,
-    }
-}
-
-#[repr(transparent)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable, Default)]
-pub(super) struct LinePattern(u32);
-
-impl ops::BitOrAssign<LineSegment> for LinePattern {
-    fn bitor_assign(&mut self, rhs: LineSegment) {
-        self.0 |= 1 << rhs as u32;
-    }
-}
-
-#[derive(Clone, Copy)]
-// their meaning is clear from the context
-#[allow(clippy::min_ident_chars)]
-pub(super) enum LineSegment {
-    /// positive x
-    E = 0,
-    /// +x, +y
-    NE = 1,
-    /// positive y
-    N = 2,
-    /// -x +y
-    NW = 3,
-    /// negative x
-    W = 4,
-    /// -x -y
-    SW = 5,
-    /// negative y
-    S = 6,
-    /// +x -y
-    SE = 7,
-    /// +x, +y
-    NECorner = 9,
-    /// -x +y
-    NWCorner = 11,
-    /// -x -y
-    SWCorner = 13,
-    /// +x -y
-    SECorner = 15,
-}
-
-impl From<Orientation> for LineSegment {
-    fn from(value: Orientation) -> Self {
-        match value {
-            Orientation::E => Self::E,
-            Orientation::NE => Self::NE,
-            Orientation::N => Self::N,
-            Orientation::NW => Self::NW,
-            Orientation::W => Self::W,
-            Orientation::SW => Self::SW,
-            Orientation::S => Self::S,
-            Orientation::SE => Self::SE,
-        }
-    }
-}
-
-impl LineSegment {
-    pub(crate) fn get_x_corner(self) -> Option<LineSegment> {
-        match self {
-            Self::NE => Some(Self::NWCorner),
-            Self::NW => Some(Self::NECorner),
-            Self::SW => Some(Self::SECorner),
-            Self::SE => Some(Self::SWCorner),
-            _ => None,
-        }
-    }
-}
-
-impl Neg for LineSegment {
-    type Output = Self;
-
-    fn neg(self) -> Self::Output {
-        match self {
-            Self::E => Self::W,
-            Self::NE => Self::SW,
-            Self::N => Self::S,
-            Self::NW => Self::SE,
-            Self::W => Self::E,
-            Self::SW => Self::NE,
-            Self::S => Self::N,
-            Self::SE => Self::NW,
-            Self::NECorner => Self::SWCorner,
-            Self::NWCorner => Self::SECorner,
-            Self::SWCorner => Self::NECorner,
-            Self::SECorner => Self::NWCorner,
-        }
+        flags,
     }
 }
@@ -1,26 +1,22 @@
 use std::{
+    collections::VecDeque,
     f32::consts::{PI, TAU},
     mem::size_of,
-    ops::{AddAssign, SubAssign},
     time::Duration,
 };
 
 use bytemuck::{offset_of, Pod, Zeroable};
 use glam::{FloatExt, IVec3, Mat4, Quat, Vec2, Vec3, Vec4};
-use log::error;
 use std::{borrow::Cow, time::Instant};
 use wgpu::{self, util::DeviceExt};
 
-use crate::api::Identifier;
-
-use super::{
-    camera::Camera,
-    elapsed_as_vec,
-    floor::{Floor, LineSegment},
-    projection::Projection,
-    DepthTexture,
+use crate::{
+    command::Reply,
+    world::{Action, World},
 };
 
+use super::{camera::Camera, elapsed_as_vec, projection::Projection, DepthTexture};
+
 pub(super) struct Robot {
     pipeline: wgpu::RenderPipeline,
     pipeline_wire: Option<wgpu::RenderPipeline>,
@@ -32,9 +28,11 @@ pub(super) struct Robot {
     matrix_buf: wgpu::Buffer,
     animation_position: Vec3,
     animation_angle: f32,
-    position: IVec3,
-    orientation: Orientation,
     current_animation: Option<Animation>,
+    /// actions of the current command which will be animated after the current animation
+    pending_actions: VecDeque<Action>,
+    /// acknowledges the command of the current animation once it has been completed
+    pending_reply: Option<Reply>,
 }
 
 impl Robot {
@@ -45,6 +43,7 @@ impl Robot {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         view_format: wgpu::TextureFormat,
+        world: &World,
     ) -> Self {
         let (vertex_data, index_data) = Self::create_vertices();
 
@@ -182,9 +181,6 @@ impl Robot {
                 )
             });
 
-        let orientation = Orientation::default();
-        let position = IVec3::new(0, 0, 0);
-
         Self {
             vertex_buf,
             index_buf,
@@ -194,11 +190,11 @@ impl Robot {
             matrix_buf,
             pipeline,
             pipeline_wire: wireframe_pipeline,
-            animation_position: position.as_vec3() + Vec3::new(0.5, 0.5, 0.0),
+            animation_position: tile_center(world.position()),
             current_animation: None,
-            animation_angle: orientation.angle(),
-            orientation,
-            position,
+            pending_actions: VecDeque::new(),
+            pending_reply: None,
+            animation_angle: world.orientation().angle(),
         }
     }
 
@@ -211,8 +207,12 @@ impl Robot {
         start_time: Instant,
     ) {
         if let Some(animation) = self.current_animation.as_ref() {
-            if animation.animate(&mut self.animation_position, &mut self.animation_angle) {
-                self.current_animation.take();
+            if animation.animate(&mut self.animation_position, &mut self.animation_angle)
+                && !self.start_next_animation()
+            {
+                if let Some(reply) = self.pending_reply.take() {
+                    reply.send(Ok(None));
+                }
             }
         };
 
@@ -476,76 +476,69 @@ impl Robot {
         self.current_animation.is_none()
     }
 
-    pub(super) fn process_command(&mut self, command: &Command, floor: &mut Floor) {
+    /// Finishes all animations of the current command immediately and acknowledges it.
+    pub(super) fn complete_animation(&mut self) {
         if let Some(current_animation) = self.current_animation.take() {
             current_animation.complete(&mut self.animation_position, &mut self.animation_angle);
         }
+        for action in self.pending_actions.drain(..) {
+            match action {
+                Action::Moved { to, .. } => self.animation_position = tile_center(to),
+                Action::Turned { to, .. } => self.animation_angle = to.angle(),
+            }
+        }
+        if let Some(reply) = self.pending_reply.take() {
+            reply.send(Ok(None));
+        }
+    }
 
-        self.current_animation = match command.name.0.as_str() {
-            "MoveForward" => {
-                let segment = LineSegment::from(self.orientation);
-
-                // TODO make this a safe function
-                #[allow(clippy::cast_sign_loss)]
-                let start_index = (self.position.y * 10 + self.position.x + 55) as usize;
-                floor.tiles[start_index].line_pattern |= segment;
-
-                let offset = self.orientation.as_ivec3();
-                if offset.x != 0 && offset.y != 0 {
-                    // TODO make this a safe function
-                    #[allow(clippy::cast_sign_loss)]
-                    let index0 =
-                        (self.position.y * 10 + (self.position.x + offset.x) + 55) as usize;
-                    floor.tiles[index0].line_pattern |= segment.get_x_corner().unwrap();
-
-                    // TODO make this a safe function
-                    #[allow(clippy::cast_sign_loss)]
-                    let index1 =
-                        ((self.position.y + offset.y) * 10 + self.position.x + 55) as usize;
-                    floor.tiles[index1].line_pattern |= -segment.get_x_corner().unwrap();
-                }
-
-                self.position += offset;
+    /// Starts animating the actions of a command, which have already been applied to the world.
+    ///
+    /// The command will be acknowledged once all animations have been completed.
+    pub(super) fn animate(&mut self, actions: Vec<Action>, reply: Reply) {
+        self.complete_animation();
+
+        self.pending_actions = actions.into();
+        self.pending_reply = Some(reply);
+        if !self.start_next_animation() {
+            // nothing to animate
+            self.complete_animation();
+        }
+    }
 
-                // TODO make this a safe function
-                #[allow(clippy::cast_sign_loss)]
-                let end_index = (self.position.y * 10 + self.position.x + 55) as usize;
-                floor.tiles[end_index].line_pattern |= -segment;
-                floor.tainted = true;
+    /// Starts animating the next pending action; returns `false` if there is none left.
+    fn start_next_animation(&mut self) -> bool {
+        let Some(action) = self.pending_actions.pop_front() else {
+            self.current_animation = None;
+            return false;
+        };
 
-                Some(Animation::Move {
-                    start: self.animation_position,
-                    end: self.position.as_vec3() + Vec3::new(0.5, 0.5, 0.0),
-                    start_time: Instant::now(),
-                    duration: Duration::from_millis(1_000),
-                })
-            }
-            "TurnLeft" => {
-                self.orientation += 1;
-                Some(Animation::Rotate {
-                    start: self.animation_angle,
-                    end: self.orientation.angle(),
-                    start_time: Instant::now(),
-                    duration: Duration::from_millis(1_000),
-                })
-            }
-            "TurnRight" => {
-                self.orientation -= 1;
-                Some(Animation::Rotate {
-                    start: self.animation_angle,
-                    end: self.orientation.angle(),
-                    start_time: Instant::now(),
-                    duration: Duration::from_millis(1_000),
-                })
-            }
-            other => {
-                error!("Unknown Command: {other}");
-                None
-            }
+        let animation = match action {
+            Action::Moved { to, .. } => Animation::Move {
+                start: self.animation_position,
+                end: tile_center(to),
+                start_time: Instant::now(),
+                duration: Duration::from_millis(1_000),
+            },
+            Action::Turned { to, .. } => Animation::Rotate {
+                start: self.animation_angle,
+                end: to.angle(),
+                start_time: Instant::now(),
+                duration: Duration::from_millis(1_000),
+            },
         };
+
+        // the caller will be blocked until the animation has finished
+        self.current_animation = Some(animation);
+        true
     }
 }
 
+/// Returns the center of a tile on the floor's surface
+fn tile_center(position: IVec3) -> Vec3 {
+    position.as_vec3() + Vec3::new(0.5, 0.5, 0.0)
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable, Default)]
 struct Vertex {
@@ -562,11 +555,6 @@ fn vertex(position: Vec3, texture_coord: Vec2) -> Vertex {
     }
 }
 
-#[derive(Debug)]
-pub struct Command {
-    pub name: Identifier,
-}
-
 enum Animation {
     Move {
         start: Vec3,
@@ -627,75 +615,3 @@ impl Animation {
         }
     }
 }
-
-// TODO W.I.P.
-// their meaning is clear from the context
-#[allow(clippy::min_ident_chars)]
-#[derive(Clone, Copy, Default)]
-#[repr(u8)]
-pub(super) enum Orientation {
-    /// positive x
-    #[default]
-    E = 0,
-    /// +x, +y
-    NE = 1,
-    /// positive y
-    N = 2,
-    /// -x +y
-    NW = 3,
-    /// negative x
-    W = 4,
-    /// -x -y
-    SW = 5,
-    /// negative y
-    S = 6,
-    /// +x -y
-    SE = 7,
-}
-
-impl Orientation {
-    fn as_ivec3(self) -> IVec3 {
-        match self {
-            Orientation::E => IVec3::new(1, 0, 0),
-            Orientation::NE => IVec3::new(1, 1, 0),
-            Orientation::N => IVec3::new(0, 1, 0),
-            Orientation::NW => IVec3::new(-1, 1, 0),
-            Orientation::W => IVec3::new(-1, 0, 0),
-            Orientation::SW => IVec3::new(-1, -1, 0),
-            Orientation::S => IVec3::new(0, -1, 0),
-            Orientation::SE => IVec3::new(1, -1, 0),
-        }
-    }
-
-    fn angle(self) -> f32 {
-        f32::from(self as u8) / 8.0 * TAU
-    }
-}
-
-impl From<u8> for Orientation {
-    fn from(value: u8) -> Self {
-        match value & 0x07 {
-            0 => Self::E,
-            1 => Self::NE,
-            2 => Self::N,
-            3 => Self::NW,
-            4 => Self::W,
-            5 => Self::SW,
-            6 => Self::S,
-            7 => Self::SE,
-            _ => unreachable!(),
-        }
-    }
-}
-
-impl AddAssign<u8> for Orientation {
-    fn add_assign(&mut self, rhs: u8) {
-        *self = (*self as u8).wrapping_add(rhs).into();
-    }
-}
-
-impl SubAssign<u8> for Orientation {
-    fn sub_assign(&mut self, rhs: u8) {
-        *self = (*self as u8).wrapping_sub(rhs).into();
-    }
-}
@@ -0,0 +1,642 @@
+//! The logical state of the world, independent of any rendering.
+//!
+//! The [`World`] keeps track of the robot's pose and the state of every tile. It processes
+//! [`Command`]s and reports which [`Action`] has been taken, so that renderers can animate the
+//! transition, while headless users (tests, grading, …) can just inspect the resulting state.
+
+use glam::{IVec3, UVec2};
+use log::error;
+
+use std::collections::BTreeMap;
+
+use crate::{
+    api::{Identifier, Value},
+    command::{CommandError, Event},
+    level::{Level, TilePosition},
+    Command,
+};
+
+mod orientation;
+mod tile;
+
+pub use orientation::Orientation;
+pub use tile::{LinePattern, LineSegment, Tile, TileFlags};
+
+#[derive(Clone, Debug)]
+pub struct World {
+    /// number of tiles along the x- and y-axis
+    size: UVec2,
+    /// row-major list of all tiles, starting at the origin
+    tiles: Vec<Tile>,
+    /// tile the robot is standing on
+    position: IVec3,
+    /// direction the robot is facing
+    orientation: Orientation,
+}
+
+/// A single step of a command, which may be repeated
+type Step = fn(&mut World) -> Result<Action, CommandError>;
+
+/// The change a successfully processed [`Command`] has applied to the world.
+#[expect(
+    variant_size_differences,
+    reason = "actions are short-lived values; boxing the larger variant wouldn't pay off"
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// the robot moved from one tile to an adjacent one
+    Moved { from: IVec3, to: IVec3 },
+    /// the robot turned on the spot
+    Turned { from: Orientation, to: Orientation },
+}
+
+impl World {
+    /// Creates the initial state of the given level.
+    #[must_use]
+    pub fn new(level: &Level) -> Self {
+        let size = level.size();
+        let mut tiles = vec![Tile::default(); size.x as usize * size.y as usize];
+
+        let index = |[x, y]: TilePosition| {
+            // positions have been checked when loading the level
+            usize::try_from(y).unwrap() * size.x as usize + usize::try_from(x).unwrap()
+        };
+
+        for &position in &level.blocked {
+            tiles[index(position)].flags |= TileFlags::BLOCKED;
+        }
+        for &position in &level.goals {
+            tiles[index(position)].flags |= TileFlags::GOAL;
+        }
+        for wall in &level.walls {
+            let [first, second] = wall.between;
+            let direction = IVec3::new(second[0] - first[0], second[1] - first[1], 0);
+            if let (Some(forward), Some(backward)) =
+                (TileFlags::wall(direction), TileFlags::wall(-direction))
+            {
+                tiles[index(first)].flags |= forward;
+                tiles[index(second)].flags |= backward;
+            }
+        }
+
+        Self {
+            size,
+            tiles,
+            position: level.start.position(),
+            orientation: level.start.orientation,
+        }
+    }
+
+    /// number of tiles along the x- and y-axis
+    #[must_use]
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// row-major list of all tiles, starting at the origin
+    #[must_use]
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    /// Returns the tile at the given position or `None` if it lies outside of the world.
+    #[must_use]
+    pub fn tile(&self, position: IVec3) -> Option<&Tile> {
+        self.tile_index(position).map(|index| &self.tiles[index])
+    }
+
+    /// tile the robot is standing on
+    #[must_use]
+    pub fn position(&self) -> IVec3 {
+        self.position
+    }
+
+    /// direction the robot is facing
+    #[must_use]
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Returns the index into [`Self::tiles`] for the given position
+    /// or `None` if the position lies outside of the world.
+    #[must_use]
+    pub fn tile_index(&self, position: IVec3) -> Option<usize> {
+        let width = self.size.x as usize;
+        let x = usize::try_from(position.x).ok().filter(|&x| x < width)?;
+        let y = usize::try_from(position.y)
+            .ok()
+            .filter(|&y| y < self.size.y as usize)?;
+        Some(y * width + x)
+    }
+
+    /// Returns `true` if the tile at the given position cannot be entered.
+    #[must_use]
+    pub fn is_blocked(&self, position: IVec3) -> bool {
+        let Some(tile) = self.tile(position) else {
+            return true;
+        };
+        tile.flags.contains(TileFlags::BLOCKED)
+    }
+
+    /// Returns `true` if there's a wall on the edge of the tile which faces the given direction.
+    /// The direction is expected to point to an adjacent tile.
+    #[must_use]
+    pub fn has_wall(&self, position: IVec3, direction: IVec3) -> bool {
+        let Some(wall) = TileFlags::wall(direction) else {
+            return false;
+        };
+        self.tile(position)
+            .is_some_and(|tile| tile.flags.contains(wall))
+    }
+
+    /// Applies the given command to the world and returns the actions taken in order.
+    ///
+    /// The world is left unchanged if the command cannot be executed, even if some of its
+    /// steps could have been.
+    /// Replying to the command is left to the caller.
+    pub fn process_command(&mut self, command: &Command) -> Result<Vec<Action>, CommandError> {
+        // each function names the parameter which tells how often to repeat its step
+        let (step, parameter): (Step, _) = match command.name.0.as_str() {
+            "move forward" => (Self::move_forward, "steps"),
+            "turn left" => (|world| Ok(world.turn_left()), "times"),
+            "turn right" => (|world| Ok(world.turn_right()), "times"),
+            other => {
+                error!("Unknown Command: {other}");
+                return Err(CommandError::new(
+                    "unknown function",
+                    format!("the robot doesn't know how to `{other}`"),
+                ));
+            }
+        };
+
+        // an api without the parameter only allows single steps
+        let repetitions = match command.argument(parameter) {
+            Some(&Value::Integer(repetitions)) => repetitions,
+            None => 1,
+            Some(other) => {
+                return Err(CommandError::new(
+                    "invalid argument",
+                    format!("expected a number of repetitions, but got {other}"),
+                ))
+            }
+        };
+
+        let mut world = self.clone();
+        let actions = (0..repetitions)
+            .map(|_| step(&mut world))
+            .collect::<Result<_, _>>()?;
+        *self = world;
+        Ok(actions)
+    }
+
+    /// Answers a read-only command from the current state without changing the world.
+    ///
+    /// Returns `None` if the command isn't read-only; it needs to be processed by
+    /// [`Self::process_command`] instead.
+    /// Replying to the command is left to the caller.
+    #[must_use]
+    pub fn query(&self, command: &Command) -> Option<Result<Value, CommandError>> {
+        let answer = match command.name.0.as_str() {
+            "position" => Ok(position_value(self.position)),
+            "orientation" => Ok(orientation_value(self.orientation)),
+            "tile ahead" => Ok(self.tile_ahead()),
+            "line pattern" => self.line_pattern(command.argument("position")),
+            _ => return None,
+        };
+        Some(answer)
+    }
+
+    /// Returns the events raised by the outcome of a command, which has just been processed.
+    #[must_use]
+    pub fn events(&self, outcome: &Result<Vec<Action>, CommandError>) -> Vec<Event> {
+        match *outcome {
+            Ok(ref actions) => actions
+                .iter()
+                .filter_map(|action| match *action {
+                    Action::Moved { to, .. } => Some(to),
+                    Action::Turned { .. } => None,
+                })
+                .filter(|&to| {
+                    self.tile(to)
+                        .is_some_and(|tile| tile.flags.contains(TileFlags::GOAL))
+                })
+                .map(|to| Event::new("reached goal", vec![position_value(to)]))
+                .collect(),
+            Err(ref error) if matches!(error.kind.0.as_str(), "blocked" | "out of bounds") => {
+                vec![Event::new(
+                    "bumped into wall",
+                    vec![position_value(self.position)],
+                )]
+            }
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Returns `true` if a wall prevents the robot from moving forward.
+    fn wall_ahead(&self) -> bool {
+        let offset = self.orientation.as_ivec3();
+        let target = self.position + offset;
+        // diagonal moves squeeze through the corner and are stopped by any wall touching it
+        if LineSegment::from(self.orientation).get_x_corner().is_some() {
+            let x_offset = IVec3::new(offset.x, 0, 0);
+            let y_offset = IVec3::new(0, offset.y, 0);
+            self.has_wall(self.position, x_offset)
+                || self.has_wall(self.position, y_offset)
+                || self.has_wall(target, -x_offset)
+                || self.has_wall(target, -y_offset)
+        } else {
+            self.has_wall(self.position, offset)
+        }
+    }
+
+    /// Describes the tile in front of the robot as a value of the api's `tile` type.
+    fn tile_ahead(&self) -> Value {
+        let target = self.position + self.orientation.as_ivec3();
+        let goal = self
+            .tile(target)
+            .is_some_and(|tile| tile.flags.contains(TileFlags::GOAL));
+        Value::Record(BTreeMap::from([
+            (Identifier("position".to_owned()), position_value(target)),
+            (
+                Identifier("blocked".to_owned()),
+                Value::Boolean(self.is_blocked(target)),
+            ),
+            (
+                Identifier("wall".to_owned()),
+                Value::Boolean(self.wall_ahead()),
+            ),
+            (Identifier("goal".to_owned()), Value::Boolean(goal)),
+        ]))
+    }
+
+    /// Lists the directions in which lines leave the center of the tile at the given position.
+    fn line_pattern(&self, position: Option<&Value>) -> Result<Value, CommandError> {
+        let Some(position) = position.and_then(position_from_value) else {
+            return Err(CommandError::new(
+                "invalid argument",
+                format!("expected a position, but got {position:?}"),
+            ));
+        };
+        let Some(tile) = self.tile(position) else {
+            return Err(CommandError::new(
+                "out of bounds",
+                format!(
+                    "there is no tile at {x}/{y} as this is outside the world",
+                    x = position.x,
+                    y = position.y
+                ),
+            ));
+        };
+        Ok(Value::List(
+            (0..8)
+                .map(Orientation::from)
+                .filter(|&orientation| tile.line_pattern.contains(LineSegment::from(orientation)))
+                .map(orientation_value)
+                .collect(),
+        ))
+    }
+
+    fn turn_left(&mut self) -> Action {
+        let from = self.orientation;
+        self.orientation += 1;
+        Action::Turned {
+            from,
+            to: self.orientation,
+        }
+    }
+
+    fn turn_right(&mut self) -> Action {
+        let from = self.orientation;
+        self.orientation -= 1;
+        Action::Turned {
+            from,
+            to: self.orientation,
+        }
+    }
+
+    fn move_forward(&mut self) -> Result<Action, CommandError> {
+        let segment = LineSegment::from(self.orientation);
+        let offset = self.orientation.as_ivec3();
+        let target = self.position + offset;
+
+        let (Some(start_index), Some(end_index)) =
+            (self.tile_index(self.position), self.tile_index(target))
+        else {
+            return Err(CommandError::new(
+                "out of bounds",
+                format!(
+                    "the robot cannot move to {x}/{y} as this is outside the world",
+                    x = target.x,
+                    y = target.y
+                ),
+            ));
+        };
+
+        if self.is_blocked(target) {
+            return Err(CommandError::new(
+                "blocked",
+                format!(
+                    "the robot cannot move to {x}/{y} as this tile is blocked",
+                    x = target.x,
+                    y = target.y
+                ),
+            ));
+        }
+
+        if self.wall_ahead() {
+            return Err(CommandError::new(
+                "blocked",
+                "the robot cannot move forward as there's a wall in the way",
+            ));
+        }
+
+        self.tiles[start_index].line_pattern |= segment;
+
+        if let Some(corner) = segment.get_x_corner() {
+            // both tiles are within the bounds as the start and the end tile are
+            if let Some(index) = self.tile_index(self.position + IVec3::new(offset.x, 0, 0)) {
+                self.tiles[index].line_pattern |= corner;
+            }
+            if let Some(index) = self.tile_index(self.position + IVec3::new(0, offset.y, 0)) {
+                self.tiles[index].line_pattern |= -corner;
+            }
+        }
+
+        self.tiles[end_index].line_pattern |= -segment;
+
+        let from = self.position;
+        self.position = target;
+
+        Ok(Action::Moved { from, to: target })
+    }
+}
+
+/// Converts a position into a value of the api's `position` type.
+fn position_value(position: IVec3) -> Value {
+    Value::Record(BTreeMap::from([
+        (
+            Identifier("x".to_owned()),
+            Value::Integer(position.x.into()),
+        ),
+        (
+            Identifier("y".to_owned()),
+            Value::Integer(position.y.into()),
+        ),
+    ]))
+}
+
+/// Reads a value of the api's `position` type.
+fn position_from_value(value: &Value) -> Option<IVec3> {
+    let Value::Record(ref fields) = *value else {
+        return None;
+    };
+    let coordinate = |name: &str| match fields.get(&Identifier(name.to_owned())) {
+        Some(&Value::Integer(coordinate)) => i32::try_from(coordinate).ok(),
+        _ => None,
+    };
+    Some(IVec3::new(coordinate("x")?, coordinate("y")?, 0))
+}
+
+/// Converts an orientation into a variant of the api's `orientation` type.
+fn orientation_value(orientation: Orientation) -> Value {
+    let name = match orientation {
+        Orientation::E => "east",
+        Orientation::NE => "north east",
+        Orientation::N => "north",
+        Orientation::NW => "north west",
+        Orientation::W => "west",
+        Orientation::SW => "south west",
+        Orientation::S => "south",
+        Orientation::SE => "south east",
+    };
+    Value::Enum(Identifier(name.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::{Api, ParameterDescriptor, TypeDescriptor};
+
+    use super::*;
+
+    fn level(source: &str) -> Level {
+        json5::from_str(source).unwrap()
+    }
+
+    fn robot_api() -> Api {
+        Api::load("apis/robot.api.json").unwrap()
+    }
+
+    fn command(api: &Api, name: &str, arguments: Vec<Value>) -> Command {
+        let function = api.function(name).unwrap();
+        Command::new(api, function, arguments).unwrap().0
+    }
+
+    /// Processes the command with the given number of repetitions.
+    fn process(
+        world: &mut World,
+        name: &str,
+        repetitions: i64,
+    ) -> Result<Vec<Action>, CommandError> {
+        world.process_command(&command(
+            &robot_api(),
+            name,
+            vec![Value::Integer(repetitions)],
+        ))
+    }
+
+    fn error_kind(outcome: Result<Vec<Action>, CommandError>) -> String {
+        outcome.unwrap_err().kind.0
+    }
+
+    #[test]
+    fn starts_at_level_start() {
+        let world = World::new(&level(
+            "{ size: [4, 3], start: { position: [1, 2], orientation: \"N\" }, blocked: [[0, 0]] }",
+        ));
+        assert_eq!(world.size(), UVec2::new(4, 3));
+        assert_eq!(world.tiles().len(), 12);
+        assert_eq!(world.position(), IVec3::new(1, 2, 0));
+        assert_eq!(world.orientation(), Orientation::N);
+        assert!(world.is_blocked(IVec3::new(0, 0, 0)));
+        assert!(!world.is_blocked(IVec3::new(1, 0, 0)));
+        assert!(world.is_blocked(IVec3::new(4, 0, 0)));
+    }
+
+    #[test]
+    fn moves_forward() {
+        let mut world = World::new(&level("{ size: [4, 3], start: { position: [0, 1] } }"));
+        let actions = process(&mut world, "move forward", 2).unwrap();
+        assert_eq!(
+            actions,
+            [
+                Action::Moved {
+                    from: IVec3::new(0, 1, 0),
+                    to: IVec3::new(1, 1, 0)
+                },
+                Action::Moved {
+                    from: IVec3::new(1, 1, 0),
+                    to: IVec3::new(2, 1, 0)
+                },
+            ]
+        );
+        assert_eq!(world.position(), IVec3::new(2, 1, 0));
+
+        let east = LineSegment::from(Orientation::E);
+        let west = LineSegment::from(Orientation::W);
+        let pattern = |x| world.tile(IVec3::new(x, 1, 0)).unwrap().line_pattern;
+        assert!(pattern(0).contains(east) && !pattern(0).contains(west));
+        assert!(pattern(1).contains(east) && pattern(1).contains(west));
+        assert!(!pattern(2).contains(east) && pattern(2).contains(west));
+    }
+
+    #[test]
+    fn turns() {
+        let mut world = World::new(&level("{ size: [4, 3], start: { position: [0, 1] } }"));
+        let actions = process(&mut world, "turn left", 2).unwrap();
+        assert_eq!(actions.len(), 2);
+        assert_eq!(world.orientation(), Orientation::N);
+        process(&mut world, "turn right", 3).unwrap();
+        assert_eq!(world.orientation(), Orientation::SE);
+        assert_eq!(world.position(), IVec3::new(0, 1, 0));
+    }
+
+    #[test]
+    fn bumps_into_walls() {
+        let mut world = World::new(&level(
+            "{ size: [4, 3], start: { position: [0, 1] }, walls: [{ between: [[1, 1], [2, 1]] }] }",
+        ));
+        process(&mut world, "move forward", 1).unwrap();
+        let outcome = process(&mut world, "move forward", 1);
+        assert_eq!(
+            world.events(&outcome)[0].name,
+            Identifier("bumped into wall".to_owned())
+        );
+        assert_eq!(error_kind(outcome), "blocked");
+        assert_eq!(world.position(), IVec3::new(1, 1, 0));
+    }
+
+    #[test]
+    fn diagonal_moves_are_stopped_by_walls_touching_the_corner() {
+        let mut world = World::new(&level(
+            "{ size: [4, 3], start: { position: [0, 0] }, walls: [{ between: [[1, 0], [1, 1]] }] }",
+        ));
+        process(&mut world, "turn left", 1).unwrap();
+        assert_eq!(
+            error_kind(process(&mut world, "move forward", 1)),
+            "blocked"
+        );
+        assert_eq!(world.position(), IVec3::new(0, 0, 0));
+    }
+
+    #[test]
+    fn cannot_enter_blocked_tiles() {
+        let mut world = World::new(&level(
+            "{ size: [4, 3], start: { position: [0, 1] }, blocked: [[1, 1]] }",
+        ));
+        assert_eq!(
+            error_kind(process(&mut world, "move forward", 1)),
+            "blocked"
+        );
+        assert_eq!(world.position(), IVec3::new(0, 1, 0));
+    }
+
+    #[test]
+    fn stays_within_bounds() {
+        let mut world = World::new(&level("{ size: [4, 3], start: { position: [3, 1] } }"));
+        let outcome = process(&mut world, "move forward", 1);
+        assert_eq!(world.events(&outcome).len(), 1);
+        assert_eq!(error_kind(outcome), "out of bounds");
+        assert_eq!(world.position(), IVec3::new(3, 1, 0));
+    }
+
+    #[test]
+    fn multi_step_moves_are_all_or_nothing() {
+        let mut world = World::new(&level(
+            "{ size: [4, 3], start: { position: [0, 1] }, blocked: [[3, 1]] }",
+        ));
+        let initial_tiles = world.tiles().to_vec();
+
+        // the first two steps would succeed
+        assert_eq!(
+            error_kind(process(&mut world, "move forward", 3)),
+            "blocked"
+        );
+        assert_eq!(world.position(), IVec3::new(0, 1, 0));
+        assert_eq!(world.tiles(), initial_tiles);
+
+        process(&mut world, "move forward", 2).unwrap();
+        assert_eq!(world.position(), IVec3::new(2, 1, 0));
+    }
+
+    #[test]
+    fn reaches_goals() {
+        let mut world = World::new(&level(
+            "{ size: [4, 3], start: { position: [0, 1] }, goals: [[2, 1]] }",
+        ));
+        let outcome = process(&mut world, "move forward", 2);
+        let events = world.events(&outcome);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name, Identifier("reached goal".to_owned()));
+        assert_eq!(events[0].payload, [position_value(IVec3::new(2, 1, 0))]);
+    }
+
+    #[test]
+    fn reads_repetitions_by_parameter_name() {
+        // an api whose function takes another argument ahead of the number of steps
+        let mut api = robot_api();
+        let function = api
+            .functions
+            .iter_mut()
+            .find(|function| function.name.0 == "move forward")
+            .unwrap();
+        let mut slowly: ParameterDescriptor = function.parameters[0].clone();
+        slowly.name = Identifier("slowly".to_owned());
+        slowly.typ = TypeDescriptor::Boolean;
+        slowly.default = Some(Value::Boolean(false));
+        function.parameters.insert(0, slowly);
+
+        let mut world = World::new(&level("{ size: [4, 3], start: { position: [0, 1] } }"));
+        let command = command(
+            &api,
+            "move forward",
+            vec![Value::Boolean(true), Value::Integer(3)],
+        );
+        assert_eq!(world.process_command(&command).unwrap().len(), 3);
+        assert_eq!(world.position(), IVec3::new(3, 1, 0));
+    }
+
+    #[test]
+    fn answers_queries_without_changing_the_world() {
+        let api = robot_api();
+        let world = World::new(&level(
+            "{ size: [4, 3], start: { position: [1, 1] }, blocked: [[2, 1]] }",
+        ));
+        let answer = |name, arguments| world.query(&command(&api, name, arguments));
+
+        assert_eq!(
+            answer("position", vec![]).unwrap().unwrap(),
+            position_value(IVec3::new(1, 1, 0))
+        );
+        assert_eq!(
+            answer("orientation", vec![]).unwrap().unwrap(),
+            Value::Enum(Identifier("east".to_owned()))
+        );
+        let Value::Record(tile) = answer("tile ahead", vec![]).unwrap().unwrap() else {
+            panic!("a tile is a record");
+        };
+        assert_eq!(
+            tile[&Identifier("blocked".to_owned())],
+            Value::Boolean(true)
+        );
+        let outside = position_value(IVec3::new(4, 0, 0));
+        assert_eq!(
+            answer("line pattern", vec![outside])
+                .unwrap()
+                .unwrap_err()
+                .kind
+                .0,
+            "out of bounds"
+        );
+        assert!(answer("move forward", vec![]).is_none());
+    }
+}
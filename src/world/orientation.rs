@@ -0,0 +1,85 @@
+use std::{
+    f32::consts::TAU,
+    ops::{AddAssign, SubAssign},
+};
+
+use glam::IVec3;
+use serde::{Deserialize, Serialize};
+
+// TODO W.I.P.
+#[expect(
+    clippy::min_ident_chars,
+    reason = "the names of the directions are clear from the context"
+)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum Orientation {
+    /// positive x
+    #[default]
+    E = 0,
+    /// +x, +y
+    NE = 1,
+    /// positive y
+    N = 2,
+    /// -x +y
+    NW = 3,
+    /// negative x
+    W = 4,
+    /// -x -y
+    SW = 5,
+    /// negative y
+    S = 6,
+    /// +x -y
+    SE = 7,
+}
+
+impl Orientation {
+    /// Returns the offset to the adjacent tile this orientation is facing.
+    #[must_use]
+    pub fn as_ivec3(self) -> IVec3 {
+        match self {
+            Orientation::E => IVec3::new(1, 0, 0),
+            Orientation::NE => IVec3::new(1, 1, 0),
+            Orientation::N => IVec3::new(0, 1, 0),
+            Orientation::NW => IVec3::new(-1, 1, 0),
+            Orientation::W => IVec3::new(-1, 0, 0),
+            Orientation::SW => IVec3::new(-1, -1, 0),
+            Orientation::S => IVec3::new(0, -1, 0),
+            Orientation::SE => IVec3::new(1, -1, 0),
+        }
+    }
+
+    /// Returns the counter-clockwise angle relative to the positive x-axis in radians.
+    #[must_use]
+    pub fn angle(self) -> f32 {
+        f32::from(self as u8) / 8.0 * TAU
+    }
+}
+
+impl From<u8> for Orientation {
+    fn from(value: u8) -> Self {
+        match value & 0x07 {
+            0 => Self::E,
+            1 => Self::NE,
+            2 => Self::N,
+            3 => Self::NW,
+            4 => Self::W,
+            5 => Self::SW,
+            6 => Self::S,
+            7 => Self::SE,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl AddAssign<u8> for Orientation {
+    fn add_assign(&mut self, rhs: u8) {
+        *self = (*self as u8).wrapping_add(rhs).into();
+    }
+}
+
+impl SubAssign<u8> for Orientation {
+    fn sub_assign(&mut self, rhs: u8) {
+        *self = (*self as u8).wrapping_sub(rhs).into();
+    }
+}
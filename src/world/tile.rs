@@ -0,0 +1,160 @@
+use std::ops::{self, Neg};
+
+use bytemuck::{Pod, Zeroable};
+use glam::IVec3;
+use serde::Serialize;
+
+use super::Orientation;
+
+/// State of a single tile of the world
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tile {
+    /// lines the robot has drawn onto this tile
+    pub line_pattern: LinePattern,
+    /// static properties as defined by the level
+    pub flags: TileFlags,
+}
+
+/// Static properties of a tile as defined by the level
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable, Default)]
+pub struct TileFlags(u32);
+
+impl TileFlags {
+    /// the tile cannot be entered
+    pub const BLOCKED: Self = Self(1 << 0);
+    /// the tile is a goal of the level
+    pub const GOAL: Self = Self(1 << 1);
+    /// there's a wall at the edge towards positive x
+    pub const WALL_E: Self = Self(1 << 4);
+    /// there's a wall at the edge towards positive y
+    pub const WALL_N: Self = Self(1 << 5);
+    /// there's a wall at the edge towards negative x
+    pub const WALL_W: Self = Self(1 << 6);
+    /// there's a wall at the edge towards negative y
+    pub const WALL_S: Self = Self(1 << 7);
+
+    #[must_use]
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the wall flag for the edge facing the given direction.
+    /// Returns `None` if the direction doesn't point to an edge-adjacent tile.
+    #[must_use]
+    pub fn wall(direction: IVec3) -> Option<Self> {
+        match (direction.x, direction.y) {
+            (1, 0) => Some(Self::WALL_E),
+            (0, 1) => Some(Self::WALL_N),
+            (-1, 0) => Some(Self::WALL_W),
+            (0, -1) => Some(Self::WALL_S),
+            _ => None,
+        }
+    }
+}
+
+impl ops::BitOrAssign for TileFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Bit set of all [`LineSegment`]s drawn onto a tile
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable, Default, Serialize)]
+pub struct LinePattern(u32);
+
+impl LinePattern {
+    /// Returns `true` if the given segment has been drawn.
+    #[must_use]
+    pub fn contains(self, segment: LineSegment) -> bool {
+        self.0 & 1 << segment as u32 != 0
+    }
+}
+
+impl ops::BitOrAssign<LineSegment> for LinePattern {
+    fn bitor_assign(&mut self, rhs: LineSegment) {
+        self.0 |= 1 << rhs as u32;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[expect(
+    clippy::min_ident_chars,
+    reason = "the names of the directions are clear from the context"
+)]
+pub enum LineSegment {
+    /// positive x
+    E = 0,
+    /// +x, +y
+    NE = 1,
+    /// positive y
+    N = 2,
+    /// -x +y
+    NW = 3,
+    /// negative x
+    W = 4,
+    /// -x -y
+    SW = 5,
+    /// negative y
+    S = 6,
+    /// +x -y
+    SE = 7,
+    /// +x, +y
+    NECorner = 9,
+    /// -x +y
+    NWCorner = 11,
+    /// -x -y
+    SWCorner = 13,
+    /// +x -y
+    SECorner = 15,
+}
+
+impl From<Orientation> for LineSegment {
+    fn from(value: Orientation) -> Self {
+        match value {
+            Orientation::E => Self::E,
+            Orientation::NE => Self::NE,
+            Orientation::N => Self::N,
+            Orientation::NW => Self::NW,
+            Orientation::W => Self::W,
+            Orientation::SW => Self::SW,
+            Orientation::S => Self::S,
+            Orientation::SE => Self::SE,
+        }
+    }
+}
+
+impl LineSegment {
+    #[must_use]
+    pub fn get_x_corner(self) -> Option<LineSegment> {
+        match self {
+            Self::NE => Some(Self::NWCorner),
+            Self::NW => Some(Self::NECorner),
+            Self::SW => Some(Self::SECorner),
+            Self::SE => Some(Self::SWCorner),
+            _ => None,
+        }
+    }
+}
+
+impl Neg for LineSegment {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        match self {
+            Self::E => Self::W,
+            Self::NE => Self::SW,
+            Self::N => Self::S,
+            Self::NW => Self::SE,
+            Self::W => Self::E,
+            Self::SW => Self::NE,
+            Self::S => Self::N,
+            Self::SE => Self::NW,
+            Self::NECorner => Self::SWCorner,
+            Self::NWCorner => Self::SECorner,
+            Self::SWCorner => Self::NECorner,
+            Self::SECorner => Self::NWCorner,
+        }
+    }
+}
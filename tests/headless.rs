@@ -0,0 +1,305 @@
+#![allow(
+    unused_crate_dependencies,
+    reason = "the dependencies are shared with the library"
+)]
+#![allow(missing_docs, reason = "tests are documented by their names")]
+#![allow(clippy::unwrap_used, reason = "failing tests may panic")]
+#![allow(clippy::expect_used, reason = "failing tests may panic")]
+#![allow(
+    clippy::tests_outside_test_module,
+    reason = "integration tests are a crate of their own"
+)]
+
+//! Runs scripts headless and checks their reports.
+
+use std::{
+    fs,
+    path::PathBuf,
+    sync::mpsc::channel,
+    thread,
+    time::{Duration, Instant},
+};
+
+use gam3du::{
+    api::Api,
+    command::event_channel,
+    headless::{self, Report},
+    level::Level,
+    monitor::{Activity, Monitor},
+    python::{self, Abort, CancelHandle, Limits, Sandbox, ScriptError, ScriptErrorKind},
+};
+
+/// Writes the script to a file of its own, as the runners read scripts from files.
+fn script_file(name: &str, source: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("gam3du-tests-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let path = directory.join(name);
+    fs::write(&path, source).unwrap();
+    path
+}
+
+fn limits() -> Limits {
+    Limits {
+        timeout: Some(Duration::from_secs(10)),
+        max_commands: None,
+    }
+}
+
+fn setup() -> (Level, Api) {
+    let level = Level::load("levels/demo.level.json5").unwrap();
+    let api = level
+        .restrict(&Api::load("apis/robot.api.json").unwrap())
+        .unwrap();
+    (level, api)
+}
+
+fn run_python(name: &str, source: &str) -> Report {
+    run_python_with(name, source, limits())
+}
+
+fn run_python_with(name: &str, source: &str, limits: Limits) -> Report {
+    let (level, api) = setup();
+    headless::run_python(
+        script_file(name, source),
+        &level,
+        &api,
+        limits,
+        Sandbox::student(),
+    )
+}
+
+fn run_lua(name: &str, source: &str, limits: Limits) -> Report {
+    let (level, api) = setup();
+    headless::run_lua(
+        script_file(name, source),
+        &level,
+        &api,
+        limits,
+        Sandbox::student(),
+    )
+}
+
+fn error(report: Report) -> ScriptError {
+    report.error.expect("the script should have failed")
+}
+
+#[test]
+fn reports_blocked_imports_at_their_location() {
+    let error = error(run_python(
+        "blocked_import.py",
+        "import math\n\nif math.pi > 3:\n    import os\n",
+    ));
+    assert_eq!(error.kind, ScriptErrorKind::Sandbox);
+    assert_eq!(error.line, Some(4));
+    assert_eq!(error.column, Some(5));
+    assert_eq!(error.source_line.as_deref(), Some("    import os"));
+    assert_eq!(
+        error.message,
+        "ImportError: module 'os' is not available in the sandbox"
+    );
+}
+
+#[test]
+fn reports_forbidden_names_at_their_location() {
+    let error = error(run_python(
+        "forbidden_name.py",
+        "def f():\n    pass\n\nprint(f.__globals__)\n",
+    ));
+    assert_eq!(error.kind, ScriptErrorKind::Sandbox);
+    assert_eq!(error.line, Some(4));
+    assert_eq!(error.source_line.as_deref(), Some("print(f.__globals__)"));
+}
+
+#[test]
+fn reports_forbidden_builtins_as_sandbox_violation() {
+    let error = error(run_python("forbidden_builtin.py", "x = 1\nopen('x')\n"));
+    assert_eq!(error.kind, ScriptErrorKind::Sandbox);
+    assert_eq!(error.line, Some(2));
+}
+
+#[test]
+fn runs_allowed_scripts() {
+    let report = run_python(
+        "allowed.py",
+        "import math\nfrom robot_api import move_forward\nmove_forward(2)\nprint(math.floor(2.5))\n",
+    );
+    assert!(report.error.is_none(), "{:?}", report.error);
+    assert_eq!(report.position, [7, 5]);
+    assert_eq!(report.command_count, 1);
+    assert_eq!(report.output, ["2"]);
+}
+
+#[test]
+fn times_out_endless_python_loops() {
+    let report = run_python_with(
+        "endless.py",
+        "while True:\n    pass\n",
+        Limits {
+            // leaves the interpreter enough time to start up, even in debug builds
+            timeout: Some(Duration::from_secs(2)),
+            max_commands: None,
+        },
+    );
+    assert!(report.timed_out);
+    assert!(!report.budget_exceeded);
+    let error = error(report);
+    assert_eq!(error.kind, ScriptErrorKind::Timeout);
+    assert!(error.line.is_some(), "{error:?}");
+    assert_eq!(
+        error.message,
+        "ScriptAborted: the script didn't finish within its time limit of 2s"
+    );
+}
+
+#[test]
+fn times_out_python_scripts_catching_exceptions() {
+    let report = run_python_with(
+        "catching.py",
+        "while True:\n    try:\n        while True:\n            pass\n    except Exception:\n        pass\n",
+        Limits {
+            timeout: Some(Duration::from_secs(2)),
+            max_commands: None,
+        },
+    );
+    assert!(report.timed_out);
+    let error = error(report);
+    assert_eq!(error.kind, ScriptErrorKind::Timeout);
+    // within the loop rather than while starting up
+    assert!(error.line.is_some(), "{error:?}");
+}
+
+#[test]
+fn aborts_python_scripts_exceeding_their_budget() {
+    let report = run_python_with(
+        "budget.py",
+        "from robot_api import turn_left\nwhile True:\n    turn_left()\n",
+        Limits {
+            timeout: Some(Duration::from_secs(10)),
+            max_commands: Some(3),
+        },
+    );
+    assert!(report.budget_exceeded);
+    assert!(!report.timed_out);
+    assert_eq!(report.command_count, 3);
+    let error = error(report);
+    assert_eq!(error.kind, ScriptErrorKind::Aborted);
+    assert_eq!(error.line, Some(3));
+}
+
+#[test]
+fn cancels_python_scripts() {
+    let (_level, api) = setup();
+    let path = script_file(
+        "cancelled.py",
+        "print('started', flush=True)\nwhile True:\n    pass\n",
+    );
+    let cancel = CancelHandle::new();
+    let monitor = Monitor::default();
+    let activities = monitor.subscribe();
+    let script = {
+        let cancel = cancel.clone();
+        thread::spawn(move || {
+            let (sender, _receiver) = channel();
+            let (_events, event_receiver) = event_channel(&api);
+            python::runner(
+                path,
+                sender,
+                event_receiver,
+                &api,
+                Limits::default(),
+                &cancel,
+                &Sandbox::student(),
+                &monitor,
+            )
+        })
+    };
+
+    // cancels the script within its loop
+    let started = activities.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert!(
+        matches!(started, Activity::Output { ref text, .. } if text == "started"),
+        "{started:?}"
+    );
+    let cancelled = Instant::now();
+    cancel.cancel();
+    let error = script
+        .join()
+        .unwrap()
+        .expect_err("the script should have been cancelled");
+    assert!(
+        cancelled.elapsed() < Duration::from_secs(5),
+        "the script took {:?} to stop",
+        cancelled.elapsed()
+    );
+    assert_eq!(cancel.reason(), Some(Abort::Cancelled));
+    assert_eq!(error.kind, ScriptErrorKind::Aborted);
+    assert!(error.line.is_some(), "{error:?}");
+}
+
+#[test]
+fn times_out_lua_scripts_catching_the_abort() {
+    let report = run_lua(
+        "pcall_timeout.lua",
+        "while true do\n  pcall(function() while true do end end)\nend\n",
+        Limits {
+            timeout: Some(Duration::from_millis(200)),
+            max_commands: None,
+        },
+    );
+    assert!(report.timed_out);
+    let error = error(report);
+    assert_eq!(error.kind, ScriptErrorKind::Timeout);
+    // rather than left behind by the runner
+    assert_eq!(error.line, Some(2));
+}
+
+#[test]
+fn aborts_lua_scripts_catching_the_exhausted_budget() {
+    let report = run_lua(
+        "pcall_budget.lua",
+        "while true do\n  pcall(function()\n    if not pcall(robot_api.turn_left) then\n      while true do end\n    end\n  end)\nend\n",
+        Limits {
+            timeout: Some(Duration::from_secs(2)),
+            max_commands: Some(3),
+        },
+    );
+    assert!(report.budget_exceeded);
+    assert_eq!(report.command_count, 3);
+    let error = error(report);
+    assert_eq!(error.kind, ScriptErrorKind::Aborted);
+    assert!(error.line.is_some(), "{error:?}");
+}
+
+#[test]
+fn collects_lua_output() {
+    let report = run_lua(
+        "output.lua",
+        "print(\"moving\", 2)\nrobot_api.move_forward(2)\n",
+        limits(),
+    );
+    assert!(report.error.is_none(), "{:?}", report.error);
+    assert_eq!(report.position, [7, 5]);
+    assert_eq!(report.output, ["moving\t2"]);
+}
+
+#[test]
+fn refuses_lua_bytecode_in_the_sandbox() {
+    let report = run_lua(
+        "bytecode.lua",
+        "print(string.dump)
+local chunk, message = load(\"\\27Lua\", \"bytecode\", \"b\")
+print(chunk, message)
+print(load(\"return 42\")())
+",
+        limits(),
+    );
+    assert!(report.error.is_none(), "{:?}", report.error);
+    assert_eq!(
+        report.output,
+        [
+            "nil",
+            "nil\tattempt to load a binary chunk (mode is 't')",
+            "42"
+        ]
+    );
+}
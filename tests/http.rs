@@ -0,0 +1,358 @@
+#![allow(
+    unused_crate_dependencies,
+    reason = "the dependencies are shared with the library"
+)]
+#![allow(missing_docs, reason = "tests are documented by their names")]
+#![allow(clippy::unwrap_used, reason = "failing tests may panic")]
+#![allow(clippy::expect_used, reason = "failing tests may panic")]
+#![allow(
+    clippy::tests_outside_test_module,
+    reason = "integration tests are a crate of their own"
+)]
+
+//! Talks to the HTTP control server over a real connection.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    sync::mpsc::channel,
+    thread,
+    time::Duration,
+};
+
+use gam3du::{
+    api::{Api, Identifier, Value},
+    bindgen::openapi::{self, DOCUMENT_PATH},
+    command::CommandResult,
+    http::{self, EVENTS_PATH},
+    level::Level,
+    monitor::{Activity, Monitor, OutputStream},
+    world::World,
+    Command,
+};
+use serde_json::{json, Value as Json};
+use tiny_http::Server;
+
+/// Starts a server on an ephemeral port and returns its address.
+///
+/// The commands are executed within the demo level, where the robot starts at `[5, 5]` facing
+/// east.
+fn start_server(monitor: &Monitor) -> String {
+    let mut world = World::new(&Level::load("levels/demo.level.json5").unwrap());
+    start_server_with(
+        Api::load("apis/robot.api.json").unwrap(),
+        monitor,
+        move |command| match world.query(command) {
+            Some(answer) => answer.map(Some),
+            None => world.process_command(command).map(|_actions| None),
+        },
+    )
+}
+
+/// Starts a server for the given api, whose commands get answered by the given engine.
+fn start_server_with(
+    api: Api,
+    monitor: &Monitor,
+    mut engine: impl FnMut(&Command) -> CommandResult + Send + 'static,
+) -> String {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let address = server.server_addr().to_ip().unwrap().to_string();
+    let monitor = monitor.clone();
+    let (command_sender, command_receiver) = channel::<Command>();
+    thread::spawn(move || http::serve(&server, &command_sender, &api, &monitor));
+    thread::spawn(move || {
+        for command in command_receiver {
+            let result = engine(&command);
+            command.reply.send(result);
+        }
+    });
+    address
+}
+
+/// Connects to the event stream and returns it once the response headers have arrived.
+fn subscribe(address: &str) -> BufReader<TcpStream> {
+    let mut connection = TcpStream::connect(address).unwrap();
+    connection
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    write!(
+        connection,
+        "GET {EVENTS_PATH} HTTP/1.1\r\nHost: {address}\r\nAccept: text/event-stream\r\n\r\n"
+    )
+    .unwrap();
+
+    let mut stream = BufReader::new(connection);
+    let status = read_line(&mut stream);
+    assert_eq!(
+        status, "HTTP/1.1 200 OK",
+        "the server should accept the subscription"
+    );
+    let mut headers = Vec::new();
+    loop {
+        let header = read_line(&mut stream);
+        if header.is_empty() {
+            break;
+        }
+        headers.push(header);
+    }
+    assert!(
+        headers.contains(&"Content-Type: text/event-stream".to_owned()),
+        "{headers:?}"
+    );
+    stream
+}
+
+/// Sends a request with the given body and returns the status and json body of the response.
+fn request(address: &str, method: &str, path: &str, body: &str) -> (u16, Json) {
+    let mut connection = TcpStream::connect(address).unwrap();
+    connection
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    write!(
+        connection,
+        "{method} {path} HTTP/1.1\r\nHost: {address}\r\nContent-Length: {length}\r\n\
+        Connection: close\r\n\r\n{body}",
+        length = body.len()
+    )
+    .unwrap();
+
+    let mut response = String::new();
+    connection.read_to_string(&mut response).unwrap();
+    let (head, content) = response
+        .split_once("\r\n\r\n")
+        .expect("the response should have a body");
+    let status = head
+        .split(' ')
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .expect(head);
+    assert!(
+        head.contains("Content-Type: application/json"),
+        "the response should be json: {head}"
+    );
+    (status, serde_json::from_str(content).expect(content))
+}
+
+fn read_line(stream: &mut BufReader<TcpStream>) -> String {
+    let mut line = String::new();
+    stream
+        .read_line(&mut line)
+        .expect("the stream should go on");
+    line.trim_end_matches(['\r', '\n']).to_owned()
+}
+
+/// Reads the next event and returns its name and data.
+fn next_event(stream: &mut BufReader<TcpStream>) -> (String, Json) {
+    let event = read_line(stream);
+    let data = read_line(stream);
+    assert_eq!(read_line(stream), "", "events end with an empty line");
+    let event = event.strip_prefix("event: ").expect(&event).to_owned();
+    let data = data.strip_prefix("data: ").expect(&data);
+    (event, serde_json::from_str(data).unwrap())
+}
+
+#[test]
+fn streams_published_activity() {
+    let monitor = Monitor::default();
+    let address = start_server(&monitor);
+    // the server subscribes before sending the headers, so nothing published from now on is lost
+    let mut stream = subscribe(&address);
+
+    monitor.publish(&Activity::AnimationCompleted {
+        function: Identifier("move forward".to_owned()),
+    });
+    monitor.publish(&Activity::Output {
+        stream: OutputStream::Stdout,
+        text: "hello".to_owned(),
+    });
+
+    assert_eq!(
+        next_event(&mut stream),
+        (
+            "animation_completed".to_owned(),
+            json!({ "type": "animation_completed", "function": "move forward" })
+        )
+    );
+    assert_eq!(
+        next_event(&mut stream),
+        (
+            "output".to_owned(),
+            json!({ "type": "output", "stream": "stdout", "text": "hello" })
+        )
+    );
+}
+
+#[test]
+fn streams_to_every_client() {
+    let monitor = Monitor::default();
+    let address = start_server(&monitor);
+    let mut first = subscribe(&address);
+    let mut second = subscribe(&address);
+
+    monitor.publish(&Activity::AnimationCompleted {
+        function: Identifier("turn left".to_owned()),
+    });
+
+    for stream in [&mut first, &mut second] {
+        let (event, data) = next_event(stream);
+        assert_eq!(event, "animation_completed");
+        assert_eq!(
+            data.get("function"),
+            Some(&json!("turn left")),
+            "every client should receive the event: {data}"
+        );
+    }
+}
+
+#[test]
+fn serves_the_openapi_document() {
+    let address = start_server(&Monitor::default());
+    let (status, document) = request(&address, "GET", DOCUMENT_PATH, "");
+    assert_eq!(status, 200, "{document}");
+    let api = Api::load("apis/robot.api.json").unwrap();
+    assert_eq!(document, openapi::document(&api));
+}
+
+#[test]
+fn replies_with_the_result() {
+    let address = start_server(&Monitor::default());
+    assert_eq!(
+        request(&address, "POST", "/robot/move%20forward", r#"{"steps": 2}"#),
+        (200, json!({ "result": null }))
+    );
+    // an empty body passes no arguments at all
+    assert_eq!(
+        request(&address, "POST", "/robot/position", ""),
+        (200, json!({ "result": { "x": 7, "y": 5 } }))
+    );
+}
+
+#[test]
+fn ignores_query_strings() {
+    let address = start_server(&Monitor::default());
+    assert_eq!(
+        request(&address, "POST", "/robot/turn%20left?x=1", ""),
+        (200, json!({ "result": null }))
+    );
+}
+
+/// Asserts that the request failed with the given status and kind of error.
+fn assert_failure(response: (u16, Json), status: u16, kind: &str) {
+    let (actual_status, body) = response;
+    assert_eq!(actual_status, status, "{body}");
+    assert_eq!(
+        body.pointer("/error/kind").and_then(Json::as_str),
+        Some(kind),
+        "{body}"
+    );
+    assert!(
+        body.pointer("/error/message").is_some_and(Json::is_string),
+        "the error should explain itself: {body}"
+    );
+}
+
+#[test]
+fn reports_unknown_functions() {
+    let address = start_server(&Monitor::default());
+    assert_failure(
+        request(&address, "POST", "/robot/fly", ""),
+        404,
+        "unknown function",
+    );
+    assert_failure(
+        request(&address, "POST", "/plane/move%20forward", ""),
+        404,
+        "unknown api",
+    );
+}
+
+#[test]
+fn requires_post() {
+    let address = start_server(&Monitor::default());
+    assert_failure(
+        request(&address, "GET", "/robot/move%20forward", ""),
+        405,
+        "method not allowed",
+    );
+}
+
+#[test]
+fn reports_invalid_arguments() {
+    let address = start_server(&Monitor::default());
+    let call = |body: &str| request(&address, "POST", "/robot/move%20forward", body);
+    assert_failure(call("{"), 400, "malformed request");
+    assert_failure(call("[2]"), 422, "invalid arguments");
+    assert_failure(call(r#"{"steps": "two"}"#), 422, "invalid arguments");
+    assert_failure(call(r#"{"steps": 0}"#), 422, "invalid arguments");
+    assert_failure(call(r#"{"speed": 2}"#), 422, "invalid arguments");
+    assert_failure(
+        request(&address, "POST", "/robot/line%20pattern", "{}"),
+        422,
+        "invalid arguments",
+    );
+}
+
+#[test]
+fn reports_refused_commands() {
+    let address = start_server(&Monitor::default());
+    // the level ends after four more tiles
+    assert_failure(
+        request(&address, "POST", "/robot/move%20forward", r#"{"steps": 5}"#),
+        409,
+        "out of bounds",
+    );
+}
+
+#[test]
+fn replies_with_the_state() {
+    let address = start_server(&Monitor::default());
+    let (status, state) = request(&address, "GET", "/robot/state", "");
+    assert_eq!(status, 200, "{state}");
+    assert_eq!(
+        state.pointer("/position"),
+        Some(&json!({ "x": 5, "y": 5 })),
+        "{state}"
+    );
+    assert_eq!(
+        state.pointer("/orientation"),
+        Some(&json!("east")),
+        "{state}"
+    );
+    // all read-only functions without parameters and nothing else
+    let mut keys: Vec<&str> = state
+        .as_object()
+        .expect("the state should be an object")
+        .keys()
+        .map(String::as_str)
+        .collect();
+    keys.sort_unstable();
+    assert_eq!(keys, ["orientation", "position", "tile ahead"]);
+    assert_eq!(
+        state.pointer("/tile ahead/position"),
+        Some(&json!({ "x": 6, "y": 5 })),
+        "{state}"
+    );
+}
+
+#[test]
+fn calls_functions_named_state_via_post() {
+    let mut api = Api::load("apis/robot.api.json").unwrap();
+    let mut function = api.function("orientation").unwrap().clone();
+    function.name = Identifier("state".to_owned());
+    function.read_only = false;
+    api.functions.push(function);
+    api.validate().unwrap();
+    // replies with the name of the function being called
+    let address = start_server_with(api, &Monitor::default(), |command| {
+        Ok(Some(Value::Enum(command.name.clone())))
+    });
+
+    // the state of the engine doesn't include the function, as it isn't read-only
+    let (status, state) = request(&address, "GET", "/robot/state", "");
+    assert_eq!(status, 200, "{state}");
+    assert_eq!(state.pointer("/state"), None, "{state}");
+    assert_eq!(
+        request(&address, "POST", "/robot/state", ""),
+        (200, json!({ "result": "state" }))
+    );
+}